@@ -1,13 +1,33 @@
 use crate::binary::{Binary, Fmt};
 use crate::config::Config;
-use crate::segoff::{Seg, SegOff};
-use crate::util::range_set::RangeSet;
+use crate::segoff::{Seg, Off, SegOff};
 
 use super::workqueue::WorkQueue;
 use super::code_segment::{CodeSegments};
 use super::func_details::{FuncDetails, ReturnKind};
-
-use std::collections::{BTreeMap, HashSet};
+use super::byte_map::{ByteMap, ByteKind};
+use super::call_graph::CallGraph;
+use super::indirect_call;
+use super::arg_infer::{self, ArgsEvidence, ArgsInference};
+use super::ret_infer::{self, RetEvidence, RetInference};
+use super::regarg_infer::{self, RegargEvidence};
+use super::reg_usage::RegSet;
+use super::signature::{self, Signature};
+use super::xref::XrefDb;
+use super::global_infer;
+use super::strings;
+use super::lint;
+use super::coverage::{self, Coverage};
+use super::map_import;
+use crate::binfmt::map::MapFile;
+use crate::binfmt::tdinfo::{self, TdInfo};
+use crate::config::CallMode;
+use crate::types::Type;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// Size of a Borland overlay stub: int 3Fh, a u16 destination offset and a padding byte
+const OVERLAY_STUB_SIZE: u32 = 5;
 
 pub struct Analyze {
   cfg: Config,
@@ -19,6 +39,10 @@ impl Analyze {
   pub fn new(cfg: &Config, exe_path: &str) -> Self {
     let fmt = Fmt::Exe(exe_path.to_string());
     let binary = Binary::from_fmt(&fmt, Some(cfg)).unwrap();
+    Self::from_binary(cfg, binary)
+  }
+
+  pub fn from_binary(cfg: &Config, binary: Binary) -> Self {
     let code_segments = CodeSegments::from_binary(&binary);
 
     Self {
@@ -28,29 +52,23 @@ impl Analyze {
     }
   }
 
+  pub fn into_binary(self) -> Binary {
+    self.binary
+  }
+
   pub fn dump_info(&self) {
     self.binary.exe().unwrap().print();
   }
 
-  pub fn analyze_code_segment(&self, seg: Seg) -> (u32, u32) {
+  pub fn analyze_code_segment(&self, seg: Seg, byte_map: Option<&ByteMap>) -> (u32, u32) {
     let code_seg = self.code_segments.find_by_segment(seg).unwrap();
 
-    let mut r = RangeSet::new();
-
-    // Add all ranges implied by function config
     for f in &self.cfg.funcs {
-      if f.start.seg != code_seg.primary.seg { continue };
-      let Some(end) = &f.end else {
+      if f.start.seg == code_seg.primary.seg && f.end.is_none() {
         println!("Unknown end address for {}", f.name);
-        continue;
-      };
-      r.insert(f.start.off.0 as u32, end.off.0 as u32);
-    }
-
-    // Add all ranges implied by the text section data
-    for t in self.cfg.text_regions_matching_segment(code_seg.primary.seg) {
-      r.insert(t.start.off.0 as u32, t.end.off.0 as u32);
+      }
     }
+    let r = coverage::annotated_ranges(&self.cfg, code_seg.primary.seg);
 
     let seg_start = code_seg.primary.skip_off;
     let seg_end = seg_start + code_seg.primary.size;
@@ -78,14 +96,19 @@ impl Analyze {
     if gaps.len() > 0 {
       println!("Gaps:");
       for gap in &gaps {
-        println!("   [ 0x{:04x}, 0x{:04x} )   size: {}", gap.start, gap.end, gap.end - gap.start);
+        print!("   [ 0x{:04x}, 0x{:04x} )   size: {}", gap.start, gap.end, gap.end - gap.start);
+        if let Some(byte_map) = byte_map {
+          let counts = byte_map.counts(code_seg.primary.seg, gap.start, gap.end);
+          print!("   code: {}  data: {}  unknown: {}", counts.code, counts.data, counts.unknown);
+        }
+        println!();
       }
     }
 
     (total_gap, total_size)
   }
 
-  pub fn analyze_code_segments_and_report(&self) {
+  pub fn analyze_code_segments_and_report(&self, byte_map: Option<&ByteMap>) {
     //self.code_segments.dump();
     let mut total_gap = 0;
     let mut total_size = 0;
@@ -93,7 +116,7 @@ impl Analyze {
       let seg = c.primary.seg;
       println!("Segment {}", seg);
       println!("===============================");
-      let (gap, size) = self.analyze_code_segment(seg);
+      let (gap, size) = self.analyze_code_segment(seg, byte_map);
       total_gap += gap;
       total_size += size;
       println!("");
@@ -120,7 +143,7 @@ impl Analyze {
   }
 
   // Scan known functions to find new functions, then scan those, return a big list of all found functions
-  pub fn scan_for_all_functions(&self, emit_annotation_format: bool) {
+  pub fn scan_functions(&self) -> BTreeMap<SegOff, Result<FuncDetails, String>> {
     let mut workqueue = WorkQueue::new();
 
     // init work queue with known config functions
@@ -132,24 +155,305 @@ impl Analyze {
     while let Some(addr) = workqueue.pop() {
       let result = self.analyze_function_by_start(addr);
 
-      // Add all new calls to the work queue
+      // Add all new calls to the work queue, including those through tables we can resolve
       if let Ok(details) = &result {
         for call in &details.direct_calls {
           workqueue.insert(*call);
         }
+        for call in indirect_call::resolve(details, &self.cfg, &self.binary, &self.code_segments) {
+          for target in call.targets {
+            workqueue.insert(target);
+          }
+        }
       }
 
       functions.insert(addr, result);
     }
 
+    functions
+  }
+
+  pub fn scan_for_all_functions(&self, emit_annotation_format: bool) {
+    let functions = self.scan_functions();
     if emit_annotation_format {
       // Synthesize annotations
-      generate_annotations(&functions, &self.cfg);
+      generate_annotations(&functions, &self.cfg, &self.resolved(&functions));
     } else {
       // Print out a report
       dump_functions(&functions, &self.cfg);
     }
   }
+
+  // The indirect calls in each function that resolve through a known table
+  fn resolved(&self, functions: &BTreeMap<SegOff, Result<FuncDetails, String>>) -> Inferred {
+    let mut resolved_calls = BTreeMap::new();
+    for (addr, details) in functions.iter().filter_map(|(addr, r)| Some((addr, r.as_ref().ok()?))) {
+      let n = indirect_call::resolve(details, &self.cfg, &self.binary, &self.code_segments).len();
+      if n > 0 { resolved_calls.insert(*addr, n); }
+    }
+    Inferred { resolved_calls, ..Default::default() }
+  }
+
+  // Like scan_for_all_functions(true), but with args inferred from "ret N" and caller stack cleanup
+  pub fn infer_args_and_report(&self) {
+    let functions = self.scan_functions();
+    let evidence = arg_infer::gather(&functions, &self.cfg);
+    generate_annotations(&functions, &self.cfg, &Inferred { args: Some(evidence), ..self.resolved(&functions) });
+  }
+
+  // Like scan_for_all_functions(true), but with return types inferred from how callers use DX:AX
+  pub fn infer_ret_and_report(&self) {
+    let functions = self.scan_functions();
+    let evidence = ret_infer::gather(&functions, &self.cfg);
+    generate_annotations(&functions, &self.cfg, &Inferred { ret: Some(evidence), ..self.resolved(&functions) });
+  }
+
+  // Like scan_for_all_functions(true), but with regargs inferred from registers read before written at entry
+  pub fn infer_regargs_and_report(&self) {
+    let functions = self.scan_functions();
+    let evidence = regarg_infer::gather(&functions, &self.cfg);
+    generate_annotations(&functions, &self.cfg, &Inferred { regargs: Some(evidence), ..self.resolved(&functions) });
+  }
+
+  // Return types for functions where the inference is unambiguous, keyed by function start
+  pub fn infer_ret_types(&self) -> BTreeMap<SegOff, Type> {
+    let functions = self.scan_functions();
+    ret_infer::gather(&functions, &self.cfg).into_iter()
+      .filter_map(|(addr, ev)| match ev.infer() {
+        RetInference::Type(typ) => Some((addr, typ)),
+        _ => None,
+      })
+      .collect()
+  }
+
+  // Scan every code segment for known library functions and emit annotations for the matches
+  pub fn match_signatures_and_report(&self, sigs: &[Signature]) {
+    for c in &self.code_segments.0 {
+      let r = &c.primary;
+      let end = (r.skip_off + r.size).min(0xffff);
+      let start = SegOff { seg: r.seg, off: Off(r.skip_off as u16) };
+      let matches = signature::scan(&self.binary, start, SegOff { seg: r.seg, off: Off(end as u16) }, sigs);
+      if matches.is_empty() { continue; }
+
+      let seg_name = match self.cfg.code_seg_lookup(r.seg) {
+        Some(cs) => cs.name.clone(),
+        None => format!("_{}", r.seg),
+      };
+      println!();
+      println!("    ## Section {}: {}", r.seg, seg_name);
+
+      for m in matches {
+        let sig = m.sigs[0];
+        if m.sigs.len() > 1 {
+          let names: Vec<&str> = m.sigs.iter().map(|s| s.name.as_str()).collect();
+          println!("    # SIGNATURE AMBIGUOUS | {} | {}", m.start, names.join(", "));
+          continue;
+        }
+        if let Some(func) = self.cfg.func_lookup_by_start(m.start) {
+          if func.name != sig.name {
+            println!("    # SIGNATURE CONFLICT | {} | {} | matches '{}'", func.name, m.start, sig.name);
+          }
+          continue;
+        }
+        if let Some(func) = self.cfg.func_lookup_by_name(&sig.name) {
+          println!("    # SIGNATURE CONFLICT | {} | {} | name already used at {}", sig.name, m.start, func.start);
+          continue;
+        }
+
+        let name     = format!("\"{}\",", sig.name);
+        let ret_str  = match &sig.ret {
+          Some(ret) => format!("\"{}\",", ret),
+          None      => "None,".to_string(),
+        };
+        let args_str = match sig.args {
+          Some(args) => format!("{},", args),
+          None       => "None,".to_string(),
+        };
+        let flags    = if sig.mode == CallMode::Near { ", flags = \"NEAR\"" } else { "" };
+        println!("    F( {:<30} {:<7} {:<12} \"{}\", \"{}\"{} ),", name, ret_str, args_str, m.start, m.end(), flags);
+      }
+    }
+  }
+
+  pub fn xrefs(&self) -> XrefDb {
+    XrefDb::build(&self.scan_functions())
+  }
+
+  // Propose globals for every DS offset accessed directly that the config doesn't cover, as BSL
+  pub fn propose_globals_and_report(&self) {
+    let db = self.xrefs();
+    let cands = global_infer::propose(&db.unannotated_data(&self.cfg), &self.cfg);
+    let records: Vec<_> = cands.iter().filter_map(|c| Some((c, c.record.as_ref()?))).collect();
+    if !records.is_empty() {
+      println!("  structures {{");
+      for (c, r) in records {
+        println!("    {:<15} {{ size {} members {{", c.struct_name(), r.size);
+        for (off, typ) in &r.fields {
+          println!("      {:<20} {{ type {:<15} off 0x{:02x} }}", format!("f_{:02x}", off), typ.to_string(), off);
+        }
+        println!("    }}}}");
+      }
+      println!("  }}");
+    }
+    println!("  globals {{");
+    for c in cands {
+      println!("    {:30} {{ off 0x{:04x}  type {:20} }}", c.name(), c.off, c.type_name());
+    }
+    println!("  }}");
+  }
+
+  // Emit BSL for the segments and publics of a linker MAP file that the config doesn't have yet
+  pub fn import_map_and_report(&self, map: &MapFile) {
+    report_import(&map_import::build(map, &self.cfg, &self.binary));
+  }
+
+  // ... or from Turbo Debugger info, with struct types and locals
+  pub fn import_tdinfo_and_report(&self, info: &TdInfo) {
+    report_import(&map_import::build_tdinfo(info, &self.cfg, &self.binary));
+  }
+
+  pub fn tdinfo(&self) -> Option<TdInfo> {
+    tdinfo::find(self.binary.exe()?)
+  }
+
+  // Find the strings in the data segment and emit annotations for those not in the config yet.
+  // Strings pushed as call arguments are almost certainly real, the others are left commented out.
+  pub fn discover_strings_and_report(&self) {
+    let Some((ds_start, ds_end)) = self.binary.data_segment() else {
+      eprintln!("Error: Binary has no data segment");
+      return;
+    };
+    let pushed = strings::pushed_immediates(&self.scan_functions());
+
+    println!("    ## Strings in the data segment ({})", ds_start.seg);
+    for (off, text) in strings::scan(self.binary.region(ds_start, ds_end), ds_start.off.0) {
+      if self.cfg.global_lookup(off).is_some() { continue; }
+      let name = format!("\"s_{:04x}\",", off);
+      let typ  = format!("\"u8[{}]\",", text.len() + 1);
+      let literal = strings::c_literal(text);
+      match pushed.get(&off) {
+        Some(sites) => {
+          let sites: Vec<String> = sites.iter().map(|s| s.to_string()).collect();
+          println!("    Global( {:<30} {:<10} 0x{:04x} ),  # {} | pushed at {}", name, typ, off, literal, sites.join(", "));
+        }
+        None => {
+          println!("    # UNREFERENCED | Global( {:<30} {:<10} 0x{:04x} ),  # {}", name, typ, off, literal);
+        }
+      }
+    }
+  }
+
+  // Report every inconsistency between the config and the binary, returns the number of problems
+  pub fn lint_and_report(&self) -> usize {
+    let mut problems = lint::lint_config(&self.cfg);
+    problems.extend(lint::lint_functions(&self.cfg, &self.scan_functions()));
+    for p in &problems {
+      println!("{}", p);
+    }
+    problems.len()
+  }
+
+  pub fn coverage(&self) -> Coverage {
+    coverage::build(&self.cfg, &self.code_segments, &self.binary)
+  }
+
+  pub fn call_graph(&self) -> CallGraph {
+    CallGraph::build(&self.scan_functions(), &self.cfg)
+  }
+
+  // Walk all code reachable from the program entry point and every overlay stub, and classify
+  // each byte of the code segments as code, data (known from the config) or unknown
+  pub fn discover_all_code(&self) -> (ByteMap, BTreeMap<SegOff, Result<FuncDetails, String>>) {
+    let exe = self.binary.exe().unwrap(); // FIXME
+    let mut byte_map = ByteMap::new(&self.code_segments);
+    let mut workqueue = WorkQueue::new();
+
+    // The entry point is relative to the load segment, just like our normal segments
+    let (cs, ip) = (exe.hdr.cs, exe.hdr.ip);
+    workqueue.insert(SegOff { seg: Seg::Normal(cs as u16), off: Off(ip) });
+
+    // Calls into overlays go through the stubs, so we follow them to their destination
+    let mut stub_dests = HashMap::new();
+    if let Some(ovr) = &exe.ovr {
+      for stub in &ovr.stubs {
+        stub_dests.insert(stub.stub_addr(), stub.dest_addr());
+        workqueue.insert(stub.dest_addr());
+        let start = stub.stub_offset as u32;
+        byte_map.mark_range(stub.stub_addr().seg, start, start + OVERLAY_STUB_SIZE, ByteKind::Code);
+      }
+    }
+
+    let mut functions = BTreeMap::new();
+    while let Some(addr) = workqueue.pop() {
+      let result = self.analyze_function_by_start(addr);
+
+      if let Ok(details) = &result {
+        for r in details.code.iter() {
+          byte_map.mark_range(addr.seg, r.start, r.end, ByteKind::Code);
+        }
+        for t in &details.jump_tables {
+          byte_map.mark_range(t.start.seg, t.start.off.0 as u32, t.end().off.0 as u32, ByteKind::Data);
+        }
+        for call in &details.direct_calls {
+          workqueue.insert(*stub_dests.get(call).unwrap_or(call));
+        }
+        for call in indirect_call::resolve(details, &self.cfg, &self.binary, &self.code_segments) {
+          for target in &call.targets {
+            workqueue.insert(*stub_dests.get(target).unwrap_or(target));
+          }
+        }
+      }
+
+      functions.insert(addr, result);
+    }
+
+    // Annotated text section regions and the overlay stub headers are data
+    for t in &self.cfg.text_section {
+      byte_map.mark_unknown_range(t.start.seg, t.start.off.0 as u32, t.end.off.0 as u32, ByteKind::Data);
+    }
+    for c in &self.code_segments.0 {
+      let Some(stub) = &c.stub else { continue };
+      byte_map.mark_unknown_range(stub.seg, stub.skip_off, stub.skip_off + stub.size, ByteKind::Data);
+    }
+
+    (byte_map, functions)
+  }
+
+  pub fn discover_all_code_and_report(&self) {
+    let (byte_map, functions) = self.discover_all_code();
+
+    println!("Discovered functions");
+    println!("===============================");
+    dump_functions(&functions, &self.cfg);
+    println!();
+
+    println!("Byte map");
+    println!("===============================");
+    for seg in byte_map.segments() {
+      let counts = byte_map.segment_counts(seg);
+      let perc = if counts.total() > 0 {
+        100.0 * (counts.code as f64) / (counts.total() as f64)
+      } else {
+        100.0
+      };
+      println!("Segment {:<15} code: {:>6}  data: {:>6}  unknown: {:>6}   ({:.2} % code)",
+               format!("{}", seg), counts.code, counts.data, counts.unknown, perc);
+      for run in byte_map.runs(seg) {
+        if run.kind == ByteKind::Code { continue; }
+        println!("   [ 0x{:04x}, 0x{:04x} )   size: {:<6} {}", run.start, run.end, run.end - run.start, run.kind);
+      }
+    }
+    println!();
+
+    self.analyze_code_segments_and_report(Some(&byte_map));
+  }
+}
+
+fn report_import(imp: &map_import::Import) {
+  for note in &imp.notes {
+    eprintln!("WARN: {}", note);
+  }
+  print!("{}", map_import::gen_bsl(imp).unwrap());
 }
 
 fn dump_functions(functions: &BTreeMap<SegOff, Result<FuncDetails, String>>, cfg: &Config) {
@@ -212,7 +516,16 @@ impl FunctionNames {
   }
 }
 
-fn generate_annotations(functions: &BTreeMap<SegOff, Result<FuncDetails, String>>, cfg: &Config) {
+// Inferred properties to fold into the generated annotations
+#[derive(Default)]
+struct Inferred {
+  args: Option<BTreeMap<SegOff, ArgsEvidence>>,
+  ret: Option<BTreeMap<SegOff, RetEvidence>>,
+  regargs: Option<BTreeMap<SegOff, RegargEvidence>>,
+  resolved_calls: BTreeMap<SegOff, usize>, // indirect calls with known targets, per function
+}
+
+fn generate_annotations(functions: &BTreeMap<SegOff, Result<FuncDetails, String>>, cfg: &Config, inferred: &Inferred) {
   let mut function_names = FunctionNames::from_cfg(cfg);
 
   let mut current_seg = None;
@@ -232,23 +545,76 @@ fn generate_annotations(functions: &BTreeMap<SegOff, Result<FuncDetails, String>
     }
 
 
-    let (name, ret, args) = match cfg.func_lookup(*addr) {
-      Some(func) => (func.name.clone(), func.ret.clone(), func.args),
-      None       => (function_names.compute_unique(&seg_name), None, None),
+    let (name, mut ret, mut args, mut regargs) = match cfg.func_lookup(*addr) {
+      Some(func) => (func.name.clone(), func.ret.clone(), func.args, func.regargs.as_ref().map(|r| RegSet::of(r))),
+      None       => (function_names.compute_unique(&seg_name), None, None, None),
     };
 
+    let evidence = inferred.args.as_ref().and_then(|e| e.get(addr));
+    if let Some(ev) = evidence {
+      match (ev.infer(), args) {
+        (ArgsInference::Args(n), None) => args = Some(n),
+        (ArgsInference::Args(n), Some(cfg_args)) if n != cfg_args => {
+          println!("    # ARGS CONFLICT | {} | {} | config: {}  inferred: {}", name, addr, cfg_args, n);
+        }
+        (ArgsInference::Conflict(msg), _) => {
+          println!("    # ARGS CONFLICT | {} | {} | {}", name, addr, msg);
+        }
+        _ => (),
+      }
+    }
+
+    if let Some(ev) = inferred.ret.as_ref().and_then(|e| e.get(addr)) {
+      match (ev.infer(), &ret) {
+        (RetInference::Type(typ), None) => ret = Some(typ),
+        (RetInference::Type(typ), Some(cfg_ret)) if ret_infer::ret_size(cfg_ret).is_some_and(|sz| Some(sz) != ret_infer::ret_size(&typ)) => {
+          println!("    # RET CONFLICT | {} | {} | config: {}  inferred: {}", name, addr, cfg_ret, typ);
+        }
+        (RetInference::Conflict(msg), _) => {
+          println!("    # RET CONFLICT | {} | {} | {}", name, addr, msg);
+        }
+        _ => (),
+      }
+    }
+
+    if let Some(ev) = inferred.regargs.as_ref().and_then(|e| e.get(addr)) {
+      let inferred_regs = ev.regs.widen();
+      match regargs {
+        None if !inferred_regs.is_empty() => {
+          regargs = Some(inferred_regs);
+          for (site, missing) in ev.unconfirmed() {
+            println!("    # REGARGS UNCONFIRMED | {} | {} | caller at {} doesn't set up {}", name, addr, site, missing);
+          }
+        }
+        Some(cfg_regs) if cfg_regs != inferred_regs => {
+          println!("    # REGARGS CONFLICT | {} | {} | config: {}  inferred: {}", name, addr, cfg_regs, inferred_regs);
+        }
+        _ => (),
+      }
+    }
+
     match result {
       Ok(details) => {
-        if details.indirect_calls > 0 {
+        let resolved = inferred.resolved_calls.get(addr).copied().unwrap_or(0);
+        let unresolved = details.indirect_calls.saturating_sub(resolved);
+        if unresolved > 0 {
           print!("    # IGNORED INDIRECT CALLS | {} | {} | ", name, addr);
           println!("start: {}  end: {}  indirect_calls: {}",
-                   details.start_addr, details.end_addr_inferred, details.indirect_calls);
+                   details.start_addr, details.end_addr_inferred, unresolved);
         } else {
+          // Only one flag fits, so a near function that pops its own args gets a note instead
+          let callee_pops = evidence.is_some_and(|ev| ev.callee_pops());
+          if details.return_kind == ReturnKind::Near && callee_pops {
+            println!("    # CALLEE POPS | {} | {}", name, addr);
+          }
+
           let name     = format!("\"{}\",", name);
           let start    = format!("\"{}\",", details.start_addr);
           let end      = format!("\"{}\"", details.end_addr_inferred);
           let flags    = if details.return_kind == ReturnKind::Near {
             ", flags = \"NEAR\""
+          } else if callee_pops {
+            ", flags = \"DONT_POP_ARGS\""
           } else {
             ""
           };
@@ -263,7 +629,23 @@ fn generate_annotations(functions: &BTreeMap<SegOff, Result<FuncDetails, String>
             None       => "None,".to_string(),
           };
 
-          println!("    F( {:<30} {:<7} {:<12} {} {}{} ),", name, ret_str, args_str, start, end, flags);
+          let regargs_str = match regargs {
+            Some(regs) if !regs.is_empty() => {
+              let regs: Vec<String> = regs.iter().map(|r| format!("\"{}\"", r.info().name)).collect();
+              format!(", regargs = [{}]", regs.join(", "))
+            }
+            _ => "".to_string(),
+          };
+
+          println!("    F( {:<30} {:<7} {:<12} {} {}{}{} ),", name, ret_str, args_str, start, end, flags, regargs_str);
+        }
+
+        // Recovered jump tables that the config doesn't know about yet
+        for t in &details.jump_tables {
+          if cfg.text_region_lookup(t.start, t.jmp_addr).is_some() { continue; }
+          let name = format!("\"{}_jmptbl_{:04x}\",", name, t.start.off.0);
+          let typ  = format!("\"u16[{}]\",", t.len);
+          println!("    TextData( {:<30} {:<10} \"{}\", \"{}\", access_at=\"{}\" ),", name, typ, t.start, t.end(), t.jmp_addr);
         }
       }
       Err(err) => {
@@ -0,0 +1,134 @@
+use super::func_details::FuncDetails;
+use crate::config::Config;
+use crate::segoff::SegOff;
+use std::collections::BTreeMap;
+
+// Everything observed about the stack bytes occupied by a function's arguments
+#[derive(Default)]
+pub struct ArgsEvidence {
+  pub ret_pops: BTreeMap<u16, Vec<SegOff>>, // callee-pops: bytes popped by each return => return sites
+  pub cleanups: BTreeMap<u16, Vec<SegOff>>, // caller-pops: bytes cleaned up after the call => call sites
+  pub unknown: Vec<SegOff>,                 // call sites without a recognizable cleanup
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArgsInference {
+  Unknown,          // never called, and doesn't pop anything itself
+  Args(u16),        // number of u16 args
+  Conflict(String),
+}
+
+impl ArgsEvidence {
+  // "ret N" with the same N != 0 at every return
+  fn ret_pop(&self) -> Option<u16> {
+    match self.ret_pops.keys().collect::<Vec<_>>()[..] {
+      [n] if *n != 0 => Some(*n),
+      _ => None,
+    }
+  }
+
+  pub fn callee_pops(&self) -> bool {
+    self.ret_pop().is_some()
+  }
+
+  pub fn infer(&self) -> ArgsInference {
+    if self.ret_pops.len() > 1 {
+      let all: Vec<String> = self.ret_pops.iter().map(|(bytes, sites)| format_sites(*bytes, sites)).collect();
+      return ArgsInference::Conflict(format!("returns disagree: {}", all.join(", ")));
+    }
+    if let Some(n) = self.ret_pop() {
+      if !n.is_multiple_of(2) {
+        return ArgsInference::Conflict(format!("odd 'ret {}'", n));
+      }
+      let popping: Vec<String> = self.cleanups.iter().map(|(bytes, sites)| format_sites(*bytes, sites)).collect();
+      if !popping.is_empty() {
+        return ArgsInference::Conflict(format!("callee pops {} bytes, but callers pop too: {}", n, popping.join(", ")));
+      }
+      return ArgsInference::Args(n / 2);
+    }
+
+    match self.cleanups.len() {
+      0 => ArgsInference::Unknown,
+      1 => {
+        let bytes = *self.cleanups.keys().next().unwrap();
+        if !bytes.is_multiple_of(2) {
+          return ArgsInference::Conflict(format!("odd cleanup of {} bytes", bytes));
+        }
+        ArgsInference::Args(bytes / 2)
+      }
+      _ => {
+        let all: Vec<String> = self.cleanups.iter().map(|(bytes, sites)| format_sites(*bytes, sites)).collect();
+        ArgsInference::Conflict(format!("callers disagree: {}", all.join(", ")))
+      }
+    }
+  }
+}
+
+fn format_sites(bytes: u16, sites: &[SegOff]) -> String {
+  let sites: Vec<String> = sites.iter().map(|s| s.to_string()).collect();
+  format!("{} bytes at [{}]", bytes, sites.join(", "))
+}
+
+// Collect the evidence for every function, keyed by function start
+pub fn gather(functions: &BTreeMap<SegOff, Result<FuncDetails, String>>, cfg: &Config) -> BTreeMap<SegOff, ArgsEvidence> {
+  // Calls into overlays target the stub, which the config knows as the function's entry
+  let resolve = |addr: SegOff| cfg.func_lookup(addr).map(|f| f.start).unwrap_or(addr);
+
+  let mut evidence: BTreeMap<SegOff, ArgsEvidence> = BTreeMap::new();
+  for (addr, result) in functions {
+    let Ok(details) = result else { continue };
+    if resolve(*addr) != *addr { continue; }
+    evidence.entry(*addr).or_default().ret_pops = details.ret_pops.clone();
+    for site in &details.call_sites {
+      let ev = evidence.entry(resolve(site.target)).or_default();
+      match site.cleanup {
+        Some(bytes) => ev.cleanups.entry(bytes).or_default().push(site.addr),
+        None => ev.unknown.push(site.addr),
+      }
+    }
+  }
+  evidence
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Returns are at 0x1000 + bytes popped, unknown cleanups are None
+  fn evidence(ret_pops: &[u16], cleanups: &[(Option<u16>, u16)]) -> ArgsEvidence {
+    let mut ev = ArgsEvidence::default();
+    for bytes in ret_pops {
+      ev.ret_pops.entry(*bytes).or_default().push(SegOff::new(0x10, 0x1000 + *bytes));
+    }
+    for (bytes, site) in cleanups {
+      match bytes {
+        Some(bytes) => ev.cleanups.entry(*bytes).or_default().push(SegOff::new(0x10, *site)),
+        None => ev.unknown.push(SegOff::new(0x10, *site)),
+      }
+    }
+    ev
+  }
+
+  #[test]
+  fn callee_pops() {
+    assert_eq!(evidence(&[6], &[(None, 0x10), (None, 0x20)]).infer(), ArgsInference::Args(3));
+    assert_eq!(evidence(&[6, 6], &[]).infer(), ArgsInference::Args(3));
+    assert!(matches!(evidence(&[6], &[(Some(6), 0x10)]).infer(), ArgsInference::Conflict(_)));
+    assert!(matches!(evidence(&[3], &[]).infer(), ArgsInference::Conflict(_)));
+
+    let ArgsInference::Conflict(msg) = evidence(&[0, 4], &[]).infer() else { panic!() };
+    assert_eq!(msg, "returns disagree: 0 bytes at [0010:1000], 4 bytes at [0010:1004]");
+  }
+
+  #[test]
+  fn caller_pops() {
+    assert_eq!(evidence(&[], &[]).infer(), ArgsInference::Unknown);
+    assert_eq!(evidence(&[0], &[(Some(4), 0x10), (Some(4), 0x20)]).infer(), ArgsInference::Args(2));
+    // Cleanup that's deferred or missing says nothing either way
+    assert_eq!(evidence(&[0], &[(None, 0x10)]).infer(), ArgsInference::Unknown);
+    assert_eq!(evidence(&[0], &[(Some(4), 0x10), (None, 0x20)]).infer(), ArgsInference::Args(2));
+
+    let ArgsInference::Conflict(msg) = evidence(&[0], &[(Some(4), 0x10), (Some(8), 0x20)]).infer() else { panic!() };
+    assert_eq!(msg, "callers disagree: 4 bytes at [0010:0010], 8 bytes at [0010:0020]");
+  }
+}
@@ -0,0 +1,169 @@
+use super::code_segment::{CodeSegments, Region};
+use crate::segoff::{Seg, SegOff};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteKind {
+  Unknown,
+  Code,
+  Data,
+}
+
+impl fmt::Display for ByteKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ByteKind::Unknown => write!(f, "unknown"),
+      ByteKind::Code    => write!(f, "code"),
+      ByteKind::Data    => write!(f, "data"),
+    }
+  }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ByteCounts {
+  pub code: u32,
+  pub data: u32,
+  pub unknown: u32,
+}
+
+impl ByteCounts {
+  pub fn total(&self) -> u32 {
+    self.code + self.data + self.unknown
+  }
+}
+
+// A run of bytes with the same classification: [start, end)
+pub struct Run {
+  pub start: u32,
+  pub end: u32,
+  pub kind: ByteKind,
+}
+
+struct SegmentBytes {
+  skip_off: u32, // offset of kinds[0] within the segment
+  kinds: Vec<ByteKind>,
+}
+
+// Classification of every byte of every code segment (and overlay stub segment)
+pub struct ByteMap(BTreeMap<Seg, SegmentBytes>);
+
+impl ByteMap {
+  pub fn new(code_segments: &CodeSegments) -> ByteMap {
+    let mut segs = BTreeMap::new();
+    let mut add = |r: &Region| {
+      segs.insert(r.seg, SegmentBytes { skip_off: r.skip_off, kinds: vec![ByteKind::Unknown; r.size as usize] });
+    };
+    for c in &code_segments.0 {
+      add(&c.primary);
+      if let Some(stub) = &c.stub { add(stub); }
+    }
+    ByteMap(segs)
+  }
+
+  // Classify [start, end), ignoring anything outside the known segments
+  pub fn mark_range(&mut self, seg: Seg, start: u32, end: u32, kind: ByteKind) {
+    let Some(s) = self.0.get_mut(&seg) else { return };
+    let seg_end = s.skip_off + s.kinds.len() as u32;
+    let start = start.clamp(s.skip_off, seg_end);
+    let end = end.clamp(start, seg_end);
+    for k in &mut s.kinds[(start - s.skip_off) as usize .. (end - s.skip_off) as usize] {
+      *k = kind;
+    }
+  }
+
+  // Like mark_range(), but only for bytes that aren't already classified
+  pub fn mark_unknown_range(&mut self, seg: Seg, start: u32, end: u32, kind: ByteKind) {
+    let Some(s) = self.0.get_mut(&seg) else { return };
+    let seg_end = s.skip_off + s.kinds.len() as u32;
+    let start = start.clamp(s.skip_off, seg_end);
+    let end = end.clamp(start, seg_end);
+    for k in &mut s.kinds[(start - s.skip_off) as usize .. (end - s.skip_off) as usize] {
+      if *k == ByteKind::Unknown { *k = kind; }
+    }
+  }
+
+  pub fn get(&self, addr: SegOff) -> Option<ByteKind> {
+    let s = self.0.get(&addr.seg)?;
+    let idx = (addr.off.0 as u32).checked_sub(s.skip_off)?;
+    s.kinds.get(idx as usize).copied()
+  }
+
+  pub fn segments(&self) -> impl Iterator<Item=Seg> + '_ {
+    self.0.keys().copied()
+  }
+
+  // Counts over [start, end) of a segment (clamped to the segment)
+  pub fn counts(&self, seg: Seg, start: u32, end: u32) -> ByteCounts {
+    let mut counts = ByteCounts::default();
+    for run in self.runs(seg) {
+      let n = run.end.min(end).saturating_sub(run.start.max(start));
+      match run.kind {
+        ByteKind::Code    => counts.code += n,
+        ByteKind::Data    => counts.data += n,
+        ByteKind::Unknown => counts.unknown += n,
+      }
+    }
+    counts
+  }
+
+  pub fn segment_counts(&self, seg: Seg) -> ByteCounts {
+    self.counts(seg, 0, u32::MAX)
+  }
+
+  pub fn runs(&self, seg: Seg) -> Vec<Run> {
+    let mut runs: Vec<Run> = vec![];
+    let Some(s) = self.0.get(&seg) else { return runs };
+    for (i, kind) in s.kinds.iter().enumerate() {
+      let off = s.skip_off + i as u32;
+      match runs.last_mut() {
+        Some(last) if last.kind == *kind => last.end = off + 1,
+        _ => runs.push(Run { start: off, end: off + 1, kind: *kind }),
+      }
+    }
+    runs
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::analyze::code_segment::CodeSegment;
+  use crate::segoff::Off;
+
+  fn byte_map() -> ByteMap {
+    let primary = Region { seg: Seg::Normal(0x100), skip_off: 0x10, size: 0x20 };
+    ByteMap::new(&CodeSegments(vec![CodeSegment { primary, stub: None }]))
+  }
+
+  #[test]
+  fn mark_and_count() {
+    let seg = Seg::Normal(0x100);
+    let mut m = byte_map();
+    m.mark_range(seg, 0x10, 0x18, ByteKind::Code);
+    m.mark_unknown_range(seg, 0x14, 0x20, ByteKind::Data);
+
+    let counts = m.segment_counts(seg);
+    assert_eq!((counts.code, counts.data, counts.unknown), (8, 8, 16));
+
+    let counts = m.counts(seg, 0x16, 0x1a);
+    assert_eq!((counts.code, counts.data, counts.unknown), (2, 2, 0));
+
+    let runs: Vec<_> = m.runs(seg).iter().map(|r| (r.start, r.end, r.kind)).collect();
+    assert_eq!(runs, vec![(0x10, 0x18, ByteKind::Code), (0x18, 0x20, ByteKind::Data), (0x20, 0x30, ByteKind::Unknown)]);
+  }
+
+  #[test]
+  fn mark_outside_segment_ignored() {
+    let seg = Seg::Normal(0x100);
+    let mut m = byte_map();
+    m.mark_range(seg, 0x0, 0x12, ByteKind::Code);
+    m.mark_range(seg, 0x2e, 0x40, ByteKind::Code);
+    m.mark_range(Seg::Normal(0x200), 0x10, 0x30, ByteKind::Code);
+
+    assert_eq!(m.segment_counts(seg).code, 4);
+    assert_eq!(m.get(SegOff { seg, off: Off(0x11) }), Some(ByteKind::Code));
+    assert_eq!(m.get(SegOff { seg, off: Off(0x12) }), Some(ByteKind::Unknown));
+    assert_eq!(m.get(SegOff { seg, off: Off(0x0f) }), None);
+  }
+}
@@ -0,0 +1,173 @@
+use crate::config::Config;
+use crate::segoff::{Seg, SegOff};
+use super::func_details::FuncDetails;
+use crate::util::json::json_str;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Write;
+
+pub struct Node {
+  pub addr: SegOff,
+  pub name: String,
+  pub indirect_calls: usize,
+  pub error: Option<String>,
+}
+
+pub struct CallGraph {
+  pub nodes: BTreeMap<SegOff, Node>,
+  pub edges: BTreeSet<(SegOff, SegOff)>, // (caller, callee)
+}
+
+impl CallGraph {
+  pub fn build(functions: &BTreeMap<SegOff, Result<FuncDetails, String>>, cfg: &Config) -> CallGraph {
+    // Calls into overlays target the stub, which the config knows as the function's entry
+    let resolve = |addr: SegOff| cfg.func_lookup(addr).map(|f| f.start).unwrap_or(addr);
+
+    let mut nodes = BTreeMap::new();
+    let mut edges = BTreeSet::new();
+    for (addr, result) in functions {
+      // Entries reached through a stub are analyzed again from the function start
+      if resolve(*addr) != *addr { continue; }
+      let addr = *addr;
+      let name = match cfg.func_lookup_by_start(addr) {
+        Some(func) => func.name.clone(),
+        None => format!("UNKNOWN_{}", addr),
+      };
+      let (indirect_calls, error) = match result {
+        Ok(details) => (details.indirect_calls, None),
+        Err(err) => (0, Some(err.clone())),
+      };
+      nodes.insert(addr, Node { addr, name, indirect_calls, error });
+
+      let Ok(details) = result else { continue };
+      for call in &details.direct_calls {
+        edges.insert((addr, resolve(*call)));
+      }
+    }
+
+    CallGraph { nodes, edges }
+  }
+
+  fn retain_nodes(mut self, keep: impl Fn(&SegOff) -> bool) -> CallGraph {
+    self.nodes.retain(|addr, _| keep(addr));
+    self.edges.retain(|(caller, callee)| keep(caller) && keep(callee));
+    self
+  }
+
+  // Only the functions in one segment (and the calls between them)
+  pub fn restrict_to_segment(self, seg: Seg) -> CallGraph {
+    self.retain_nodes(|addr| addr.seg == seg)
+  }
+
+  // Only the functions that call (or are called by) `root` within `depth` levels
+  pub fn restrict_to_neighborhood(self, root: SegOff, depth: usize) -> CallGraph {
+    let mut keep = BTreeSet::new();
+    keep.insert(root);
+    for forward in [true, false] {
+      let mut queue = VecDeque::from([(root, 0)]);
+      let mut seen = BTreeSet::from([root]);
+      while let Some((addr, level)) = queue.pop_front() {
+        if level == depth { continue; }
+        for (caller, callee) in &self.edges {
+          let (from, to) = if forward { (*caller, *callee) } else { (*callee, *caller) };
+          if from != addr || !seen.insert(to) { continue; }
+          keep.insert(to);
+          queue.push_back((to, level + 1));
+        }
+      }
+    }
+    self.retain_nodes(|addr| keep.contains(addr))
+  }
+}
+
+pub fn gen_graphviz_dotfile(g: &CallGraph) -> Result<String, std::fmt::Error> {
+  let mut buf = String::new();
+  let f = &mut buf;
+  writeln!(f, "strict digraph call_graph {{")?;
+  writeln!(f, "  node [shape=box, style=filled];")?;
+  for node in g.nodes.values() {
+    let color = if node.error.is_some() {
+      "lightcoral"
+    } else if node.addr.is_overlay_addr() {
+      "lightblue"
+    } else {
+      "lightgrey"
+    };
+    let mut label = format!("{}\\n{}", node.name, node.addr);
+    if node.indirect_calls > 0 {
+      label += &format!("\\nindirect calls: {}", node.indirect_calls);
+    }
+    writeln!(f, "  \"{}\" [label=\"{}\", fillcolor={}];", node.addr, label, color)?;
+  }
+  for (caller, callee) in &g.edges {
+    writeln!(f, "  \"{}\" -> \"{}\";", caller, callee)?;
+  }
+  writeln!(f, "}}")?;
+  Ok(buf)
+}
+
+pub fn gen_json(g: &CallGraph) -> Result<String, std::fmt::Error> {
+  let mut buf = String::new();
+  let f = &mut buf;
+  writeln!(f, "{{")?;
+  writeln!(f, "  \"nodes\": [")?;
+  for (i, node) in g.nodes.values().enumerate() {
+    let sep = if i + 1 < g.nodes.len() { "," } else { "" };
+    let error = match &node.error {
+      Some(err) => json_str(err),
+      None => "null".to_string(),
+    };
+    writeln!(f, "    {{ \"addr\": {}, \"name\": {}, \"overlay\": {}, \"indirect_calls\": {}, \"error\": {} }}{}",
+             json_str(&node.addr.to_string()), json_str(&node.name), node.addr.is_overlay_addr(),
+             node.indirect_calls, error, sep)?;
+  }
+  writeln!(f, "  ],")?;
+  writeln!(f, "  \"edges\": [")?;
+  for (i, (caller, callee)) in g.edges.iter().enumerate() {
+    let sep = if i + 1 < g.edges.len() { "," } else { "" };
+    writeln!(f, "    {{ \"caller\": {}, \"callee\": {} }}{}",
+             json_str(&caller.to_string()), json_str(&callee.to_string()), sep)?;
+  }
+  writeln!(f, "  ]")?;
+  writeln!(f, "}}")?;
+  Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn node(addr: SegOff) -> Node {
+    Node { addr, name: format!("F_{}", addr.off.0), indirect_calls: 0, error: None }
+  }
+
+  // a -> b -> c -> d, e -> b, plus x in an overlay
+  fn graph() -> CallGraph {
+    let addrs: Vec<SegOff> = (1..=5).map(|n| SegOff::new(0x10, n)).collect();
+    let x = SegOff::new_overlay(0, 6);
+    let mut nodes: BTreeMap<_, _> = addrs.iter().map(|a| (*a, node(*a))).collect();
+    nodes.insert(x, node(x));
+    let edges = BTreeSet::from([
+      (addrs[0], addrs[1]), (addrs[1], addrs[2]), (addrs[2], addrs[3]), (addrs[4], addrs[1]), (addrs[3], x),
+    ]);
+    CallGraph { nodes, edges }
+  }
+
+  #[test]
+  fn neighborhood() {
+    let g = graph().restrict_to_neighborhood(SegOff::new(0x10, 2), 1);
+    let kept: Vec<u16> = g.nodes.keys().map(|a| a.off.0).collect();
+    assert_eq!(kept, vec![1, 2, 3, 5]);
+    assert_eq!(g.edges.len(), 3);
+
+    let g = graph().restrict_to_neighborhood(SegOff::new(0x10, 2), 2);
+    let kept: Vec<u16> = g.nodes.keys().map(|a| a.off.0).collect();
+    assert_eq!(kept, vec![1, 2, 3, 4, 5]);
+  }
+
+  #[test]
+  fn segment() {
+    let g = graph().restrict_to_segment(Seg::Overlay(0));
+    assert_eq!(g.nodes.len(), 1);
+    assert!(g.edges.is_empty());
+  }
+}
@@ -28,12 +28,28 @@ impl CodeSegment {
 
 pub struct CodeSegments(pub Vec<CodeSegment>);
 
+// Without Borland's seginfo table, the code segments are found from the relocations
+fn regions_from_relocations(dat: &[u8], relocs: &[mz::Reloc], entry_cs: u16, ss: u16) -> Vec<Region> {
+  mz::code_segments_from_relocations(dat, relocs, entry_cs, ss).into_iter()
+    .map(|(seg, size)| Region { seg: Seg::Normal(seg), skip_off: 0, size })
+    .collect()
+}
+
 impl CodeSegments {
   // Should basically match those that were manually found in annotations.py
   pub fn from_binary(binary: &Binary) -> CodeSegments {
     let exe = binary.exe().unwrap(); // FIXME
-    let seginfo = exe.seginfo.as_ref().unwrap(); // FIXME
-    let ovr = exe.ovr.as_ref().unwrap(); // FIXME
+    let Some(seginfo) = exe.seginfo.as_ref() else {
+      let regions = regions_from_relocations(exe.exe_data(), &exe.relocs, exe.hdr.cs as u16, exe.hdr.ss as u16);
+      let mut code_segments: Vec<_> = regions.into_iter().map(|primary| CodeSegment { primary, stub: None }).collect();
+      // Microsoft LINK overlays are called inline from the root segments, so they have no stub segment
+      let ovr_segs = exe.ovr.as_ref().map(|ovr| ovr.segs.as_slice()).unwrap_or(&[]);
+      for (i, seg) in ovr_segs.iter().enumerate() {
+        let primary = Region { seg: Seg::Overlay(i as u16), skip_off: 0, size: seg.segment_size as u32 };
+        code_segments.push(CodeSegment { primary, stub: None });
+      }
+      return CodeSegments(code_segments);
+    };
 
     // Collect ordinary code segments and stub segments
     let mut code_segments = vec![];
@@ -53,7 +69,8 @@ impl CodeSegments {
     }
 
     // Iterate all overlay segments and match them up with the stubs
-    for (i, seg) in ovr.segs.iter().enumerate() {
+    let ovr_segs = exe.ovr.as_ref().map(|ovr| ovr.segs.as_slice()).unwrap_or(&[]);
+    for (i, seg) in ovr_segs.iter().enumerate() {
       let region = Region {
       seg: Seg::Overlay(i as u16),
         skip_off: 0,
@@ -110,3 +127,25 @@ impl CodeDetail {
     CodeDetail { function_entries }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn relocation_boundaries() {
+    let mut dat = vec![0x90; 0x80];
+    dat[0x00..0x05].copy_from_slice(&[0x9a, 0x00, 0x00, 0x02, 0x00]); // 0000:0000 call far 0002:0000
+    dat[0x20..0x25].copy_from_slice(&[0xea, 0x10, 0x00, 0x00, 0x00]); // 0002:0000 jmp far 0000:0010
+    dat[0x30..0x33].copy_from_slice(&[0xb8, 0x05, 0x00]); // 0003:0000 mov ax,seg 0005
+    let relocs = [
+      mz::Reloc { offset: 0x03, segment: 0 },
+      mz::Reloc { offset: 0x03, segment: 2 },
+      mz::Reloc { offset: 0x01, segment: 3 },
+    ];
+
+    let regions = regions_from_relocations(&dat, &relocs, 0, 0x7);
+    let found: Vec<(Seg, u32)> = regions.iter().map(|r| (r.seg, r.size)).collect();
+    assert_eq!(found, vec![(Seg::Normal(0), 0x20), (Seg::Normal(2), 0x30)]);
+  }
+}
@@ -0,0 +1,271 @@
+use super::code_segment::CodeSegments;
+use crate::asm::decode::Decoder;
+use crate::binary::Binary;
+use crate::config::Config;
+use crate::segoff::{Off, Seg, SegOff};
+use crate::util::json::json_str;
+use crate::util::range_set::RangeSet;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gap {
+  pub start: u32,
+  pub end: u32,
+  pub before: Option<String>, // the annotated function ending closest before the gap
+  pub after: Option<String>,  // ... and starting closest after it
+  pub decodes: bool,          // the whole gap decodes as instructions
+}
+
+#[derive(Debug, Clone)]
+pub struct SegmentCoverage {
+  pub seg: Seg,
+  pub name: String,
+  pub start: u32,
+  pub size: u32,
+  pub annotated: u32,
+  pub gaps: Vec<Gap>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+  pub segments: Vec<SegmentCoverage>,
+}
+
+fn percent(annotated: u32, size: u32) -> f64 {
+  if size > 0 { 100.0 * (annotated as f64) / (size as f64) } else { 100.0 }
+}
+
+impl SegmentCoverage {
+  pub fn percent(&self) -> f64 {
+    percent(self.annotated, self.size)
+  }
+}
+
+impl Coverage {
+  pub fn size(&self) -> u32 {
+    self.segments.iter().map(|s| s.size).sum()
+  }
+
+  pub fn annotated(&self) -> u32 {
+    self.segments.iter().map(|s| s.annotated).sum()
+  }
+
+  pub fn percent(&self) -> f64 {
+    percent(self.annotated(), self.size())
+  }
+}
+
+// Everything in the segment the config accounts for: functions with a known end and text section data
+pub fn annotated_ranges(cfg: &Config, seg: Seg) -> RangeSet {
+  let mut r = RangeSet::new();
+  for f in &cfg.funcs {
+    if f.start.seg != seg { continue };
+    let Some(end) = &f.end else { continue };
+    r.insert(f.start.off.0 as u32, end.off.0 as u32);
+  }
+  for t in cfg.text_regions_matching_segment(seg) {
+    r.insert(t.start.off.0 as u32, t.end.off.0 as u32);
+  }
+  r
+}
+
+fn neighbours(cfg: &Config, seg: Seg, start: u32, end: u32) -> (Option<String>, Option<String>) {
+  let funcs = cfg.funcs.iter().filter(|f| f.start.seg == seg);
+  let before = funcs.clone()
+    .filter_map(|f| f.end.map(|e| (e.off.0 as u32, f)))
+    .filter(|(e, _)| *e <= start)
+    .max_by_key(|(e, _)| *e)
+    .map(|(_, f)| f.name.clone());
+  let after = funcs
+    .filter(|f| f.start.off.0 as u32 >= end)
+    .min_by_key(|f| f.start.off.0)
+    .map(|f| f.name.clone());
+  (before, after)
+}
+
+fn decodes_as_code(binary: &Binary, seg: Seg, start: u32, end: u32) -> bool {
+  let start = SegOff { seg, off: Off(start as u16) };
+  let end = SegOff { seg, off: Off(end as u16) };
+  let mut decoder = Decoder::new(binary.region_iter(start, end));
+  loop {
+    match decoder.try_next() {
+      Ok(Some(_)) => (),
+      Ok(None) => return true,
+      Err(_) => return false,
+    }
+  }
+}
+
+pub fn build(cfg: &Config, code_segments: &CodeSegments, binary: &Binary) -> Coverage {
+  let mut cov = Coverage::default();
+  for c in &code_segments.0 {
+    let seg = c.primary.seg;
+    let start = c.primary.skip_off;
+    let end = start + c.primary.size;
+    let name = match cfg.code_seg_lookup(seg) {
+      Some(cs) => cs.name.clone(),
+      None => format!("_{}", seg),
+    };
+
+    // Ranges past the end of the segment would read past the data
+    let end_clipped = end.min(0xffff);
+    let mut gaps = vec![];
+    for g in annotated_ranges(cfg, seg).gaps_within(start, end) {
+      let (before, after) = neighbours(cfg, seg, g.start, g.end);
+      let decodes = decodes_as_code(binary, seg, g.start.min(end_clipped), g.end.min(end_clipped));
+      gaps.push(Gap { start: g.start, end: g.end, before, after, decodes });
+    }
+
+    let total_gap: u32 = gaps.iter().map(|g| g.end - g.start).sum();
+    cov.segments.push(SegmentCoverage { seg, name, start, size: c.primary.size, annotated: c.primary.size - total_gap, gaps });
+  }
+  cov
+}
+
+fn json_opt(s: &Option<String>) -> String {
+  match s {
+    Some(s) => json_str(s),
+    None => "null".to_string(),
+  }
+}
+
+pub fn gen_json(cov: &Coverage) -> Result<String, std::fmt::Error> {
+  let mut buf = String::new();
+  let f = &mut buf;
+  writeln!(f, "{{")?;
+  writeln!(f, "  \"total\": {{ \"size\": {}, \"annotated\": {}, \"percent\": {:.2} }},", cov.size(), cov.annotated(), cov.percent())?;
+  writeln!(f, "  \"segments\": [")?;
+  for (i, s) in cov.segments.iter().enumerate() {
+    writeln!(f, "    {{ \"seg\": {}, \"name\": {}, \"start\": {}, \"size\": {}, \"annotated\": {}, \"percent\": {:.2}, \"gaps\": [",
+             json_str(&s.seg.to_string()), json_str(&s.name), s.start, s.size, s.annotated, s.percent())?;
+    for (j, g) in s.gaps.iter().enumerate() {
+      let sep = if j + 1 < s.gaps.len() { "," } else { "" };
+      writeln!(f, "      {{ \"start\": {}, \"end\": {}, \"size\": {}, \"before\": {}, \"after\": {}, \"decodes\": {} }}{}",
+               g.start, g.end, g.end - g.start, json_opt(&g.before), json_opt(&g.after), g.decodes, sep)?;
+    }
+    let sep = if i + 1 < cov.segments.len() { "," } else { "" };
+    writeln!(f, "    ]}}{}", sep)?;
+  }
+  writeln!(f, "  ]")?;
+  writeln!(f, "}}")?;
+  Ok(buf)
+}
+
+const CSV_HEADER: &str = "kind,seg,name,start,end,size,annotated,before,after,decodes";
+
+// One "segment" row per segment followed by a "gap" row per gap
+pub fn gen_csv(cov: &Coverage) -> Result<String, std::fmt::Error> {
+  let mut buf = String::new();
+  let f = &mut buf;
+  writeln!(f, "{}", CSV_HEADER)?;
+  for s in &cov.segments {
+    writeln!(f, "segment,{},{},{},{},{},{},,,", s.seg, s.name, s.start, s.start + s.size, s.size, s.annotated)?;
+    for g in &s.gaps {
+      writeln!(f, "gap,{},{},{},{},{},,{},{},{}", s.seg, s.name, g.start, g.end, g.end - g.start,
+               g.before.as_deref().unwrap_or(""), g.after.as_deref().unwrap_or(""), g.decodes)?;
+    }
+  }
+  Ok(buf)
+}
+
+// Per-segment totals from the segment rows of a previous CSV report, keyed by segment
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PrevSegment {
+  pub size: u32,
+  pub annotated: u32,
+  pub gaps: usize,
+}
+
+pub fn parse_csv(text: &str) -> Result<BTreeMap<String, PrevSegment>, String> {
+  let mut lines = text.lines();
+  if lines.next() != Some(CSV_HEADER) {
+    return Err("Not a coverage report: unexpected CSV header".to_string());
+  }
+  let mut out: BTreeMap<String, PrevSegment> = BTreeMap::new();
+  for (i, line) in lines.enumerate() {
+    let cols: Vec<&str> = line.split(',').collect();
+    let err = || format!("Invalid coverage report line {}: '{}'", i + 2, line);
+    if cols.len() != 10 { return Err(err()); }
+    match cols[0] {
+      "segment" => {
+        let ent = out.entry(cols[1].to_string()).or_default();
+        ent.size = cols[5].parse().map_err(|_| err())?;
+        ent.annotated = cols[6].parse().map_err(|_| err())?;
+      }
+      "gap" => out.entry(cols[1].to_string()).or_default().gaps += 1,
+      _ => return Err(err()),
+    }
+  }
+  Ok(out)
+}
+
+// Progress since a previous report
+pub fn gen_trend(prev: &BTreeMap<String, PrevSegment>, cov: &Coverage) -> Result<String, std::fmt::Error> {
+  let mut buf = String::new();
+  let f = &mut buf;
+  let mut prev_annotated = 0;
+  let mut prev_size = 0;
+  writeln!(f, "{:<15} {:<15} {:>24} {:>22} {:>12}", "segment", "name", "annotated", "percent", "gaps")?;
+  for s in &cov.segments {
+    let p = prev.get(&s.seg.to_string()).copied().unwrap_or_default();
+    prev_annotated += p.annotated;
+    prev_size += p.size;
+    writeln!(f, "{:<15} {:<15} {:>6} -> {:>6} ({:>+6}) {:>7.2}% -> {:>7.2}% {:>4} -> {:>4}",
+             s.seg.to_string(), s.name, p.annotated, s.annotated, s.annotated as i64 - p.annotated as i64,
+             percent(p.annotated, p.size), s.percent(), p.gaps, s.gaps.len())?;
+  }
+  writeln!(f, "{:<15} {:<15} {:>6} -> {:>6} ({:>+6}) {:>7.2}% -> {:>7.2}%",
+           "total", "", prev_annotated, cov.annotated(), cov.annotated() as i64 - prev_annotated as i64,
+           percent(prev_annotated, prev_size), cov.percent())?;
+  Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn coverage() -> Coverage {
+    Coverage { segments: vec![SegmentCoverage {
+      seg: Seg::Normal(0x10), name: "cs_main".to_string(), start: 0, size: 0x100, annotated: 0xf0,
+      gaps: vec![Gap { start: 0x40, end: 0x50, before: Some("F_a".to_string()), after: None, decodes: true }],
+    }]}
+  }
+
+  #[test]
+  fn csv_round_trip() {
+    let text = gen_csv(&coverage()).unwrap();
+    assert_eq!(text, "kind,seg,name,start,end,size,annotated,before,after,decodes\n\
+                      segment,0010,cs_main,0,256,256,240,,,\n\
+                      gap,0010,cs_main,64,80,16,,F_a,,true\n");
+    let prev = parse_csv(&text).unwrap();
+    assert_eq!(prev["0010"], PrevSegment { size: 256, annotated: 240, gaps: 1 });
+    assert!(parse_csv("garbage").is_err());
+  }
+
+  #[test]
+  fn trend() {
+    let mut prev = BTreeMap::new();
+    prev.insert("0010".to_string(), PrevSegment { size: 256, annotated: 200, gaps: 3 });
+    let text = gen_trend(&prev, &coverage()).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines[1].contains("200 ->    240 (   +40)"));
+    assert!(lines[1].ends_with("3 ->    1"));
+  }
+
+  #[test]
+  fn neighbours_and_decoding() {
+    let mut cfg = Config::empty();
+    for (name, start, end) in [("F_a", 0x0, 0x2), ("F_b", 0x4, 0x6)] {
+      cfg.funcs.push(crate::config::Func {
+        name: name.to_string(), start: SegOff::new(0, start), end: Some(SegOff::new(0, end)), entry: None,
+        mode: crate::config::CallMode::Far, ret: None, args: None, regargs: None, dont_pop_args: false, locals: vec![],
+      });
+    }
+    assert_eq!(neighbours(&cfg, Seg::Normal(0), 0x2, 0x4), (Some("F_a".to_string()), Some("F_b".to_string())));
+
+    let binary = Binary::from_raw(&[0x90, 0xcb, 0x40, 0xe8, 0x90, 0xcb], None);
+    assert!(decodes_as_code(&binary, Seg::Normal(0), 0x0, 0x3));
+    assert!(!decodes_as_code(&binary, Seg::Normal(0), 0x2, 0x4)); // truncated call
+  }
+}
@@ -2,13 +2,15 @@ use super::code_segment::CodeSegment;
 use super::workqueue::WorkQueue;
 use crate::segoff::SegOff;
 use crate::binary::Binary;
-use crate::asm::instr::Instr;
+use crate::asm::instr::{Instr, Opcode, Operand, OperandReg, Reg};
 use crate::asm::decode::Decoder;
 use crate::asm::intel_syntax::instr_str;
-use std::collections::BTreeSet;
+use crate::util::range_set::RangeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 
 use crate::analyze::instr_details::{self, Next, Call};
+use crate::analyze::jump_table::{self, JumpTable};
 pub use crate::analyze::instr_details::ReturnKind;
 
 const DEBUG: bool = false;
@@ -20,6 +22,20 @@ pub struct FuncDetails {
   pub direct_calls:      BTreeSet<SegOff>,
   pub indirect_calls:    usize,
   pub return_kind:       ReturnKind,
+  pub code:              RangeSet, // offsets of all decoded instruction bytes
+  pub jump_tables:       Vec<JumpTable>,
+  pub indirect_jumps:    Vec<SegOff>, // indexed jumps that didn't look like a switch: targets unknown
+  pub ret_pops:          BTreeMap<u16, Vec<SegOff>>, // bytes popped by each return ("ret N" / "retf N", else 0) => where
+  pub call_sites:        Vec<CallSite>,
+  pub instrs:            BTreeMap<SegOff, Instr>,      // every decoded instruction
+  pub succs:             BTreeMap<SegOff, Vec<SegOff>>, // successors of each instruction (none for returns)
+}
+
+// A direct call and the stack cleanup that immediately follows it
+pub struct CallSite {
+  pub addr:    SegOff,
+  pub target:  SegOff,
+  pub cleanup: Option<u16>, // bytes popped by "add sp,N" / "pop cx" after the call (None if it's elsewhere, or there isn't any)
 }
 
 impl fmt::Display for FuncDetails {
@@ -34,6 +50,12 @@ impl fmt::Display for FuncDetails {
     writeln!(f, "]")?;
     writeln!(f, "indirect_calls:    {}", self.indirect_calls)?;
     writeln!(f, "return_kind:       {}", self.return_kind)?;
+    for t in &self.jump_tables {
+      writeln!(f, "jump_table:        {} => {}..{} ({} entries)", t.jmp_addr, t.start, t.end(), t.len)?;
+    }
+    for addr in &self.indirect_jumps {
+      writeln!(f, "indirect_jump:     {}", addr)?;
+    }
     Ok(())
   }
 }
@@ -72,6 +94,13 @@ impl FuncDetails {
     let mut direct_calls = BTreeSet::new();
     let mut indirect_calls = 0;
     let mut return_kind = None;
+    let mut code = RangeSet::new();
+    let mut jump_tables = vec![];
+    let mut indirect_jumps = vec![];
+    let mut decoded = BTreeMap::new();
+    let mut ret_pops: BTreeMap<u16, Vec<SegOff>> = BTreeMap::new();
+    let mut calls = vec![];
+    let mut succs = BTreeMap::new();
 
     // Iterate over blocks
     while let Some(loc) = workqueue.pop() {
@@ -90,20 +119,43 @@ impl FuncDetails {
         let instr = decode_one_instr(&binary, addr, code_seg_end)?;
         let end_addr = instr.end_addr();
         if end_addr > largest_addr { largest_addr = end_addr; }
+        code.insert(instr.addr.off.0 as u32, instr.addr.off.0 as u32 + instr.n_bytes as u32);
 
         if DEBUG { println!("INSTR | {} | {}", instr.addr, instr_str(&instr)); }
 
         // Add instr to the block
         block.instrs.push(instr);
+        decoded.insert(instr.addr, instr);
 
         // Compute instr details
-        let details = instr_details::instr_details(&instr, &binary)?;
+        let details = if is_table_jump(&instr) {
+          // Blocks are visited in address order, so the range guard has already been decoded
+          let mut instrs: Vec<Instr> = decoded.range(..=instr.addr).rev().take(jump_table::MAX_LOOKBEHIND + 1).map(|(_, ins)| *ins).collect();
+          instrs.reverse();
+          let next = match jump_table::recover(&instrs, binary) {
+            Ok(table) => {
+              let next = Next::Jump(table.targets.clone());
+              jump_tables.push(table);
+              next
+            }
+            Err(err) => {
+              // Not a switch after all: carry on with the rest of the function
+              if DEBUG { println!("{}", err); }
+              indirect_jumps.push(instr.addr);
+              Next::Jump(vec![])
+            }
+          };
+          instr_details::InstrDetails { next, call: None }
+        } else {
+          instr_details::instr_details(&instr, &binary)?
+        };
 
         // Handle calls
         match &details.call {
           Some(Call::Direct(addr)) => {
             if DEBUG { println!("Call to {}", addr); }
             direct_calls.insert(*addr);
+            calls.push((instr.addr, *addr));
           }
           Some(Call::Indirect) => {
             if DEBUG { println!("Indirect call"); }
@@ -115,6 +167,7 @@ impl FuncDetails {
         // Figure out what to do next
         match details.next {
           Next::Fallthrough(target) => {
+            succs.insert(instr.addr, vec![target]);
             addr = target;
             continue;
           }
@@ -122,6 +175,12 @@ impl FuncDetails {
             if return_kind.is_none() {
               return_kind = Some(ret);
             }
+            let pop = match instr.operands.as_slice().first() {
+              Some(Operand::Imm(imm)) => imm.val,
+              _ => 0,
+            };
+            ret_pops.entry(pop).or_default().push(instr.addr);
+            succs.insert(instr.addr, vec![]);
             block.exits = vec![];
             block_complete = true;
           }
@@ -130,6 +189,7 @@ impl FuncDetails {
               workqueue.insert(*tgt);
             }
             // Add exits to block
+            succs.insert(instr.addr, targets.clone());
             block.exits = targets;
             block_complete = true;
           }
@@ -139,16 +199,50 @@ impl FuncDetails {
       if DEBUG { println!("{}", block); }
     }
 
+    let call_sites = calls.into_iter().map(|(addr, target)| {
+      let cleanup = call_cleanup(&decoded, decoded[&addr].end_addr());
+      CallSite { addr, target, cleanup }
+    }).collect();
+
     Ok(FuncDetails {
       start_addr: func_start,
       end_addr_inferred: largest_addr,
       direct_calls,
       indirect_calls,
-      return_kind: return_kind.unwrap(),
+      return_kind: return_kind.ok_or_else(|| format!("No return found in function at {}", func_start))?,
+      code,
+      jump_tables,
+      indirect_jumps,
+      ret_pops,
+      call_sites,
+      instrs: decoded,
+      succs,
     })
   }
 }
 
+// Caller-pops cleanup after a call: "add sp,N" or a run of "pop cx". Anything else says nothing: the
+// cleanup may be deferred to a later "add sp,N" or the epilogue's "mov sp,bp", or there are no args.
+fn call_cleanup(decoded: &BTreeMap<SegOff, Instr>, mut addr: SegOff) -> Option<u16> {
+  let mut bytes = 0;
+  while let Some(ins) = decoded.get(&addr) {
+    match (ins.opcode, ins.operands.as_slice()) {
+      (Opcode::OP_ADD, [Operand::Reg(OperandReg(Reg::SP)), Operand::Imm(imm)]) if bytes == 0 => return Some(imm.val),
+      (Opcode::OP_POP, [Operand::Reg(OperandReg(Reg::CX))]) => bytes += 2,
+      _ => break,
+    }
+    addr = ins.end_addr();
+  }
+  (bytes != 0).then_some(bytes)
+}
+
+// Indexed, like "jmp WORD PTR cs:[bx+0x6d7]". Others are left to instr_details.
+fn is_table_jump(ins: &Instr) -> bool {
+  if ins.opcode != Opcode::OP_JMP { return false; }
+  let Operand::Mem(m) = &ins.operands[0] else { return false };
+  m.reg1.is_some() && m.reg2.is_none() && m.off.is_some()
+}
+
 // FIXME: THIS FUNCTION IS WAY TOO COMPLICATED FOR ITS SIMPLE TASK: APIs NEED IMPROVEMENT
 fn decode_one_instr(binary: &Binary, loc: SegOff, end: SegOff) -> Result<Instr, String> {
   let mut decoder = Decoder::new(binary.region_iter(loc, end));
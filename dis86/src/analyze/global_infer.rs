@@ -0,0 +1,235 @@
+use super::xref::Xref;
+use crate::config::Config;
+use crate::types::{ArraySize, Type};
+use std::collections::BTreeMap;
+
+// Fewest evenly strided elements before neighbouring scalars are proposed as an array instead
+pub const MIN_ARRAY_LEN: usize = 3;
+
+// A proposed global variable covering one or more unannotated accesses
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+  pub off: u16,
+  pub typ: Type,              // for a record array, as bytes
+  pub record: Option<Record>, // the element type when it's an array of records
+}
+
+// The same fields repeating every `size` bytes, proposed as a struct
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+  pub size: u16,
+  pub fields: Vec<(u16, Type)>, // offset in the record => type
+}
+
+impl Candidate {
+  fn new(off: u16, typ: Type) -> Self {
+    Candidate { off, typ, record: None }
+  }
+
+  pub fn name(&self) -> String {
+    format!("g_{:04x}", self.off)
+  }
+
+  pub fn struct_name(&self) -> String {
+    format!("s_{:04x}", self.off)
+  }
+
+  // As written in the config, the record array as an array of its struct
+  pub fn type_name(&self) -> String {
+    match &self.record {
+      Some(r) => format!("{}[{}]", self.struct_name(), self.size() / r.size as u32),
+      None => self.typ.to_string(),
+    }
+  }
+
+  fn size(&self) -> u32 {
+    self.typ.size_in_bytes().unwrap() as u32
+  }
+}
+
+pub fn scalar(size: u32) -> Type {
+  match size {
+    1 => Type::U8,
+    2 => Type::U16,
+    4 => Type::U32,
+    n => Type::Array(Box::new(Type::U8), ArraySize::Known(n as usize)),
+  }
+}
+
+fn array(elem: Type, n: usize) -> Type {
+  Type::Array(Box::new(elem), ArraySize::Known(n))
+}
+
+// Accesses whose extents overlap are the same variable, as wide as the widest access. Anything
+// accessed at several offsets (other than the halves of a wider scalar) becomes a byte array.
+fn merge_overlapping(accesses: &BTreeMap<u16, Vec<&Xref>>) -> Vec<Candidate> {
+  struct Cluster { start: u32, end: u32, widest_at_start: u32 }
+
+  let mut clusters: Vec<Cluster> = vec![];
+  for (off, xrefs) in accesses {
+    let off = *off as u32;
+    let width = xrefs.iter().filter_map(|x| x.size).max().unwrap_or(1) as u32;
+    match clusters.last_mut() {
+      Some(c) if off < c.end => c.end = c.end.max(off + width),
+      _ => clusters.push(Cluster { start: off, end: off + width, widest_at_start: width }),
+    }
+  }
+
+  clusters.into_iter().map(|c| {
+    let len = c.end - c.start;
+    let typ = if c.widest_at_start == len { scalar(len) } else { array(Type::U8, len as usize) };
+    Candidate::new(c.start as u16, typ)
+  }).collect()
+}
+
+// The longest run from the start of `cands` of the same fields repeating at a fixed stride, as
+// (fields per record, records). Strides with a single field that fills it are plain arrays.
+fn strided_run(cands: &[Candidate]) -> Option<(usize, usize)> {
+  let first = cands.first()?;
+  let mut best: Option<(usize, usize)> = None;
+  for nfields in 1..cands.len() {
+    let stride = (cands[nfields].off - first.off) as u32;
+    let last = &cands[nfields - 1];
+    if !cands[..nfields].iter().all(|c| c.typ.is_primitive()) { break; }
+    if nfields == 1 && stride == first.size() { continue; }
+    if (last.off - first.off) as u32 + last.size() > stride { continue; }
+
+    let mut count = 1;
+    'records: loop {
+      for m in 0..nfields {
+        let Some(c) = cands.get(count * nfields + m) else { break 'records };
+        let expect = first.off as u32 + count as u32 * stride + (cands[m].off - first.off) as u32;
+        if c.off as u32 != expect || c.typ != cands[m].typ { break 'records; }
+      }
+      count += 1;
+    }
+    if count >= MIN_ARRAY_LEN && best.is_none_or(|(f, n)| nfields * count > f * n) {
+      best = Some((nfields, count));
+    }
+  }
+  best
+}
+
+// Runs of same-sized scalars laid end to end are an array indexed with constant offsets. So are
+// the same set of fields repeating every N bytes, as an array of records.
+fn detect_arrays(cands: Vec<Candidate>) -> Vec<Candidate> {
+  let mut out: Vec<Candidate> = vec![];
+  let mut i = 0;
+  while i < cands.len() {
+    let first = &cands[i];
+    let mut n = 1;
+    if first.typ.is_primitive() {
+      while let Some(next) = cands.get(i + n) {
+        let prev = &cands[i + n - 1];
+        if next.typ != first.typ || next.off as u32 != prev.off as u32 + prev.size() { break; }
+        n += 1;
+      }
+    }
+    if let Some((nfields, count)) = strided_run(&cands[i..]).filter(|(f, c)| f * c > n) {
+      let size = cands[i + nfields].off - first.off;
+      let fields = cands[i..i+nfields].iter().map(|c| (c.off - first.off, c.typ.clone())).collect();
+      out.push(Candidate {
+        off: first.off,
+        typ: array(Type::U8, size as usize * count),
+        record: Some(Record { size, fields }),
+      });
+      i += nfields * count;
+      continue;
+    }
+    if n >= MIN_ARRAY_LEN {
+      out.push(Candidate::new(first.off, array(first.typ.clone(), n)));
+    } else {
+      out.extend(cands[i..i+n].iter().cloned());
+    }
+    i += n;
+  }
+  out
+}
+
+// Candidates for the unannotated accesses (see XrefDb::unannotated_data), trimmed so that none
+// runs into a global the config already has
+pub fn propose(accesses: &BTreeMap<u16, Vec<&Xref>>, cfg: &Config) -> Vec<Candidate> {
+  let mut cands = detect_arrays(merge_overlapping(accesses));
+  for c in &mut cands {
+    let limit = cfg.globals.iter()
+      .map(|g| g.offset as u32)
+      .filter(|off| *off > c.off as u32)
+      .min();
+    if let Some(limit) = limit {
+      if c.off as u32 + c.size() > limit {
+        c.typ = array(Type::U8, (limit - c.off as u32) as usize);
+        c.record = None;
+      }
+    }
+  }
+  cands
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::analyze::xref::AccessKind;
+  use crate::config::Global;
+  use crate::segoff::SegOff;
+
+  fn xref(size: u16) -> Xref {
+    Xref { func: SegOff::new(0, 0), addr: SegOff::new(0, 0), size: Some(size), kind: AccessKind::Read, indexed: false }
+  }
+
+  fn run(accesses: &[(u16, u16)], cfg: &Config) -> Vec<(u16, String)> {
+    let xrefs: Vec<(u16, Xref)> = accesses.iter().map(|(off, sz)| (*off, xref(*sz))).collect();
+    let mut map: BTreeMap<u16, Vec<&Xref>> = BTreeMap::new();
+    for (off, x) in &xrefs {
+      map.entry(*off).or_default().push(x);
+    }
+    propose(&map, cfg).into_iter().map(|c| (c.off, c.type_name())).collect()
+  }
+
+  #[test]
+  fn overlapping() {
+    let cfg = Config::empty();
+    assert_eq!(run(&[(0x100, 2), (0x100, 1)], &cfg), vec![(0x100, "u16".to_string())]);
+    assert_eq!(run(&[(0x100, 4), (0x102, 2)], &cfg), vec![(0x100, "u32".to_string())]);
+    assert_eq!(run(&[(0x100, 2), (0x101, 2)], &cfg), vec![(0x100, "u8[3]".to_string())]);
+    assert_eq!(run(&[(0x100, 2), (0x104, 1)], &cfg), vec![(0x100, "u16".to_string()), (0x104, "u8".to_string())]);
+  }
+
+  #[test]
+  fn arrays() {
+    let cfg = Config::empty();
+    assert_eq!(run(&[(0x200, 2), (0x202, 2), (0x204, 2), (0x206, 2)], &cfg), vec![(0x200, "u16[4]".to_string())]);
+    assert_eq!(run(&[(0x200, 2), (0x202, 2)], &cfg), vec![(0x200, "u16".to_string()), (0x202, "u16".to_string())]);
+    assert_eq!(run(&[(0x200, 1), (0x201, 1), (0x202, 1), (0x204, 1)], &cfg),
+               vec![(0x200, "u8[3]".to_string()), (0x204, "u8".to_string())]);
+  }
+
+  #[test]
+  fn records() {
+    let cfg = Config::empty();
+    // { u16 x; u8 y; } every 4 bytes
+    let accesses: Vec<(u16, u16)> = (0..4).flat_map(|i| [(0x400 + 4*i, 2), (0x402 + 4*i, 1)]).collect();
+    let xrefs: Vec<(u16, Xref)> = accesses.iter().map(|(off, sz)| (*off, xref(*sz))).collect();
+    let mut map: BTreeMap<u16, Vec<&Xref>> = BTreeMap::new();
+    for (off, x) in &xrefs {
+      map.entry(*off).or_default().push(x);
+    }
+    let cands = propose(&map, &cfg);
+    assert_eq!(cands.len(), 1);
+    assert_eq!(cands[0].type_name(), "s_0400[4]");
+    assert_eq!(cands[0].record, Some(Record { size: 4, fields: vec![(0, Type::U16), (2, Type::U8)] }));
+
+    // A single field with gaps between
+    assert_eq!(run(&[(0x500, 2), (0x506, 2), (0x50c, 2)], &cfg), vec![(0x500, "s_0500[3]".to_string())]);
+    // Laid end to end it's still a plain array
+    assert_eq!(run(&[(0x200, 2), (0x202, 2), (0x204, 2), (0x206, 2)], &cfg), vec![(0x200, "u16[4]".to_string())]);
+    // Too few repeats
+    assert_eq!(run(&[(0x600, 2), (0x602, 1), (0x604, 2), (0x606, 1)], &cfg).len(), 4);
+  }
+
+  #[test]
+  fn trimmed_to_existing() {
+    let mut cfg = Config::empty();
+    cfg.globals.push(Global { name: "g_known".to_string(), offset: 0x302, typ: Type::U16 });
+    assert_eq!(run(&[(0x300, 4)], &cfg), vec![(0x300, "u8[2]".to_string())]);
+  }
+}
@@ -0,0 +1,267 @@
+use super::code_segment::CodeSegments;
+use super::func_details::FuncDetails;
+use super::reg_usage;
+use crate::asm::instr::{Instr, Opcode, Operand, OperandMem, OperandReg, Reg};
+use crate::binary::Binary;
+use crate::config::Config;
+use crate::segoff::{Off, Seg, SegOff};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Larger value sets are given up on as unknown (this also bounds the iteration on loops)
+pub const MAX_VALUES: usize = 16;
+
+// Possible values of each 16-bit general register (AX..DI), None when unknown
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct State([Option<BTreeSet<u16>>; 8]);
+
+fn reg_idx(reg: Reg) -> Option<usize> {
+  let idx = reg as usize;
+  if idx < 8 { Some(idx) } else { None }
+}
+
+impl State {
+  fn unknown() -> State {
+    State(Default::default())
+  }
+
+  fn get(&self, reg: Reg) -> Option<&BTreeSet<u16>> {
+    self.0[reg_idx(reg)?].as_ref()
+  }
+
+  fn set(&mut self, reg: Reg, vals: Option<BTreeSet<u16>>) {
+    let vals = vals.filter(|v| v.len() <= MAX_VALUES);
+    self.0[reg_idx(reg).unwrap()] = vals;
+  }
+
+  fn map(&mut self, reg: Reg, f: impl Fn(u16) -> u16) {
+    let vals = self.get(reg).map(|v| v.iter().map(|x| f(*x)).collect());
+    self.set(reg, vals);
+  }
+
+  fn join(&self, other: &State) -> State {
+    let mut out = State::unknown();
+    for i in 0..8 {
+      if let (Some(a), Some(b)) = (&self.0[i], &other.0[i]) {
+        let u: BTreeSet<u16> = a.union(b).copied().collect();
+        if u.len() <= MAX_VALUES { out.0[i] = Some(u); }
+      }
+    }
+    out
+  }
+
+  fn transfer(&mut self, ins: &Instr) {
+    let ops = ins.operands.as_slice();
+    let reg16 = |i: usize| match ops.get(i) {
+      Some(Operand::Reg(OperandReg(r))) if reg_idx(*r).is_some() => Some(*r),
+      _ => None,
+    };
+    let imm = |i: usize| match ops.get(i) {
+      Some(Operand::Imm(imm)) => Some(imm.val),
+      _ => None,
+    };
+
+    match (ins.opcode, reg16(0)) {
+      (Opcode::OP_MOV, Some(dst)) if imm(1).is_some() => self.set(dst, Some(BTreeSet::from([imm(1).unwrap()]))),
+      (Opcode::OP_MOV, Some(dst)) if reg16(1).is_some() => self.set(dst, self.get(reg16(1).unwrap()).cloned()),
+      (Opcode::OP_XOR | Opcode::OP_SUB, Some(dst)) if reg16(1) == Some(dst) => self.set(dst, Some(BTreeSet::from([0]))),
+      (Opcode::OP_ADD, Some(dst)) if imm(1).is_some() => { let n = imm(1).unwrap(); self.map(dst, |x| x.wrapping_add(n)) }
+      (Opcode::OP_SUB, Some(dst)) if imm(1).is_some() => { let n = imm(1).unwrap(); self.map(dst, |x| x.wrapping_sub(n)) }
+      (Opcode::OP_ADD, Some(dst)) if reg16(1) == Some(dst) => self.map(dst, |x| x.wrapping_add(x)),
+      (Opcode::OP_SHL, Some(dst)) if imm(1) == Some(1) => self.map(dst, |x| x.wrapping_shl(1)),
+      (Opcode::OP_INC, Some(dst)) => self.map(dst, |x| x.wrapping_add(1)),
+      (Opcode::OP_DEC, Some(dst)) => self.map(dst, |x| x.wrapping_sub(1)),
+      _ => match reg_usage::effects(ins) {
+        Some(eff) => {
+          for reg in eff.writes.widen().iter() {
+            if reg_idx(reg).is_some() { self.set(reg, None); }
+          }
+        }
+        None => *self = State::unknown(),
+      }
+    }
+  }
+}
+
+// Forward dataflow over the function: the register values on entry to each instruction
+fn value_sets(details: &FuncDetails) -> BTreeMap<SegOff, State> {
+  let mut states: BTreeMap<SegOff, State> = BTreeMap::new();
+  states.insert(details.start_addr, State::unknown());
+  let mut work = vec![details.start_addr];
+  while let Some(addr) = work.pop() {
+    let Some(ins) = details.instrs.get(&addr) else { continue };
+    let mut out = states[&addr].clone();
+    out.transfer(ins);
+    for succ in details.succs.get(&addr).into_iter().flatten() {
+      let new = match states.get(succ) {
+        Some(prev) => prev.join(&out),
+        None => out.clone(),
+      };
+      if states.get(succ) != Some(&new) {
+        states.insert(*succ, new);
+        work.push(*succ);
+      }
+    }
+  }
+  states
+}
+
+// An indirect call through a table the config knows about
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndirectCall {
+  pub addr: SegOff,
+  pub table: String,        // the global or text section region read
+  pub targets: Vec<SegOff>, // distinct, in table order
+}
+
+// A table in DS (a global) or in the calling code segment (a text section region)
+struct Table {
+  name: String,
+  off: u16,    // as addressed by the operand
+  size: u16,
+  data: SegOff, // where its bytes are in the binary
+}
+
+fn find_table(cfg: &Config, binary: &Binary, sreg: Reg, seg: Seg, off: u16) -> Option<Table> {
+  match sreg {
+    Reg::DS => {
+      let (ds_start, ds_end) = binary.data_segment()?;
+      let g = cfg.global_lookup(off)?;
+      let size = (g.typ.size_in_bytes()? as u16).min(ds_end.off.0.saturating_sub(g.offset));
+      Some(Table { name: g.name.clone(), off: g.offset, size, data: ds_start.add_offset(g.offset) })
+    }
+    Reg::CS => {
+      let r = cfg.text_regions_matching_segment(seg).into_iter().find(|r| r.start.off.0 <= off && off < r.end.off.0)?;
+      Some(Table { name: r.name.clone(), off: r.start.off.0, size: r.end.off.0 - r.start.off.0, data: r.start })
+    }
+    _ => None,
+  }
+}
+
+fn read_entry(binary: &Binary, at: SegOff, far: bool, caller: SegOff) -> SegOff {
+  let len = if far { 4 } else { 2 };
+  let dat = binary.region(at, at.add_offset(len));
+  let off = Off(u16::from_le_bytes([dat[0], dat[1]]));
+  if far {
+    SegOff { seg: Seg::Normal(u16::from_le_bytes([dat[2], dat[3]])), off }
+  } else {
+    SegOff { seg: caller.seg, off }
+  }
+}
+
+fn resolve_one(ins: &Instr, m: &OperandMem, state: &State, cfg: &Config, binary: &Binary) -> Option<(String, Vec<SegOff>)> {
+  let far = ins.opcode == Opcode::OP_CALLF;
+  let entry_size = if far { 4 } else { 2 };
+  let base = m.off.unwrap_or(0);
+
+  // Every combination of the index register values, if they're all known
+  let mut addrs = Some(BTreeSet::from([base]));
+  for reg in [m.reg1, m.reg2].into_iter().flatten() {
+    if matches!(reg, Reg::BP | Reg::SP) { return None; } // stack variables aren't tracked
+    addrs = match (addrs, state.get(reg)) {
+      (Some(addrs), Some(vals)) => Some(addrs.iter().flat_map(|a| vals.iter().map(move |v| a.wrapping_add(*v))).collect()),
+      _ => None,
+    };
+  }
+
+  let entries: Vec<(String, SegOff)> = match addrs {
+    Some(addrs) => {
+      let mut out = vec![];
+      for a in addrs {
+        let t = find_table(cfg, binary, m.sreg, ins.addr.seg, a)?;
+        let idx = a - t.off;
+        if idx + entry_size > t.size { return None; }
+        out.push((t.name.clone(), t.data.add_offset(idx)));
+      }
+      out
+    }
+    None => {
+      // Unknown index: the whole table, provided that's exactly where the operand points
+      let t = find_table(cfg, binary, m.sreg, ins.addr.seg, m.off?)?;
+      if base != t.off || t.size % entry_size != 0 { return None; }
+      (0..t.size / entry_size).map(|i| (t.name.clone(), t.data.add_offset(i * entry_size))).collect()
+    }
+  };
+
+  let name = entries.first()?.0.clone();
+  let mut targets = vec![];
+  for (_, at) in entries {
+    let target = read_entry(binary, at, far, ins.addr);
+    if !targets.contains(&target) { targets.push(target); }
+  }
+  Some((name, targets))
+}
+
+// In a code segment or an overlay stub (which discovery follows to the overlay)
+fn in_code(code_segments: &CodeSegments, addr: SegOff) -> bool {
+  code_segments.0.iter().flat_map(|c| std::iter::once(&c.primary).chain(c.stub.as_ref())).any(|r| {
+    r.seg == addr.seg && r.skip_off <= addr.off.0 as u32 && (addr.off.0 as u32) < r.skip_off + r.size
+  })
+}
+
+// Indirect calls through tables the config knows about, with the targets that land in a code segment
+pub fn resolve(details: &FuncDetails, cfg: &Config, binary: &Binary, code_segments: &CodeSegments) -> Vec<IndirectCall> {
+  if details.indirect_calls == 0 { return vec![]; }
+  let states = value_sets(details);
+
+  let mut out = vec![];
+  for ins in details.instrs.values() {
+    if !matches!(ins.opcode, Opcode::OP_CALL | Opcode::OP_CALLF) { continue; }
+    let Operand::Mem(m) = &ins.operands[0] else { continue };
+    let Some(state) = states.get(&ins.addr) else { continue };
+    let Some((table, targets)) = resolve_one(ins, m, state, cfg, binary) else { continue };
+    let targets: Vec<SegOff> = targets.into_iter().filter(|t| in_code(code_segments, *t)).collect();
+    if !targets.is_empty() {
+      out.push(IndirectCall { addr: ins.addr, table, targets });
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::analyze::code_segment::{CodeSegment, Region};
+  use crate::config::TextSectionRegion;
+  use crate::types::{ArraySize, Type};
+
+  #[test]
+  fn text_table() {
+    let mut dat = vec![
+      0xbb, 0x04, 0x00,             // 0000: mov bx,0x4
+      0x2e, 0xff, 0x97, 0x20, 0x00, // 0003: call WORD PTR cs:[bx+0x20]
+      0xbe, 0x20, 0x00,             // 0008: mov si,0x20
+      0x2e, 0xff, 0x14,             // 000b: call WORD PTR cs:[si]
+      0x8b, 0x5e, 0x04,             // 000e: mov bx,WORD PTR [bp+0x4]
+      0xd1, 0xe3,                   // 0011: shl bx,1
+      0x2e, 0xff, 0x97, 0x20, 0x00, // 0013: call WORD PTR cs:[bx+0x20]
+      0xc3,                         // 0018: ret
+    ];
+    dat.resize(0x20, 0x90);
+    dat.extend([0x30, 0x00, 0x31, 0x00, 0x32, 0x00]); // 0020: table
+    dat.resize(0x30, 0x90);
+    dat.extend([0xc3, 0xc3, 0xc3]);                     // 0030: targets
+
+    let binary = Binary::from_raw(&dat, None);
+    let code_seg = CodeSegment { primary: Region { seg: Seg::Normal(0), skip_off: 0, size: dat.len() as u32 }, stub: None };
+    let details = FuncDetails::build(SegOff::new(0, 0), &code_seg, &binary).unwrap();
+    let code_segments = CodeSegments(vec![code_seg]);
+
+    let mut cfg = Config::empty();
+    cfg.text_section.push(TextSectionRegion {
+      name: "handlers".to_string(), start: SegOff::new(0, 0x20), end: SegOff::new(0, 0x26),
+      typ: Type::Array(Box::new(Type::U16), ArraySize::Known(3)), access: None,
+    });
+
+    let calls = resolve(&details, &cfg, &binary, &code_segments);
+    let found: Vec<(u16, Vec<u16>)> = calls.iter().map(|c| (c.addr.off.0, c.targets.iter().map(|t| t.off.0).collect())).collect();
+    assert_eq!(found, vec![
+      (0x03, vec![0x32]),
+      (0x0b, vec![0x30]),
+      (0x13, vec![0x30, 0x31, 0x32]),
+    ]);
+    assert!(calls.iter().all(|c| c.table == "handlers"));
+
+    // Nothing is resolved without the table annotation
+    assert!(resolve(&details, &Config::empty(), &binary, &code_segments).is_empty());
+  }
+}
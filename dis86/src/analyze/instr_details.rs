@@ -37,7 +37,7 @@ fn determine_callf(ins: &Instr, binary: &Binary) -> Result<Call, String> {
   if let Operand::Far(far) = &ins.operands[0] {
     let seg = if ins.addr.seg.is_overlay() {
       // Far calls from overlays need to be remapped
-      binary.remap_to_segment(far.seg)
+      binary.remap_to_segment(far.seg).ok_or_else(|| format!("No segment for 0x{:04x} in '{}'", far.seg, instr_str(ins)))?
     } else {
       // Otherwise: Normal
       Seg::Normal(far.seg)
@@ -90,7 +90,9 @@ fn jump_targets(ins: &Instr) -> Result<Option<Vec<SegOff>>, String> {
     Opcode::OP_JO   => (0, true),
     Opcode::OP_JP   => (0, true),
     Opcode::OP_JS   => (0, true),
-    Opcode::OP_LOOP => (1, true),
+    Opcode::OP_LOOP   => (1, true),
+    Opcode::OP_LOOPE  => (1, true),
+    Opcode::OP_LOOPNE => (1, true),
     _ => return Ok(None),
   };
 
@@ -124,16 +126,20 @@ pub fn instr_details(ins: &Instr, binary: &Binary) -> Result<InstrDetails, Strin
   // Need to carefully think about the other instructions before adding them.
   match ins.opcode {
     Opcode::OP_AAA        => (),
+    Opcode::OP_AAD        => (),
+    Opcode::OP_AAM        => (),
     Opcode::OP_AAS        => (),
     Opcode::OP_ADC        => (),
     Opcode::OP_ADD        => (),
     Opcode::OP_AND        => (),
+    Opcode::OP_BOUND      => (),
     Opcode::OP_CALL       => call = Some(determine_calln(ins, binary)?),
     Opcode::OP_CALLF      => call = Some(determine_callf(ins, binary)?),
     Opcode::OP_CBW        => (),
     Opcode::OP_CLC        => (),
     Opcode::OP_CLD        => (),
     Opcode::OP_CLI        => (),
+    Opcode::OP_CLTS       => (),
     Opcode::OP_CMC        => (),
     Opcode::OP_CMP        => (),
     Opcode::OP_CMPS       => (),
@@ -143,7 +149,85 @@ pub fn instr_details(ins: &Instr, binary: &Binary) -> Result<InstrDetails, Strin
     Opcode::OP_DEC        => (),
     Opcode::OP_DIV        => (),
     Opcode::OP_ENTER      => (),
+    Opcode::OP_F2XM1      => (),
+    Opcode::OP_FABS       => (),
+    Opcode::OP_FADD       => (),
+    Opcode::OP_FADDP      => (),
+    Opcode::OP_FBLD       => (),
+    Opcode::OP_FBSTP      => (),
+    Opcode::OP_FCHS       => (),
+    Opcode::OP_FCOM       => (),
+    Opcode::OP_FCOMP      => (),
+    Opcode::OP_FCOMPP     => (),
+    Opcode::OP_FCOS       => (),
+    Opcode::OP_FDECSTP    => (),
+    Opcode::OP_FDIV       => (),
+    Opcode::OP_FDIVP      => (),
+    Opcode::OP_FDIVR      => (),
+    Opcode::OP_FDIVRP     => (),
+    Opcode::OP_FFREE      => (),
+    Opcode::OP_FIADD      => (),
+    Opcode::OP_FICOM      => (),
+    Opcode::OP_FICOMP     => (),
+    Opcode::OP_FIDIV      => (),
+    Opcode::OP_FIDIVR     => (),
+    Opcode::OP_FILD       => (),
+    Opcode::OP_FIMUL      => (),
+    Opcode::OP_FINCSTP    => (),
+    Opcode::OP_FIST       => (),
+    Opcode::OP_FISTP      => (),
+    Opcode::OP_FISUB      => (),
+    Opcode::OP_FISUBR     => (),
+    Opcode::OP_FLD        => (),
+    Opcode::OP_FLD1       => (),
+    Opcode::OP_FLDCW      => (),
+    Opcode::OP_FLDENV     => (),
+    Opcode::OP_FLDL2E     => (),
+    Opcode::OP_FLDL2T     => (),
+    Opcode::OP_FLDLG2     => (),
+    Opcode::OP_FLDLN2     => (),
+    Opcode::OP_FLDPI      => (),
+    Opcode::OP_FLDZ       => (),
+    Opcode::OP_FMUL       => (),
+    Opcode::OP_FMULP      => (),
+    Opcode::OP_FNCLEX     => (),
+    Opcode::OP_FNDISI     => (),
+    Opcode::OP_FNENI      => (),
+    Opcode::OP_FNINIT     => (),
+    Opcode::OP_FNOP       => (),
+    Opcode::OP_FNSAVE     => (),
+    Opcode::OP_FNSETPM    => (),
+    Opcode::OP_FNSTCW     => (),
+    Opcode::OP_FNSTENV    => (),
+    Opcode::OP_FNSTSW     => (),
+    Opcode::OP_FPATAN     => (),
+    Opcode::OP_FPREM      => (),
+    Opcode::OP_FPREM1     => (),
+    Opcode::OP_FPTAN      => (),
+    Opcode::OP_FRNDINT    => (),
+    Opcode::OP_FRSTOR     => (),
+    Opcode::OP_FSCALE     => (),
+    Opcode::OP_FSIN       => (),
+    Opcode::OP_FSINCOS    => (),
+    Opcode::OP_FSQRT      => (),
+    Opcode::OP_FST        => (),
+    Opcode::OP_FSTP       => (),
+    Opcode::OP_FSUB       => (),
+    Opcode::OP_FSUBP      => (),
+    Opcode::OP_FSUBR      => (),
+    Opcode::OP_FSUBRP     => (),
+    Opcode::OP_FTST       => (),
+    Opcode::OP_FUCOM      => (),
+    Opcode::OP_FUCOMP     => (),
+    Opcode::OP_FUCOMPP    => (),
+    Opcode::OP_FWAIT      => (),
+    Opcode::OP_FXAM       => (),
+    Opcode::OP_FXCH       => (),
+    Opcode::OP_FXTRACT    => (),
+    Opcode::OP_FYL2X      => (),
+    Opcode::OP_FYL2XP1    => (),
     // Opcode::OP_HLT     => (),
+    Opcode::OP_IDIV       => (),
     Opcode::OP_IMUL       => (),
     Opcode::OP_IMUL_TRUNC => (),
     Opcode::OP_IN         => (),
@@ -177,6 +261,9 @@ pub fn instr_details(ins: &Instr, binary: &Binary) -> Result<InstrDetails, Strin
     Opcode::OP_LEA        => (),
     Opcode::OP_LEAVE      => (),
     Opcode::OP_LES        => (),
+    Opcode::OP_LGDT       => (),
+    Opcode::OP_LIDT       => (),
+    Opcode::OP_LMSW       => (),
     Opcode::OP_LODS       => (),
     // Opcode::OP_LOOP    => (),
     // Opcode::OP_LOOPE   => (),
@@ -222,8 +309,11 @@ pub fn instr_details(ins: &Instr, binary: &Binary) -> Result<InstrDetails, Strin
     Opcode::OP_SETNE      => (),
     Opcode::OP_SETNP      => (),
     Opcode::OP_SETNS      => (),
+    Opcode::OP_SGDT       => (),
     Opcode::OP_SHL        => (),
     Opcode::OP_SHR        => (),
+    Opcode::OP_SIDT       => (),
+    Opcode::OP_SMSW       => (),
     Opcode::OP_STC        => (),
     Opcode::OP_STD        => (),
     Opcode::OP_STI        => (),
@@ -241,5 +331,12 @@ pub fn instr_details(ins: &Instr, binary: &Binary) -> Result<InstrDetails, Strin
     return Ok(InstrDetails { next: Next::Return(ret), call: None });
   }
 
+  // Microsoft LINK overlay calls: the overlay number and offset follow the "int 3Fh" inline
+  if ins.opcode == Opcode::OP_INT {
+    if let Some(dest) = binary.ms_overlay_call(ins.addr) {
+      return Ok(InstrDetails { next: Next::Fallthrough(ins.end_addr().add_offset(3)), call: Some(Call::Direct(dest)) });
+    }
+  }
+
   Ok(InstrDetails { next: Next::Fallthrough(ins.end_addr()), call })
 }
@@ -0,0 +1,176 @@
+use crate::asm::instr::{Instr, Opcode, Operand, OperandReg, OperandImm, Reg, Size};
+use crate::asm::intel_syntax::instr_str;
+use crate::binary::Binary;
+use crate::segoff::{Off, SegOff};
+
+// How far back from the jump we look for the range guard
+pub const MAX_LOOKBEHIND: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct JumpTable {
+  pub jmp_addr: SegOff,  // the indirect jump using the table
+  pub start: SegOff,     // table of u16 offsets (in the code segment)
+  pub len: usize,
+  pub targets: Vec<SegOff>,
+}
+
+impl JumpTable {
+  pub fn end(&self) -> SegOff {
+    self.start.add_offset((2 * self.len) as u16)
+  }
+}
+
+// Recovers the table for "jmp WORD PTR cs:[reg+table]" from the straight-line code leading up to
+// it, which is expected to look like the switch lowering Borland emits:
+//
+//   cmp  ax,N          ; range guard
+//   ja   default
+//   mov  bx,ax         ; (optional)
+//   shl  bx,1          ; or: add bx,bx
+//   jmp  WORD PTR cs:[bx+table]
+//
+// `instrs` are the instructions in address order, ending with the jump
+pub fn recover(instrs: &[Instr], binary: &Binary) -> Result<JumpTable, String> {
+  let Some((jmp, prev)) = instrs.split_last() else {
+    return Err("No jump instruction".to_string());
+  };
+  let fail = |why: &str| Err(format!("Failed to recover jump table for '{}' at {}: {}", instr_str(jmp), jmp.addr, why));
+
+  // Matching jumps of the form: "jmp WORD PTR cs:[bx+0x6d7]"
+  if jmp.opcode != Opcode::OP_JMP { return fail("not a jump"); }
+  let Operand::Mem(m) = &jmp.operands[0] else { return fail("not an indirect jump"); };
+  if m.sz != Size::Size16 || m.sreg != Reg::CS || m.reg2.is_some() {
+    return fail("unsupported memory operand");
+  }
+  let (Some(mut reg), Some(table_off)) = (m.reg1, m.off) else {
+    return fail("unsupported memory operand");
+  };
+
+  // Walk backwards, following the index register to the guard
+  let mut scaled = false;
+  let mut guarded = false;
+  let mut bound = None;
+  let mut expected = jmp.addr;
+  for ins in prev.iter().rev().take(MAX_LOOKBEHIND) {
+    if ins.end_addr() != expected { break; } // only straight-line code
+    expected = ins.addr;
+
+    let ops = ins.operands.as_slice();
+    let is_reg = |i: usize| ops.get(i) == Some(&Operand::Reg(OperandReg(reg)));
+    match ins.opcode {
+      Opcode::OP_SHL if !scaled && is_reg(0) && matches!(ops[1], Operand::Imm(OperandImm { val: 1, .. })) => scaled = true,
+      Opcode::OP_ADD if !scaled && is_reg(0) && is_reg(1) => scaled = true,
+      Opcode::OP_MOV if scaled && !guarded && is_reg(0) => {
+        let Operand::Reg(OperandReg(src)) = ops[1] else { return fail("index loaded from memory"); };
+        reg = src;
+      }
+      Opcode::OP_JA if scaled && !guarded => guarded = true,
+      Opcode::OP_CMP if guarded && is_reg(0) => {
+        let Operand::Imm(imm) = ops[1] else { return fail("range guard isn't against a constant"); };
+        bound = Some(imm.val);
+        break;
+      }
+      _ => return fail(&format!("unexpected '{}'", instr_str(ins))),
+    }
+  }
+
+  let Some(bound) = bound else { return fail("no range guard found"); };
+  if bound >= 0x8000 { return fail("range guard is implausibly large"); }
+  let len = bound as usize + 1;
+
+  let start = SegOff { seg: jmp.addr.seg, off: Off(table_off) };
+  let size = 2 * len as u32;
+  if table_off as u32 + size > 0xffff { return fail("table runs past the end of the segment"); }
+  let end = start.add_offset(size as u16);
+  let Some(dat) = binary.try_region(start, end) else { return fail("table runs past the end of the binary"); };
+
+  let mut targets = vec![];
+  for i in 0..len {
+    let off = Off(u16::from_le_bytes(dat[2*i .. 2*i+2].try_into().unwrap()));
+    targets.push(SegOff { seg: jmp.addr.seg, off });
+  }
+
+  Ok(JumpTable { jmp_addr: jmp.addr, start, len, targets })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::analyze::code_segment::{CodeSegment, Region};
+  use crate::analyze::func_details::FuncDetails;
+  use crate::asm::decode::Decoder;
+  use crate::segoff::Seg;
+
+  fn decode_all(binary: &Binary, end: u16) -> Vec<Instr> {
+    let region = binary.region_iter(SegOff::new(0, 0), SegOff::new(0, end));
+    Decoder::new(region).map(|(ins, _)| ins).collect()
+  }
+
+  #[test]
+  fn borland_switch() {
+    let dat = [
+      0x3d, 0x03, 0x00,               // 0000: cmp ax,0x3
+      0x77, 0x11,                     // 0003: ja 0x16
+      0x8b, 0xd8,                     // 0005: mov bx,ax
+      0xd1, 0xe3,                     // 0007: shl bx,1
+      0x2e, 0xff, 0xa7, 0x0e, 0x00,   // 0009: jmp WORD PTR cs:[bx+0xe]
+      0x16, 0x00, 0x17, 0x00,         // 000e: table
+      0x18, 0x00, 0x19, 0x00,
+      0xc3, 0xc3, 0xc3, 0xc3,         // 0016: targets
+    ];
+    let binary = Binary::from_raw(&dat, None);
+    let instrs = decode_all(&binary, 0x0e);
+
+    let t = recover(&instrs, &binary).unwrap();
+    assert_eq!(t.jmp_addr, SegOff::new(0, 0x09));
+    assert_eq!(t.start, SegOff::new(0, 0x0e));
+    assert_eq!(t.end(), SegOff::new(0, 0x16));
+    assert_eq!(t.targets, (0x16..0x1a).map(|off| SegOff::new(0, off)).collect::<Vec<_>>());
+
+    // Without the guard there's nothing to bound the table
+    assert!(recover(&instrs[2..], &binary).is_err());
+  }
+
+  #[test]
+  fn unrelated_instr_in_between() {
+    let dat = [
+      0x83, 0xfb, 0x01,               // 0000: cmp bx,0x1
+      0x77, 0x0a,                     // 0003: ja 0x0f
+      0x03, 0xdb,                     // 0005: add bx,bx
+      0x40,                           // 0007: inc ax
+      0x2e, 0xff, 0xa7, 0x0d, 0x00,   // 0008: jmp WORD PTR cs:[bx+0xd]
+      0x0f, 0x00, 0x0f, 0x00,         // 000d: table
+    ];
+    let binary = Binary::from_raw(&dat, None);
+    let instrs = decode_all(&binary, 0x0d);
+    assert!(recover(&instrs, &binary).is_err());
+  }
+
+  #[test]
+  fn table_out_of_bounds() {
+    let mut dat = vec![
+      0x81, 0xfb, 0xff, 0x7f,         // 0000: cmp bx,0x7fff
+      0x77, 0x07,                     // 0004: ja 0x0d
+      0xd1, 0xe3,                     // 0006: shl bx,1
+      0x2e, 0xff, 0xa7, 0x10, 0x00,   // 0008: jmp WORD PTR cs:[bx+0x10]
+      0xc3,                           // 000d: ret
+    ];
+    let binary = Binary::from_raw(&dat, None);
+    let instrs = decode_all(&binary, 0x0d);
+    let err = recover(&instrs, &binary).unwrap_err();
+    assert!(err.contains("past the end of the segment"), "{}", err);
+
+    // Fits the segment, but not the binary
+    dat[2..4].copy_from_slice(&[0x10, 0x00]);
+    let binary = Binary::from_raw(&dat, None);
+    let instrs = decode_all(&binary, 0x0d);
+    let err = recover(&instrs, &binary).unwrap_err();
+    assert!(err.contains("past the end of the binary"), "{}", err);
+
+    // Which doesn't stop the rest of the function from being analyzed
+    let code_seg = CodeSegment { primary: Region { seg: Seg::Normal(0), skip_off: 0, size: dat.len() as u32 }, stub: None };
+    let details = FuncDetails::build(SegOff::new(0, 0), &code_seg, &binary).unwrap();
+    assert!(details.jump_tables.is_empty());
+    assert_eq!(details.indirect_jumps, vec![SegOff::new(0, 0x08)]);
+  }
+}
@@ -0,0 +1,220 @@
+use super::func_details::FuncDetails;
+use super::instr_details::ReturnKind;
+use crate::asm::instr::Opcode;
+use crate::config::{CallMode, Config, Func};
+use crate::segoff::SegOff;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+// One problem with the config, identified by the BSL key of the offending entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+  pub key: String,
+  pub msg: String,
+}
+
+impl fmt::Display for Problem {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}: {}", self.key, self.msg)
+  }
+}
+
+fn func_key(f: &Func) -> String {
+  format!("dis86.functions.{}", f.name)
+}
+
+fn duplicates<'a>(section: &str, names: impl Iterator<Item=&'a String>, out: &mut Vec<Problem>) {
+  let mut seen = HashSet::new();
+  for name in names {
+    if !seen.insert(name) {
+      out.push(Problem { key: format!("dis86.{}.{}", section, name), msg: "duplicate name".to_string() });
+    }
+  }
+}
+
+// Sort by start and report each entry that begins before an earlier one ends. The earlier one is
+// whichever reaches furthest, which isn't necessarily the previous entry.
+fn overlaps<T, K: Ord + Copy + fmt::Display>(items: &[T], range: impl Fn(&T) -> Option<(K, K)>, key: impl Fn(&T) -> String, out: &mut Vec<Problem>) {
+  let mut ranges: Vec<(K, K, &T)> = items.iter().filter_map(|t| range(t).map(|(s, e)| (s, e, t))).collect();
+  ranges.sort_by_key(|(s, e, _)| (*s, *e));
+  let mut furthest: Option<&(K, K, &T)> = None;
+  for r in &ranges {
+    let (start, end, cur) = r;
+    if let Some((prev_start, prev_end, prev)) = furthest {
+      if start < prev_end {
+        out.push(Problem { key: key(cur), msg: format!("overlaps {} ({} to {})", key(prev), prev_start, prev_end) });
+      }
+    }
+    if furthest.is_none_or(|(_, prev_end, _)| end > prev_end) { furthest = Some(r); }
+  }
+}
+
+// Checks that only need the config itself
+pub fn lint_config(cfg: &Config) -> Vec<Problem> {
+  let mut out = vec![];
+
+  duplicates("functions", cfg.funcs.iter().map(|f| &f.name), &mut out);
+  duplicates("globals", cfg.globals.iter().map(|g| &g.name), &mut out);
+  duplicates("structures", cfg.structs.iter().map(|s| &s.name), &mut out);
+  duplicates("text_section", cfg.text_section.iter().map(|t| &t.name), &mut out);
+
+  for f in &cfg.funcs {
+    let Some(end) = f.end else { continue };
+    if end.seg != f.start.seg || end <= f.start {
+      out.push(Problem { key: func_key(f), msg: format!("end {} isn't after start {}", end, f.start) });
+    }
+  }
+  overlaps(&cfg.funcs, |f| f.end.filter(|end| end.seg == f.start.seg).map(|end| (f.start, end)), func_key, &mut out);
+
+  overlaps(&cfg.globals, |g| {
+    let size = g.typ.size_in_bytes()? as u32;
+    Some((g.offset as u32, g.offset as u32 + size))
+  }, |g| format!("dis86.globals.{}", g.name), &mut out);
+
+  for s in &cfg.structs {
+    for m in &s.members {
+      let size = m.typ.size_in_bytes().unwrap_or(1) as u32;
+      if m.off as u32 + size > s.size as u32 {
+        out.push(Problem {
+          key: format!("dis86.structures.{}.members.{}", s.name, m.name),
+          msg: format!("member at 0x{:x} with size {} exceeds the struct size {}", m.off, size, s.size),
+        });
+      }
+    }
+  }
+
+  out
+}
+
+// Cross-check the config functions against what's actually in the binary
+pub fn lint_functions(cfg: &Config, functions: &BTreeMap<SegOff, Result<FuncDetails, String>>) -> Vec<Problem> {
+  let mut out = vec![];
+
+  for f in &cfg.funcs {
+    let Some(result) = functions.get(&f.start) else { continue };
+    let details = match result {
+      Ok(details) => details,
+      Err(err) => {
+        out.push(Problem { key: func_key(f), msg: format!("failed to analyze: {}", err) });
+        continue;
+      }
+    };
+    if let Some(end) = f.end {
+      if end != details.end_addr_inferred {
+        out.push(Problem { key: func_key(f), msg: format!("end is {}, but the code ends at {}", end, details.end_addr_inferred) });
+      }
+    }
+    let mode_ok = matches!((f.mode, details.return_kind),
+      (CallMode::Near, ReturnKind::Near) | (CallMode::Far, ReturnKind::Far) | (_, ReturnKind::Interrupt));
+    if !mode_ok {
+      out.push(Problem { key: func_key(f), msg: format!("mode is {:?}, but it returns with a {} return", f.mode, details.return_kind) });
+    }
+  }
+
+  // Calls that would trip ir_build's call mode check
+  for details in functions.values().flatten() {
+    for site in &details.call_sites {
+      let Some(callee) = cfg.func_lookup(site.target) else { continue };
+      let mode = match details.instrs[&site.addr].opcode {
+        Opcode::OP_CALL => CallMode::Near,
+        Opcode::OP_CALLF => CallMode::Far,
+        _ => continue,
+      };
+      if mode != callee.mode {
+        out.push(Problem { key: func_key(callee), msg: format!("mode is {:?}, but it's called {:?} at {}", callee.mode, mode, site.addr) });
+      }
+    }
+  }
+
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::analyze::code_segment::{CodeSegment, Region};
+  use crate::binary::Binary;
+  use crate::config::{Global, Struct, StructMember};
+  use crate::segoff::Seg;
+  use crate::types::Type;
+
+  fn func(name: &str, start: u16, end: u16) -> Func {
+    Func {
+      name: name.to_string(), start: SegOff::new(0x10, start), end: Some(SegOff::new(0x10, end)),
+      entry: None, mode: CallMode::Far, ret: None, args: None, regargs: None, dont_pop_args: false, locals: vec![],
+    }
+  }
+
+  fn keys(problems: &[Problem]) -> Vec<&str> {
+    problems.iter().map(|p| p.key.as_str()).collect()
+  }
+
+  #[test]
+  fn config() {
+    let mut cfg = Config::empty();
+    cfg.funcs = vec![func("F_a", 0x0, 0x20), func("F_b", 0x10, 0x30), func("F_c", 0x30, 0x40), func("F_c", 0x50, 0x40)];
+    // Both inside the first, but not overlapping each other
+    for (name, start, end) in [("F_big", 0x0, 0x100), ("F_x", 0x10, 0x20), ("F_y", 0x30, 0x40)] {
+      let mut f = func(name, start, end);
+      (f.start.seg, f.end.as_mut().unwrap().seg) = (Seg::Normal(0x20), Seg::Normal(0x20));
+      cfg.funcs.push(f);
+    }
+    cfg.globals = vec![
+      Global { name: "g_a".to_string(), offset: 0x100, typ: Type::U32 },
+      Global { name: "g_b".to_string(), offset: 0x102, typ: Type::U16 },
+      Global { name: "g_c".to_string(), offset: 0x104, typ: Type::U16 },
+    ];
+    cfg.structs = vec![Struct {
+      name: "foo".to_string(),
+      size: 4,
+      members: vec![
+        StructMember { name: "x".to_string(), typ: Type::U16, off: 0 },
+        StructMember { name: "y".to_string(), typ: Type::U32, off: 2 },
+      ],
+    }];
+
+    let problems = lint_config(&cfg);
+    assert_eq!(keys(&problems), vec![
+      "dis86.functions.F_c",
+      "dis86.functions.F_c",
+      "dis86.functions.F_b",
+      "dis86.functions.F_x",
+      "dis86.functions.F_y",
+      "dis86.globals.g_b",
+      "dis86.structures.foo.members.y",
+    ]);
+    assert_eq!(problems[2].msg, "overlaps dis86.functions.F_a (0010:0000 to 0010:0020)");
+    assert_eq!(problems[4].msg, "overlaps dis86.functions.F_big (0020:0000 to 0020:0100)");
+  }
+
+  #[test]
+  fn functions() {
+    let dat = [
+      0xe8, 0x01, 0x00,  // 0000: call 0x4
+      0xcb,              // 0003: retf
+      0xc3,              // 0004: ret
+      0xcb,              // 0005: retf
+    ];
+    let binary = Binary::from_raw(&dat, None);
+    let code_seg = CodeSegment { primary: Region { seg: Seg::Normal(0), skip_off: 0, size: dat.len() as u32 }, stub: None };
+
+    let mut cfg = Config::empty();
+    cfg.funcs = vec![func("F_a", 0x0, 0x10), func("F_b", 0x4, 0x5), func("F_c", 0x5, 0x6), func("F_d", 0x6, 0x7)];
+    for f in &mut cfg.funcs {
+      (f.start.seg, f.end.as_mut().unwrap().seg) = (Seg::Normal(0), Seg::Normal(0));
+    }
+    let mut functions: BTreeMap<_, _> = cfg.funcs[..3].iter()
+      .map(|f| (f.start, FuncDetails::build(f.start, &code_seg, &binary)))
+      .collect();
+    functions.insert(cfg.funcs[3].start, Err("no code".to_string()));
+
+    let problems = lint_functions(&cfg, &functions);
+    let msgs: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+    assert_eq!(msgs, vec![
+      "dis86.functions.F_a: end is 0000:0010, but the code ends at 0000:0004",
+      "dis86.functions.F_b: mode is Far, but it returns with a near return",
+      "dis86.functions.F_d: failed to analyze: no code",
+      "dis86.functions.F_b: mode is Far, but it's called Near at 0000:0000",
+    ]);
+  }
+}
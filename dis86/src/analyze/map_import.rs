@@ -0,0 +1,306 @@
+use super::code_segment::{CodeSegment, Region};
+use super::func_details::{FuncDetails, ReturnKind};
+use super::global_infer;
+use crate::binary::Binary;
+use crate::binfmt::map::{MapFile, Public};
+use crate::binfmt::tdinfo::{SymbolClass, TdInfo};
+use crate::config::{self, CallMode, CodeSeg, Config, Func, Global, Struct};
+use crate::segoff::{Seg, SegOff};
+use crate::types::{Type, TypeDatabase};
+use std::fmt::Write;
+
+// Everything from a MAP file that the config doesn't have yet
+#[derive(Debug, Default)]
+pub struct Import {
+  pub code_segs: Vec<CodeSeg>,
+  pub funcs: Vec<Func>,
+  pub globals: Vec<Global>,
+  pub structs: Vec<Struct>,
+  pub types: Option<TypeDatabase>, // the struct types refer to, when there are structs
+  pub notes: Vec<String>, // what was left out, and why
+}
+
+fn func(name: &str, start: SegOff, end: Option<SegOff>, mode: CallMode) -> Func {
+  Func { name: name.to_string(), start, end, entry: None, mode, ret: None, args: None, regargs: None, dont_pop_args: false, locals: vec![] }
+}
+
+// The public's segment, from its frame up to the end of the MAP segment it's in
+fn code_segment(map: &MapFile, p: &Public) -> Option<CodeSegment> {
+  let s = map.segment_containing(p.linear())?;
+  let base = p.addr.seg.unwrap_normal() as u32 * 16;
+  let size = (s.end - base).min(0x10000);
+  Some(CodeSegment { primary: Region { seg: p.addr.seg, skip_off: 0, size }, stub: None })
+}
+
+fn import_code_segs(map: &MapFile, cfg: &Config, imp: &mut Import) {
+  for s in map.segments.iter().filter(|s| s.is_code()) {
+    // Addressed the way the linker addressed its publics, if it has any
+    let first = map.publics.iter().find(|p| s.start <= p.linear() && p.linear() < s.end);
+    let seg = first.map(|p| p.addr.seg).unwrap_or(Seg::Normal((s.start / 16) as u16));
+    if cfg.code_seg_lookup(seg).is_some() || imp.code_segs.iter().any(|c| c.seg == seg) { continue; }
+    if let Some(c) = cfg.code_seg_lookup_by_name(&s.name) {
+      imp.notes.push(format!("Code segment {} at {}: the config already has it at {}", s.name, seg, c.seg));
+      continue;
+    }
+    imp.code_segs.push(CodeSeg { seg, name: s.name.clone() });
+  }
+}
+
+fn import_funcs(map: &MapFile, cfg: &Config, binary: &Binary, imp: &mut Import) {
+  let mut publics: Vec<&Public> = map.publics.iter()
+    .filter(|p| map.segment_containing(p.linear()).is_some_and(|s| s.is_code()))
+    .collect();
+  publics.sort_by_key(|p| p.linear());
+
+  for (i, p) in publics.iter().enumerate() {
+    let (name, start) = (p.name.as_str(), p.addr);
+    if let Some(f) = cfg.func_lookup_by_start(start) {
+      if f.name != name { imp.notes.push(format!("Function {} at {}: already annotated as {}", name, start, f.name)); }
+      continue;
+    }
+    if let Some(f) = cfg.func_lookup_by_name(name) {
+      imp.notes.push(format!("Function {} at {}: the config already has it at {}", name, start, f.start));
+      continue;
+    }
+    let inside = cfg.funcs.iter().find(|f| {
+      f.start.seg == start.seg && f.start.off <= start.off && f.end.is_some_and(|end| start.off < end.off)
+    });
+    if let Some(f) = inside {
+      imp.notes.push(format!("Function {} at {}: inside {}", name, start, f.name));
+      continue;
+    }
+
+    // The end is where decoding stops, but not past the next public
+    let next = publics[i+1..].iter().find(|n| n.addr.seg == start.seg && n.addr.off > start.off).map(|n| n.addr);
+    let code_seg = code_segment(map, p).unwrap();
+    match FuncDetails::build(start, &code_seg, binary) {
+      Ok(details) => {
+        let mut end = details.end_addr_inferred;
+        if let Some(next) = next { if next.off < end.off { end = next; } }
+        let mode = if details.return_kind == ReturnKind::Near { CallMode::Near } else { CallMode::Far };
+        imp.funcs.push(func(name, start, Some(end), mode));
+      }
+      Err(err) => {
+        imp.notes.push(format!("Function {} at {}: end unknown, failed to decode: {}", name, start, err));
+        imp.funcs.push(func(name, start, None, CallMode::Far));
+      }
+    }
+  }
+}
+
+fn import_globals(map: &MapFile, cfg: &Config, binary: &Binary, imp: &mut Import) {
+  let ds = match map.group("DGROUP") {
+    Some(g) => Some(g.seg),
+    None => binary.data_segment().map(|(start, _)| start.seg.unwrap_normal()),
+  };
+
+  let mut publics = vec![];
+  for p in &map.publics {
+    let seg = map.segment_containing(p.linear());
+    if seg.is_some_and(|s| s.is_code()) { continue; }
+    let base = ds.map(|ds| ds as u32 * 16);
+    match base.filter(|base| *base <= p.linear() && p.linear() < base + 0x10000) {
+      Some(base) => publics.push((p, (p.linear() - base) as u16, seg.map(|s| s.end))),
+      None => imp.notes.push(format!("Public {} at {}: not in a code segment or DGROUP", p.name, p.addr)),
+    }
+  }
+  publics.sort_by_key(|(_, off, _)| *off);
+
+  for (i, (p, off, seg_end)) in publics.iter().enumerate() {
+    let name = p.name.as_str();
+    if i > 0 && publics[i-1].1 == *off {
+      imp.notes.push(format!("Global {} at 0x{:04x}: same address as {}", name, off, publics[i-1].0.name));
+      continue;
+    }
+    if let Some(g) = cfg.global_lookup(*off) {
+      if g.name != name || g.offset != *off { imp.notes.push(format!("Global {} at 0x{:04x}: already annotated as {}", name, off, g.name)); }
+      continue;
+    }
+    if let Some(g) = cfg.globals.iter().find(|g| g.name == name) {
+      imp.notes.push(format!("Global {} at 0x{:04x}: the config already has it at 0x{:04x}", name, off, g.offset));
+      continue;
+    }
+
+    // Sized up to the next public, or the end of its segment
+    let linear = p.linear();
+    let mut end = seg_end.unwrap_or(linear + 1);
+    if let Some((next, _, _)) = publics[i+1..].iter().find(|(_, o, _)| o > off) {
+      end = end.min(next.linear());
+    }
+    imp.globals.push(Global { name: name.to_string(), offset: *off, typ: global_infer::scalar(end - linear) });
+  }
+}
+
+pub fn build(map: &MapFile, cfg: &Config, binary: &Binary) -> Import {
+  let mut imp = Import::default();
+  import_code_segs(map, cfg, &mut imp);
+  import_funcs(map, cfg, binary, &mut imp);
+  import_globals(map, cfg, binary, &mut imp);
+  imp
+}
+
+// Like a MAP import, plus what debug info has on top: struct types, the types of the globals and
+// the locals of the functions
+pub fn build_tdinfo(info: &TdInfo, cfg: &Config, binary: &Binary) -> Import {
+  let mut imp = build(&info.to_map(), cfg, binary);
+  let types = info.types();
+
+  for s in &types.structs {
+    if cfg.types.parse_type(&s.name).is_ok() {
+      imp.notes.push(format!("Struct {}: the config already has a type by that name", s.name));
+      continue;
+    }
+    imp.structs.push(s.clone());
+  }
+
+  for g in &mut imp.globals {
+    let sym = info.symbols.iter().find(|s| s.class == SymbolClass::Static && info.name(s.name) == g.name);
+    if let Some(typ) = sym.and_then(|s| types.get(s.typ)) { g.typ = typ.clone(); }
+  }
+
+  let funcs = info.functions();
+  for f in &mut imp.funcs {
+    let Some(tf) = funcs.iter().find(|tf| tf.addr == f.start) else { continue };
+    f.locals = tf.locals.iter()
+      .map(|l| config::Local { name: l.name.clone(), off: l.bp_off, typ: types.get(l.typ).cloned() })
+      .collect();
+  }
+
+  imp.types = Some(types.db);
+  imp
+}
+
+fn end_str(end: Option<SegOff>) -> String {
+  match end {
+    Some(end) => end.to_string(),
+    None => "\"\"".to_string(),
+  }
+}
+
+fn locals_str(imp: &Import, func: &Func) -> String {
+  if func.locals.is_empty() { return String::new(); }
+  let mut out = "locals { ".to_string();
+  for l in &func.locals {
+    out += &format!("{} {{ off {} ", l.name, l.off);
+    if let Some(typ) = &l.typ { out += &format!("type {} ", type_str(imp, typ)); }
+    out += "} ";
+  }
+  out + "} "
+}
+
+fn type_str(imp: &Import, typ: &Type) -> String {
+  match &imp.types {
+    Some(db) => db.type_name(typ),
+    None => typ.to_string(),
+  }
+}
+
+pub fn gen_bsl(imp: &Import) -> Result<String, std::fmt::Error> {
+  let mut buf = String::new();
+  let f = &mut buf;
+  writeln!(f, "  code_segments {{")?;
+  for c in &imp.code_segs {
+    writeln!(f, "    map_{} {{ seg {} name {:15}  }}", c.seg, c.seg, c.name)?;
+  }
+  writeln!(f, "  }}")?;
+  writeln!(f, "  functions {{")?;
+  for func in &imp.funcs {
+    let mode = if func.mode == CallMode::Near { "near" } else { "far" };
+    writeln!(f, "    {:30} {{ start {} end {} mode {} ret None args None {}}}", func.name, func.start, end_str(func.end), mode, locals_str(imp, func))?;
+  }
+  writeln!(f, "  }}")?;
+  writeln!(f, "  structures {{")?;
+  for s in &imp.structs {
+    writeln!(f, "    {:<15} {{ size {} members {{", s.name, s.size)?;
+    for m in &s.members {
+      writeln!(f, "      {:<20} {{ type {:<15} off 0x{:02x} }}", m.name, type_str(imp, &m.typ), m.off)?;
+    }
+    writeln!(f, "    }}}}")?;
+  }
+  writeln!(f, "  }}")?;
+  writeln!(f, "  globals {{")?;
+  for g in &imp.globals {
+    writeln!(f, "    {:30} {{ off 0x{:04x}  type {:20} }}", g.name, g.offset, type_str(imp, &g.typ))?;
+  }
+  writeln!(f, "  }}")?;
+  Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::binfmt::map;
+  use crate::types::Type;
+
+  const MAP: &str = "
+ Start  Stop   Length Name               Class
+ 00000H 0000FH 00010H _TEXT              CODE
+ 00010H 00015H 00006H _DATA              DATA
+
+ Origin   Group
+ 0001:0   DGROUP
+
+  Address         Publics by Value
+ 0000:0000       _a
+ 0000:0002       _b
+ 0000:0003       _c
+ 0001:0000       _x
+ 0001:0002       _y
+ 0001:0004       _z
+";
+
+  #[test]
+  fn import() {
+    let mut dat = vec![
+      0x55, 0xc3, // 0000: push bp; ret
+      0xcb,       // 0002: retf
+      0xc3,       // 0003: ret
+    ];
+    dat.resize(0x16, 0);
+    let binary = Binary::from_raw(&dat, None);
+
+    let mut cfg = Config::empty();
+    cfg.funcs.push(func("F_c", SegOff::new(0, 3), Some(SegOff::new(0, 4)), CallMode::Near));
+    cfg.globals.push(Global { name: "g_count".to_string(), offset: 2, typ: Type::U16 });
+
+    let imp = build(&map::parse(MAP).unwrap(), &cfg, &binary);
+    assert_eq!(imp.code_segs.len(), 1);
+    let funcs: Vec<(&str, SegOff, Option<SegOff>, CallMode)> = imp.funcs.iter().map(|f| (f.name.as_str(), f.start, f.end, f.mode)).collect();
+    assert_eq!(funcs, vec![
+      ("_a", SegOff::new(0, 0), Some(SegOff::new(0, 2)), CallMode::Near),
+      ("_b", SegOff::new(0, 2), Some(SegOff::new(0, 3)), CallMode::Far),
+    ]);
+    let globals: Vec<(&str, u16, String)> = imp.globals.iter().map(|g| (g.name.as_str(), g.offset, g.typ.to_string())).collect();
+    assert_eq!(globals, vec![("_x", 0, "u16".to_string()), ("_z", 4, "u16".to_string())]);
+    assert_eq!(imp.notes.len(), 2); // _c and _y are annotated already, under other names
+
+    let text = gen_bsl(&imp).unwrap();
+    assert!(text.contains("map_0000 { seg 0000 name _TEXT"));
+    assert!(text.contains("{ start 0000:0000 end 0000:0002 mode near ret None args None }"));
+  }
+
+  #[test]
+  fn bsl_structs_and_locals() {
+    let point = Struct { name: "point".to_string(), size: 4, members: vec![
+      config::StructMember { name: "x".to_string(), typ: Type::I16, off: 0 },
+      config::StructMember { name: "y".to_string(), typ: Type::I16, off: 2 },
+    ]};
+    let mut db = TypeDatabase::new();
+    db.append_struct(&point);
+    let typ = db.parse_type("point[3]").unwrap();
+
+    let mut f = func("F_draw", SegOff::new(0, 0), None, CallMode::Far);
+    f.locals.push(config::Local { name: "pts".to_string(), off: -12, typ: Some(typ.clone()) });
+    let imp = Import {
+      funcs: vec![f],
+      globals: vec![Global { name: "g_origin".to_string(), offset: 0x10, typ }],
+      structs: vec![point],
+      types: Some(db),
+      ..Import::default()
+    };
+    let text = gen_bsl(&imp).unwrap();
+    assert!(text.contains("args None locals { pts { off -12 type point[3] } } }"));
+    assert!(text.contains("point           { size 4 members {"));
+    assert!(text.contains("type point[3]"));
+  }
+}
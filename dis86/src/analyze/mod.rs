@@ -4,7 +4,22 @@ pub mod workqueue;
 // components
 pub mod code_segment;
 pub mod instr_details;
+pub mod reg_usage;
 pub mod func_details;
+pub mod jump_table;
+pub mod indirect_call;
+pub mod byte_map;
+pub mod call_graph;
+pub mod arg_infer;
+pub mod ret_infer;
+pub mod regarg_infer;
+pub mod global_infer;
+pub mod signature;
+pub mod xref;
+pub mod strings;
+pub mod lint;
+pub mod coverage;
+pub mod map_import;
 
 // primary
 pub mod analyze;
@@ -0,0 +1,324 @@
+use super::func_details::FuncDetails;
+use crate::asm::instr::{Instr, Opcode, Operand, Reg};
+use crate::segoff::SegOff;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+// A set of the general purpose registers, tracking the low and high byte of each separately so
+// that byte register accesses are exact
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegSet(u16);
+
+const GENERAL_REGS: [Reg; 8] = [Reg::AX, Reg::CX, Reg::DX, Reg::BX, Reg::SP, Reg::BP, Reg::SI, Reg::DI];
+const LOW_REGS: [Reg; 4] = [Reg::AL, Reg::CL, Reg::DL, Reg::BL];
+const HIGH_REGS: [Reg; 4] = [Reg::AH, Reg::CH, Reg::DH, Reg::BH];
+
+// Bits for a register: bit 2*n is the low byte of GENERAL_REGS[n] and bit 2*n+1 the high byte
+fn reg_mask(reg: Reg) -> u16 {
+  let idx = reg as usize;
+  match idx {
+    0..=7   => 0b11 << (2*idx),
+    8..=11  => 0b01 << (2*(idx - 8)),  // AL, CL, DL, BL
+    12..=15 => 0b10 << (2*(idx - 12)), // AH, CH, DH, BH
+    _ => 0,
+  }
+}
+
+impl RegSet {
+  pub const EMPTY: RegSet = RegSet(0);
+  pub const ALL: RegSet = RegSet(0xffff);
+
+  pub fn of(regs: &[Reg]) -> RegSet {
+    let mut set = RegSet::EMPTY;
+    for reg in regs { set.insert(*reg); }
+    set
+  }
+
+  // Non-general registers (segment, flags, fpu) are ignored
+  pub fn insert(&mut self, reg: Reg) {
+    self.0 |= reg_mask(reg);
+  }
+
+  // True if any byte of `reg` is in the set
+  pub fn contains(self, reg: Reg) -> bool {
+    self.0 & reg_mask(reg) != 0
+  }
+
+  pub fn is_empty(self) -> bool { self.0 == 0 }
+  pub fn union(self, other: RegSet) -> RegSet { RegSet(self.0 | other.0) }
+  pub fn intersect(self, other: RegSet) -> RegSet { RegSet(self.0 & other.0) }
+  pub fn minus(self, other: RegSet) -> RegSet { RegSet(self.0 & !other.0) }
+
+  // Both bytes of every register that has either byte in the set
+  pub fn widen(self) -> RegSet {
+    GENERAL_REGS.into_iter().filter(|reg| self.contains(*reg)).fold(RegSet::EMPTY, |acc, reg| acc.union(RegSet::of(&[reg])))
+  }
+
+  // Whole registers where both bytes are present, otherwise the byte registers
+  pub fn iter(self) -> impl Iterator<Item=Reg> {
+    let mut regs = vec![];
+    for (i, reg) in GENERAL_REGS.into_iter().enumerate() {
+      match (self.0 >> (2*i)) & 0b11 {
+        0b11 => regs.push(reg),
+        0b01 => regs.push(LOW_REGS[i]),
+        0b10 => regs.push(HIGH_REGS[i]),
+        _ => (),
+      }
+    }
+    regs.into_iter()
+  }
+}
+
+impl fmt::Display for RegSet {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, reg) in self.iter().enumerate() {
+      if i != 0 { write!(f, ",")?; }
+      write!(f, "{}", reg.info().name)?;
+    }
+    Ok(())
+  }
+}
+
+// Register effects of one instruction
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Effects {
+  pub reads: RegSet,
+  pub writes: RegSet,
+}
+
+// How an instruction accesses one of its operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Acc { R, W, RW }
+use Acc::*;
+
+// Registers that a call may clobber under the Borland calling convention (and where results are returned)
+pub const CALL_CLOBBERS: [Reg; 4] = [Reg::AX, Reg::BX, Reg::CX, Reg::DX];
+
+// Access for each of the instruction's operands (missing trailing entries are reads), or None for
+// anything we don't model
+pub fn operand_access(ins: &Instr) -> Option<&'static [Acc]> {
+  let acc: &[Acc] = match ins.opcode {
+    // Moves
+    Opcode::OP_MOV | Opcode::OP_LEA | Opcode::OP_CBW | Opcode::OP_CWD | Opcode::OP_IN | Opcode::OP_LODS => &[W, R],
+    Opcode::OP_LDS | Opcode::OP_LES => &[W, W, R],
+    Opcode::OP_POP | Opcode::OP_LAHF | Opcode::OP_FNSTSW => &[W],
+    Opcode::OP_XCHG => &[RW, RW],
+
+    // Arithmetic
+    Opcode::OP_ADD | Opcode::OP_ADC | Opcode::OP_SUB | Opcode::OP_SBB |
+    Opcode::OP_AND | Opcode::OP_OR  | Opcode::OP_XOR |
+    Opcode::OP_SHL | Opcode::OP_SHR | Opcode::OP_SAR |
+    Opcode::OP_ROL | Opcode::OP_ROR | Opcode::OP_RCL | Opcode::OP_RCR |
+    Opcode::OP_INC | Opcode::OP_DEC | Opcode::OP_NEG | Opcode::OP_NOT | Opcode::OP_XLAT => &[RW, R, R],
+    Opcode::OP_MUL | Opcode::OP_IMUL => &[W, RW, R],
+    Opcode::OP_DIV | Opcode::OP_IDIV => &[RW, RW, R],
+    Opcode::OP_IMUL_TRUNC => &[W, R, R],
+    Opcode::OP_CMP | Opcode::OP_TEST | Opcode::OP_PUSH | Opcode::OP_OUT |
+    Opcode::OP_CMPS | Opcode::OP_SCAS | Opcode::OP_SAHF => &[R, R, R],
+    Opcode::OP_STOS | Opcode::OP_MOVS => &[W, R, R],
+
+    Opcode::OP_SETO | Opcode::OP_SETNO | Opcode::OP_SETA | Opcode::OP_SETAE | Opcode::OP_SETB | Opcode::OP_SETBE |
+    Opcode::OP_SETE | Opcode::OP_SETNE | Opcode::OP_SETG | Opcode::OP_SETGE | Opcode::OP_SETL | Opcode::OP_SETLE |
+    Opcode::OP_SETP | Opcode::OP_SETNP | Opcode::OP_SETS | Opcode::OP_SETNS => &[W],
+
+    // Stack frames
+    Opcode::OP_ENTER => &[W],
+    Opcode::OP_LEAVE => &[RW, W],
+    Opcode::OP_PUSHA | Opcode::OP_POPA => &[],
+
+    // Control flow
+    Opcode::OP_JA | Opcode::OP_JAE | Opcode::OP_JB | Opcode::OP_JBE | Opcode::OP_JE | Opcode::OP_JNE |
+    Opcode::OP_JG | Opcode::OP_JGE | Opcode::OP_JL | Opcode::OP_JLE | Opcode::OP_JO | Opcode::OP_JNO |
+    Opcode::OP_JP | Opcode::OP_JNP | Opcode::OP_JS | Opcode::OP_JNS | Opcode::OP_JCXZ |
+    Opcode::OP_JMP | Opcode::OP_JMPF | Opcode::OP_RET | Opcode::OP_RETF | Opcode::OP_IRET => &[R, R],
+    Opcode::OP_LOOP | Opcode::OP_LOOPE | Opcode::OP_LOOPNE => &[RW, R],
+    Opcode::OP_CALL | Opcode::OP_CALLF => &[R],
+
+    // Flags and the FPU don't touch the general registers (other than through memory operands)
+    Opcode::OP_CLC | Opcode::OP_STC | Opcode::OP_CMC | Opcode::OP_CLD | Opcode::OP_STD |
+    Opcode::OP_CLI | Opcode::OP_STI | Opcode::OP_NOP | Opcode::OP_PUSHF | Opcode::OP_POPF => &[],
+    Opcode::OP_FST | Opcode::OP_FSTP | Opcode::OP_FIST | Opcode::OP_FISTP | Opcode::OP_FBSTP |
+    Opcode::OP_FNSTCW | Opcode::OP_FNSTENV | Opcode::OP_FNSAVE => &[W],
+    op if op.name().starts_with('f') => &[R, R, R],
+
+    _ => return None,
+  };
+  Some(acc)
+}
+
+// Conservative: None for anything we don't model (e.g. INT, where the effects depend on the service)
+pub fn effects(ins: &Instr) -> Option<Effects> {
+  let acc = operand_access(ins)?;
+
+  let mut eff = Effects::default();
+  match ins.opcode {
+    Opcode::OP_ENTER => {
+      eff.reads.insert(Reg::SP);
+      eff.writes.insert(Reg::SP);
+    }
+    Opcode::OP_PUSHA => eff.reads = RegSet::ALL,
+    Opcode::OP_POPA => eff.writes = RegSet::ALL.minus(RegSet::of(&[Reg::SP])),
+    Opcode::OP_CALL | Opcode::OP_CALLF => eff.writes = RegSet::of(&CALL_CLOBBERS),
+    _ => (),
+  }
+
+  for (i, operand) in ins.operands.as_slice().iter().enumerate() {
+    let acc = acc.get(i).copied().unwrap_or(R);
+    match operand {
+      Operand::Reg(r) => {
+        if matches!(acc, R | RW) { eff.reads.insert(r.0); }
+        if matches!(acc, W | RW) { eff.writes.insert(r.0); }
+      }
+      Operand::Mem(m) => {
+        if let Some(reg) = m.reg1 { eff.reads.insert(reg); }
+        if let Some(reg) = m.reg2 { eff.reads.insert(reg); }
+      }
+      _ => (),
+    }
+  }
+
+  // String instructions step SI/DI, and REP counts with CX
+  if matches!(ins.opcode, Opcode::OP_MOVS | Opcode::OP_CMPS | Opcode::OP_SCAS | Opcode::OP_STOS | Opcode::OP_LODS) {
+    for reg in [Reg::SI, Reg::DI] {
+      if eff.reads.contains(reg) { eff.writes.insert(reg); }
+    }
+    if ins.rep.is_some() {
+      eff.reads.insert(Reg::CX);
+      eff.writes.insert(Reg::CX);
+    }
+  }
+
+  // Zeroing idioms don't depend on the old value
+  if matches!(ins.opcode, Opcode::OP_XOR | Opcode::OP_SUB) && ins.operands[0] == ins.operands[1] {
+    if let Operand::Reg(r) = ins.operands[0] {
+      eff.reads = eff.reads.minus(RegSet::of(&[r.0]));
+    }
+  }
+
+  Some(eff)
+}
+
+// Which of `tracked` are read (before being overwritten) on some path from `start`, and which
+// are still untouched when some path reaches a return
+pub fn reads_before_writes(func: &FuncDetails, start: SegOff, tracked: RegSet) -> (RegSet, RegSet) {
+  reads_before_writes_with(func, start, tracked, effects)
+}
+
+// Same, but with the effects of each instruction decided by the caller
+pub fn reads_before_writes_with(func: &FuncDetails, start: SegOff, tracked: RegSet,
+                                effects: impl Fn(&Instr) -> Option<Effects>) -> (RegSet, RegSet) {
+  let mut reads = RegSet::EMPTY;
+  let mut at_return = RegSet::EMPTY;
+  let mut visited = HashSet::new();
+  let mut stack = vec![(start, tracked)];
+  while let Some((addr, tracked)) = stack.pop() {
+    if !visited.insert((addr, tracked)) { continue; }
+    let Some(eff) = func.instrs.get(&addr).and_then(&effects) else {
+      reads = reads.union(tracked); // pessimize: anything might be read
+      continue;
+    };
+    reads = reads.union(eff.reads.intersect(tracked));
+    let tracked = tracked.minus(eff.writes);
+    if tracked.is_empty() { continue; }
+    match func.succs.get(&addr) {
+      Some(succs) if succs.is_empty() => at_return = at_return.union(tracked),
+      Some(succs) => stack.extend(succs.iter().map(|s| (*s, tracked))),
+      None => reads = reads.union(tracked),
+    }
+  }
+  (reads, at_return)
+}
+
+// Registers written on every path from the function start to every return
+pub fn written_before_return(func: &FuncDetails) -> RegSet {
+  // Forward "must" dataflow: registers written on all paths reaching each instruction
+  let mut written: BTreeMap<SegOff, RegSet> = BTreeMap::new();
+  written.insert(func.start_addr, RegSet::EMPTY);
+  let mut work = vec![func.start_addr];
+  while let Some(addr) = work.pop() {
+    let Some(ins) = func.instrs.get(&addr) else { continue };
+    let out = written[&addr].union(effects(ins).map(|e| e.writes).unwrap_or_default());
+    for succ in func.succs.get(&addr).into_iter().flatten() {
+      let new = match written.get(succ) {
+        Some(prev) => prev.intersect(out),
+        None => out,
+      };
+      if written.get(succ) != Some(&new) {
+        written.insert(*succ, new);
+        work.push(*succ);
+      }
+    }
+  }
+
+  let mut result = None;
+  for (addr, succs) in &func.succs {
+    if !succs.is_empty() { continue; }
+    let w = written.get(addr).copied().unwrap_or_default();
+    result = Some(result.map_or(w, |r: RegSet| r.intersect(w)));
+  }
+  result.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::asm::decode::Decoder;
+  use crate::binary::Binary;
+  use crate::segoff::SegOff;
+
+  fn effects_of(dat: &[u8]) -> Option<Effects> {
+    let binary = Binary::from_raw(dat, None);
+    let region = binary.region_iter(SegOff::new(0, 0), SegOff::new(0, dat.len() as u16));
+    let (ins, _) = Decoder::new(region).next().unwrap();
+    effects(&ins)
+  }
+
+  fn eff(reads: &[Reg], writes: &[Reg]) -> Option<Effects> {
+    Some(Effects { reads: RegSet::of(reads), writes: RegSet::of(writes) })
+  }
+
+  #[test]
+  fn basic() {
+    assert_eq!(effects_of(&[0x8b, 0x47, 0x02]), eff(&[Reg::BX], &[Reg::AX]));           // mov ax,[bx+2]
+    assert_eq!(effects_of(&[0x03, 0xc2]), eff(&[Reg::AX, Reg::DX], &[Reg::AX]));        // add ax,dx
+    assert_eq!(effects_of(&[0x33, 0xc0]), eff(&[], &[Reg::AX]));                        // xor ax,ax
+    assert_eq!(effects_of(&[0xb0, 0x01]), eff(&[], &[Reg::AL]));                        // mov al,1
+    assert_eq!(effects_of(&[0x98]), eff(&[Reg::AL], &[Reg::AX]));                       // cbw
+    assert_eq!(effects_of(&[0x99]), eff(&[Reg::AX], &[Reg::DX]));                       // cwd
+    assert_eq!(effects_of(&[0xf7, 0xe3]), eff(&[Reg::AX, Reg::BX], &[Reg::AX, Reg::DX])); // mul bx
+    assert_eq!(effects_of(&[0xf3, 0xa5]),                                                // rep movsw
+               eff(&[Reg::CX, Reg::SI, Reg::DI], &[Reg::CX, Reg::SI, Reg::DI]));
+    assert_eq!(effects_of(&[0xcd, 0x21]), None);                                         // int 0x21
+  }
+
+  #[test]
+  fn flow() {
+    use crate::analyze::code_segment::{CodeSegment, Region};
+    use crate::segoff::Seg;
+
+    let dat = [
+      0x85, 0xc9,        // 0000: test cx,cx
+      0x74, 0x04,        // 0002: je 0x8
+      0xb8, 0x01, 0x00,  // 0004: mov ax,0x1
+      0xc3,              // 0007: ret
+      0x33, 0xc0,        // 0008: xor ax,ax
+      0x99,              // 000a: cwd
+      0xc3,              // 000b: ret
+    ];
+    let binary = Binary::from_raw(&dat, None);
+    let code_seg = CodeSegment { primary: Region { seg: Seg::Normal(0), skip_off: 0, size: dat.len() as u32 }, stub: None };
+    let func = FuncDetails::build(SegOff::new(0, 0), &code_seg, &binary).unwrap();
+
+    assert_eq!(written_before_return(&func), RegSet::of(&[Reg::AX]));
+    let (reads, at_return) = reads_before_writes(&func, func.start_addr, RegSet::ALL);
+    assert_eq!(reads, RegSet::of(&[Reg::CX]));
+    assert_eq!(at_return, RegSet::ALL.minus(RegSet::of(&[Reg::AX])));
+  }
+
+  #[test]
+  fn display() {
+    assert_eq!(RegSet::of(&[Reg::DL, Reg::AX]).to_string(), "AX,DL");
+    assert_eq!(RegSet::of(&[Reg::DL, Reg::DH]).to_string(), "DX");
+    assert!(RegSet::of(&[Reg::AH]).contains(Reg::AX));
+  }
+}
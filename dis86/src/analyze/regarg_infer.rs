@@ -0,0 +1,128 @@
+use super::func_details::FuncDetails;
+use super::reg_usage::{self, Effects, RegSet};
+use crate::asm::instr::{Instr, Opcode, Operand, Reg};
+use crate::config::Config;
+use crate::segoff::SegOff;
+use std::collections::BTreeMap;
+
+// How far back from a call we look for the caller setting up registers
+const MAX_LOOKBEHIND: usize = 16;
+
+// Everything observed about the registers a function takes as arguments
+#[derive(Default)]
+pub struct RegargEvidence {
+  pub regs: RegSet,                        // read before being written at entry
+  pub set_up_by: BTreeMap<SegOff, RegSet>, // call site => registers the caller writes just before the call
+}
+
+impl RegargEvidence {
+  // Call sites that don't write all of the inferred registers, and what they're missing
+  pub fn unconfirmed(&self) -> Vec<(SegOff, RegSet)> {
+    self.set_up_by.iter()
+      .map(|(site, written)| (*site, self.regs.minus(*written)))
+      .filter(|(_, missing)| !missing.is_empty())
+      .collect()
+  }
+}
+
+// BP and SP belong to the stack frame under the normal convention (and segment registers aren't tracked)
+fn candidates() -> RegSet {
+  RegSet::ALL.minus(RegSet::of(&[Reg::SP, Reg::BP]))
+}
+
+// Effects while looking for arguments at entry: saving a register isn't a use of it, calls read the
+// callee's own regargs, and the search gives up on anything unmodeled rather than assuming a read
+fn entry_effects(ins: &Instr, cfg: &Config) -> Effects {
+  let Some(mut eff) = reg_usage::effects(ins) else {
+    return Effects { reads: RegSet::EMPTY, writes: RegSet::ALL };
+  };
+  match ins.opcode {
+    Opcode::OP_PUSH if matches!(ins.operands[0], Operand::Reg(_)) => eff.reads = RegSet::EMPTY,
+    Opcode::OP_CALL | Opcode::OP_CALLF => {
+      let target = match ins.operands[0] {
+        Operand::Rel(rel) => Some(ins.rel_addr(&rel)),
+        _ => None,
+      };
+      if let Some(regargs) = target.and_then(|t| cfg.func_lookup(t)).and_then(|f| f.regargs.as_ref()) {
+        eff.reads = eff.reads.union(RegSet::of(regargs));
+      }
+    }
+    _ => (),
+  }
+  eff
+}
+
+// Registers written by the straight-line code leading up to `addr`
+fn written_before(func: &FuncDetails, addr: SegOff) -> RegSet {
+  let mut written = RegSet::EMPTY;
+  let mut cur = addr;
+  for _ in 0..MAX_LOOKBEHIND {
+    let Some((prev_addr, prev)) = func.instrs.range(..cur).next_back() else { break };
+    if prev.end_addr() != cur || func.succs.get(prev_addr) != Some(&vec![cur]) { break; }
+    if matches!(prev.opcode, Opcode::OP_CALL | Opcode::OP_CALLF) { break; }
+    let Some(eff) = reg_usage::effects(prev) else { break };
+    written = written.union(eff.writes);
+    cur = *prev_addr;
+  }
+  written
+}
+
+// Collect the evidence for every function, keyed by function start
+pub fn gather(functions: &BTreeMap<SegOff, Result<FuncDetails, String>>, cfg: &Config) -> BTreeMap<SegOff, RegargEvidence> {
+  // Calls into overlays target the stub, which the config knows as the function's entry
+  let resolve = |addr: SegOff| cfg.func_lookup(addr).map(|f| f.start).unwrap_or(addr);
+
+  let mut evidence: BTreeMap<SegOff, RegargEvidence> = BTreeMap::new();
+  for (addr, result) in functions {
+    let Ok(details) = result else { continue };
+    if resolve(*addr) != *addr { continue; }
+    let (regs, _) = reg_usage::reads_before_writes_with(details, *addr, candidates(), |ins| Some(entry_effects(ins, cfg)));
+    evidence.entry(*addr).or_default().regs = regs;
+    for site in &details.call_sites {
+      let ev = evidence.entry(resolve(site.target)).or_default();
+      ev.set_up_by.insert(site.addr, written_before(details, site.addr));
+    }
+  }
+  evidence
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::analyze::code_segment::{CodeSegment, Region};
+  use crate::binary::Binary;
+  use crate::segoff::Seg;
+
+  #[test]
+  fn helper_and_caller() {
+    let dat = [
+      // caller
+      0xbe, 0x34, 0x12,  // 0000: mov si,0x1234
+      0xb1, 0x02,        // 0003: mov cl,0x2
+      0xe8, 0x01, 0x00,  // 0005: call 0x9
+      0xc3,              // 0008: ret
+      // helper: expects SI and CX
+      0x50,              // 0009: push ax
+      0x8b, 0x04,        // 000a: mov ax,WORD PTR ds:[si]
+      0xd3, 0xe0,        // 000c: shl ax,cl
+      0x89, 0x04,        // 000e: mov WORD PTR ds:[si],ax
+      0x58,              // 0010: pop ax
+      0xc3,              // 0011: ret
+    ];
+    let binary = Binary::from_raw(&dat, None);
+    let code_seg = CodeSegment { primary: Region { seg: Seg::Normal(0), skip_off: 0, size: dat.len() as u32 }, stub: None };
+    let mut functions = BTreeMap::new();
+    for start in [0x0, 0x9] {
+      let addr = SegOff::new(0, start);
+      functions.insert(addr, FuncDetails::build(addr, &code_seg, &binary));
+    }
+
+    let evidence = gather(&functions, &Config::empty());
+    assert!(evidence[&SegOff::new(0, 0x0)].regs.is_empty());
+
+    let helper = &evidence[&SegOff::new(0, 0x9)];
+    assert_eq!(helper.regs, RegSet::of(&[Reg::SI, Reg::CL]));
+    assert_eq!(helper.set_up_by[&SegOff::new(0, 0x5)], RegSet::of(&[Reg::SI, Reg::CL]));
+    assert!(helper.unconfirmed().is_empty());
+  }
+}
@@ -0,0 +1,137 @@
+use super::func_details::FuncDetails;
+use super::reg_usage::{self, RegSet};
+use crate::asm::instr::Reg;
+use crate::config::Config;
+use crate::segoff::SegOff;
+use crate::types::Type;
+use std::collections::BTreeMap;
+
+// Everything observed about how a function's return value is produced and consumed
+#[derive(Default)]
+pub struct RetEvidence {
+  pub written: RegSet,                   // AX/DX written on every path to a return
+  pub read_by: BTreeMap<SegOff, RegSet>, // call site => AX/DX read by the caller after the call
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RetInference {
+  Unknown, // never called
+  Type(Type),
+  Conflict(String),
+}
+
+fn ret_regs() -> RegSet {
+  RegSet::of(&[Reg::AX, Reg::DX])
+}
+
+impl RetEvidence {
+  pub fn infer(&self) -> RetInference {
+    if self.read_by.is_empty() {
+      return RetInference::Unknown;
+    }
+    let used = self.read_by.values().fold(RegSet::EMPTY, |acc, r| acc.union(*r));
+
+    let (typ, needed) = if used.contains(Reg::DX) {
+      (Type::U32, ret_regs())
+    } else if used.contains(Reg::AH) {
+      (Type::U16, RegSet::of(&[Reg::AX]))
+    } else if used.contains(Reg::AL) {
+      (Type::U8, RegSet::of(&[Reg::AL]))
+    } else {
+      return RetInference::Type(Type::Void);
+    };
+
+    let missing = needed.minus(self.written);
+    if !missing.is_empty() {
+      let sites: Vec<String> = self.read_by.iter()
+        .filter(|(_, r)| !r.intersect(missing).is_empty())
+        .map(|(site, _)| site.to_string())
+        .collect();
+      return RetInference::Conflict(format!("callers read {} at [{}], but the callee doesn't always write it",
+                                            missing, sites.join(", ")));
+    }
+    RetInference::Type(typ)
+  }
+}
+
+// Size of the value in DX:AX, or None if the type doesn't tell us
+pub fn ret_size(typ: &Type) -> Option<usize> {
+  match typ {
+    Type::Void => Some(0),
+    typ => typ.size_in_bytes().map(|sz| if sz <= 2 { 2 } else { sz }),
+  }
+}
+
+// Collect the evidence for every function, keyed by function start
+pub fn gather(functions: &BTreeMap<SegOff, Result<FuncDetails, String>>, cfg: &Config) -> BTreeMap<SegOff, RetEvidence> {
+  // Calls into overlays target the stub, which the config knows as the function's entry
+  let resolve = |addr: SegOff| cfg.func_lookup(addr).map(|f| f.start).unwrap_or(addr);
+
+  struct Site { callee: SegOff, caller: SegOff, addr: SegOff, reads: RegSet, passthrough: RegSet }
+
+  let mut evidence: BTreeMap<SegOff, RetEvidence> = BTreeMap::new();
+  let mut sites = vec![];
+  for (addr, result) in functions {
+    let Ok(details) = result else { continue };
+    if resolve(*addr) != *addr { continue; }
+    evidence.entry(*addr).or_default().written = reg_usage::written_before_return(details).intersect(ret_regs());
+    for site in &details.call_sites {
+      let after = details.instrs[&site.addr].end_addr();
+      let (reads, passthrough) = reg_usage::reads_before_writes(details, after, ret_regs());
+      sites.push(Site { callee: resolve(site.target), caller: *addr, addr: site.addr, reads, passthrough });
+    }
+  }
+
+  // A caller that returns the value untouched passes it on to its own callers: iterate to a fixed point
+  let mut used: BTreeMap<SegOff, RegSet> = BTreeMap::new();
+  loop {
+    let mut changed = false;
+    for site in &sites {
+      let passed = site.passthrough.intersect(used.get(&site.caller).copied().unwrap_or_default());
+      let reads = site.reads.union(passed);
+      let ev = evidence.entry(site.callee).or_default();
+      let prev = ev.read_by.insert(site.addr, reads);
+      if prev != Some(reads) {
+        let u = used.entry(site.callee).or_default();
+        *u = u.union(reads);
+        changed = true;
+      }
+    }
+    if !changed { break; }
+  }
+
+  evidence
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn evidence(written: &[Reg], reads: &[&[Reg]]) -> RetEvidence {
+    let mut ev = RetEvidence { written: RegSet::of(written), read_by: BTreeMap::new() };
+    for (i, r) in reads.iter().enumerate() {
+      ev.read_by.insert(SegOff::new(0x10, i as u16), RegSet::of(r));
+    }
+    ev
+  }
+
+  #[test]
+  fn infer() {
+    assert_eq!(evidence(&[Reg::AX, Reg::DX], &[]).infer(), RetInference::Unknown);
+    assert_eq!(evidence(&[Reg::AX, Reg::DX], &[&[], &[]]).infer(), RetInference::Type(Type::Void));
+    assert_eq!(evidence(&[Reg::AX, Reg::DX], &[&[Reg::AX], &[]]).infer(), RetInference::Type(Type::U16));
+    assert_eq!(evidence(&[Reg::AX, Reg::DX], &[&[Reg::AX], &[Reg::AX, Reg::DX]]).infer(), RetInference::Type(Type::U32));
+    assert_eq!(evidence(&[Reg::AL], &[&[Reg::AL]]).infer(), RetInference::Type(Type::U8));
+
+    let RetInference::Conflict(msg) = evidence(&[Reg::AX], &[&[Reg::AX], &[Reg::DX]]).infer() else { panic!() };
+    assert_eq!(msg, "callers read DX at [0010:0001], but the callee doesn't always write it");
+  }
+
+  #[test]
+  fn sizes() {
+    assert_eq!(ret_size(&Type::Void), Some(0));
+    assert_eq!(ret_size(&Type::U8), Some(2));
+    assert_eq!(ret_size(&Type::I32), Some(4));
+    assert_eq!(ret_size(&Type::Unknown), None);
+  }
+}
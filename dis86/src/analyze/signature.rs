@@ -0,0 +1,364 @@
+use crate::asm::decode::Decoder;
+use crate::asm::instr::{Opcode, Operand, Reg, Size};
+use crate::asm::intel_syntax::{Symbols, INDEXED_DISP_MIN};
+use crate::binary::Binary;
+use crate::config::{CallMode, Func};
+use crate::segoff::{Off, Seg, SegOff};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+// Leading bytes of a function matched byte-for-byte (with masking)
+pub const PATTERN_LEN: usize = 32;
+
+// Signatures with fewer fixed bytes than this match far too much to be useful
+pub const MIN_FIXED_BYTES: usize = 12;
+
+// A library function recognizable across binaries, in the spirit of IDA's FLIRT .pat lines:
+//
+//   <pattern> <crc_len> <crc> <size> <mode> <ret> <args> <name>
+//   558BEC8B5E06D1E3A1....03870000E8....5DCB........................ 00 0000 0014 far None 1 _getfoo
+//
+// The pattern has two hex digits per byte, with ".." for bytes that depend on where the function
+// was linked (relocations, calls out of the function, global addresses and offsets) and for the padding past
+// the end of short functions. The crc covers the bytes after the pattern up to the first masked one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+  pub name: String,
+  pub mode: CallMode,
+  pub ret: Option<String>, // as written in the config the signature was built from
+  pub args: Option<u16>,
+  pub size: u16,
+  pub pattern: Vec<Option<u8>>,
+  pub crc_len: u8,
+  pub crc: u16,
+}
+
+// CRC-16/X-25, as used by FLIRT
+pub fn crc16(dat: &[u8]) -> u16 {
+  let mut crc: u16 = 0xffff;
+  for b in dat {
+    crc ^= *b as u16;
+    for _ in 0..8 {
+      crc = if crc & 1 != 0 { (crc >> 1) ^ 0x8408 } else { crc >> 1 };
+    }
+  }
+  !crc
+}
+
+// Addresses (main exe only) of every word the loader patches with the load segment
+pub fn reloc_addrs(binary: &Binary) -> HashSet<usize> {
+  let Some(exe) = binary.exe() else { return HashSet::new() };
+  exe.relocs.iter().map(|r| (r.segment as usize) * 16 + (r.offset as usize)).collect()
+}
+
+// The bytes of [start, end) with everything that depends on where things were linked masked out
+pub fn masked_bytes(binary: &Binary, start: SegOff, end: SegOff, relocs: &HashSet<usize>) -> Vec<Option<u8>> {
+  let mut out: Vec<Option<u8>> = binary.region(start, end).iter().map(|b| Some(*b)).collect();
+  let mut mask = |idx: usize, len: usize| {
+    for b in out.iter_mut().skip(idx).take(len) { *b = None; }
+  };
+
+  if let Seg::Normal(_) = start.seg {
+    for i in 0..start.offset_to(end) as usize {
+      if relocs.contains(&(start.abs_normal() + i)) { mask(i, 2); }
+    }
+  }
+
+  // Linear sweep: anything after undecodable bytes is left as-is
+  let mut decoder = Decoder::new(binary.region_iter(start, end));
+  while let Ok(Some((ins, raw))) = decoder.try_next() {
+    let idx = start.offset_to(ins.addr) as usize;
+    let body = ins.prefixes.len() + 1;
+    for op in &ins.operands {
+      match op {
+        // Near calls and jumps out of the function: the rel16 is the last two bytes
+        Operand::Rel(rel) if matches!(ins.opcode, Opcode::OP_CALL | Opcode::OP_JMP) && raw.len() == body + 2 => {
+          let target = ins.rel_addr(rel);
+          if target < start || target >= end { mask(idx + body, 2); }
+        }
+        Operand::Far(_) => mask(idx + body, 4),
+        // Globals, e.g. "mov ax,WORD PTR ds:[0x1234]"
+        Operand::Mem(m) if m.reg1.is_none() && m.reg2.is_none() => {
+          let Some(off) = m.off else { continue };
+          let needle = off.to_le_bytes();
+          if let Some(pos) = raw[body..].windows(2).position(|w| w == needle) {
+            mask(idx + body + pos, 2);
+          }
+        }
+        // Global arrays, e.g. "mov ax,WORD PTR [bx+0x1234]": only a 16-bit displacement (mod 10) can be one
+        Operand::Mem(m) if m.sreg == Reg::DS && raw.get(body).is_some_and(|modrm| modrm >> 6 == 2) => {
+          let Some(off) = m.off else { continue };
+          if off >= INDEXED_DISP_MIN || binary.data_symbol(off).is_some() { mask(idx + body + 1, 2); }
+        }
+        // Addresses of globals, e.g. "mov ax,offset g_foo" or "push offset g_foo": the immediate is last
+        Operand::Imm(imm) if imm.sz == Size::Size16 && raw.len() >= body + 2 && binary.data_symbol(imm.val).is_some() => {
+          mask(idx + raw.len() - 2, 2);
+        }
+        _ => (),
+      }
+    }
+  }
+  out
+}
+
+impl Signature {
+  // Build the signature of a config function that has a known end
+  pub fn build(func: &Func, binary: &Binary, relocs: &HashSet<usize>) -> Result<Signature, String> {
+    let Some(end) = func.end else {
+      return Err(format!("{}: no end address", func.name));
+    };
+    if end.seg != func.start.seg || end <= func.start {
+      return Err(format!("{}: bad range {} to {}", func.name, func.start, end));
+    }
+    let bytes = masked_bytes(binary, func.start, end, relocs);
+
+    let mut pattern: Vec<Option<u8>> = bytes.iter().take(PATTERN_LEN).copied().collect();
+    pattern.resize(PATTERN_LEN, None);
+    let tail: Vec<u8> = bytes.iter().skip(PATTERN_LEN).take(u8::MAX as usize).map_while(|b| *b).collect();
+
+    let fixed = pattern.iter().filter(|b| b.is_some()).count() + tail.len();
+    if fixed < MIN_FIXED_BYTES {
+      return Err(format!("{}: only {} fixed bytes", func.name, fixed));
+    }
+
+    Ok(Signature {
+      name: func.name.clone(),
+      mode: func.mode,
+      ret: func.ret.as_ref().map(|t| t.to_string()),
+      args: func.args,
+      size: bytes.len() as u16,
+      pattern,
+      crc_len: tail.len() as u8,
+      crc: crc16(&tail),
+    })
+  }
+
+  // Does the function at the start of `dat` match?
+  pub fn matches(&self, dat: &[u8]) -> bool {
+    if dat.len() < self.size as usize { return false; }
+    let pattern_ok = self.pattern.iter().zip(dat).all(|(p, b)| p.is_none_or(|p| p == *b));
+    if !pattern_ok { return false; }
+    let tail = &dat[PATTERN_LEN.min(dat.len())..];
+    tail.len() >= self.crc_len as usize && crc16(&tail[..self.crc_len as usize]) == self.crc
+  }
+}
+
+impl fmt::Display for Signature {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for b in &self.pattern {
+      match b {
+        Some(b) => write!(f, "{:02X}", b)?,
+        None    => write!(f, "..")?,
+      }
+    }
+    let mode = match self.mode {
+      CallMode::Near => "near",
+      CallMode::Far  => "far",
+    };
+    let ret = self.ret.as_deref().unwrap_or("None");
+    let args = self.args.map(|n| n.to_string()).unwrap_or("None".to_string());
+    write!(f, " {:02X} {:04X} {:04X} {} {} {} {}", self.crc_len, self.crc, self.size, mode, ret, args, self.name)
+  }
+}
+
+impl FromStr for Signature {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, String> {
+    let err = |what: &str| format!("Invalid signature '{}': {}", s, what);
+    let fields: Vec<&str> = s.split_whitespace().collect();
+    let [pat, crc_len, crc, size, mode, ret, args, name] = fields[..] else {
+      return Err(err("expected 8 fields"));
+    };
+
+    if pat.len() != 2 * PATTERN_LEN || !pat.is_ascii() {
+      return Err(err("bad pattern length"));
+    }
+    let mut pattern = vec![];
+    for i in 0..PATTERN_LEN {
+      let b = &pat[2*i .. 2*i+2];
+      pattern.push(match b {
+        ".." => None,
+        _ => Some(u8::from_str_radix(b, 16).map_err(|_| err("bad pattern byte"))?),
+      });
+    }
+
+    let mode = match mode {
+      "near" => CallMode::Near,
+      "far"  => CallMode::Far,
+      _ => return Err(err("bad mode")),
+    };
+    let ret = if ret == "None" { None } else { Some(ret.to_string()) };
+    let args = if args == "None" { None } else { Some(args.parse().map_err(|_| err("bad args"))?) };
+
+    Ok(Signature {
+      name: name.to_string(),
+      mode,
+      ret,
+      args,
+      size: u16::from_str_radix(size, 16).map_err(|_| err("bad size"))?,
+      pattern,
+      crc_len: u8::from_str_radix(crc_len, 16).map_err(|_| err("bad crc length"))?,
+      crc: u16::from_str_radix(crc, 16).map_err(|_| err("bad crc"))?,
+    })
+  }
+}
+
+// A signature file: one signature per line, '#' starts a comment
+pub fn parse(text: &str) -> Result<Vec<Signature>, String> {
+  text.lines()
+    .map(|line| line.split('#').next().unwrap().trim())
+    .filter(|line| !line.is_empty())
+    .map(|line| line.parse())
+    .collect()
+}
+
+pub fn load(path: &str) -> Result<Vec<Signature>, String> {
+  let text = std::fs::read_to_string(path).map_err(|err| format!("Failed to read '{}': {:?}", path, err))?;
+  parse(&text)
+}
+
+#[derive(Debug)]
+pub struct Match<'a> {
+  pub start: SegOff,
+  pub sigs: Vec<&'a Signature>, // more than one when identical code goes by several names
+}
+
+impl Match<'_> {
+  pub fn end(&self) -> SegOff {
+    self.start.add_offset(self.sigs[0].size)
+  }
+}
+
+// Scan [start, end) at every offset, skipping past each match
+pub fn scan<'a>(binary: &Binary, start: SegOff, end: SegOff, sigs: &'a [Signature]) -> Vec<Match<'a>> {
+  // Index by first byte so most offsets only try a handful of signatures
+  let mut by_first: HashMap<u8, Vec<&Signature>> = HashMap::new();
+  let mut wildcard = vec![];
+  for sig in sigs {
+    match sig.pattern[0] {
+      Some(b) => by_first.entry(b).or_default().push(sig),
+      None => wildcard.push(sig),
+    }
+  }
+
+  let dat = binary.region(start, end);
+  let mut matches = vec![];
+  let mut i = 0;
+  while i < dat.len() {
+    let candidates = by_first.get(&dat[i]).into_iter().flatten().chain(&wildcard);
+    let found: Vec<&Signature> = candidates.copied().filter(|sig| sig.matches(&dat[i..])).collect();
+    let Some(longest) = found.iter().map(|sig| sig.size).max() else {
+      i += 1;
+      continue;
+    };
+    let sigs: Vec<&Signature> = found.into_iter().filter(|sig| sig.size == longest).collect();
+    matches.push(Match { start: SegOff { seg: start.seg, off: Off(start.off.0 + i as u16) }, sigs });
+    i += longest as usize;
+  }
+  matches
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::{Config, Global};
+  use crate::types::Type;
+
+  // A small library function: "int getfoo(int) { return g_foo[arg]; }" plus a call to a helper
+  const FUNC: [u8; 20] = [
+    0x55,                   // 0000: push bp
+    0x8b, 0xec,             // 0001: mov bp,sp
+    0x8b, 0x5e, 0x06,       // 0003: mov bx,WORD PTR [bp+0x6]
+    0xd1, 0xe3,             // 0006: shl bx,1
+    0xa1, 0x34, 0x12,       // 0008: mov ax,WORD PTR ds:[0x1234]
+    0x03, 0x87, 0x00, 0x00, // 000b: add ax,WORD PTR [bx+0x0]
+    0xe8, 0x00, 0x10,       // 000f: call helper
+    0x5d,                   // 0012: pop bp
+    0xcb,                   // 0013: retf
+  ];
+
+  fn func(name: &str, start: u16, size: u16) -> Func {
+    Func {
+      name: name.to_string(), start: SegOff::new(0, start), end: Some(SegOff::new(0, start + size)),
+      entry: None, mode: CallMode::Far, ret: None, args: Some(1), regargs: None, dont_pop_args: false, locals: vec![],
+    }
+  }
+
+  #[test]
+  fn crc() {
+    assert_eq!(crc16(b"123456789"), 0x906e);
+  }
+
+  #[test]
+  fn masking() {
+    let binary = Binary::from_raw(&FUNC, None);
+    let bytes = masked_bytes(&binary, SegOff::new(0, 0), SegOff::new(0, 0x14), &HashSet::new());
+    let masked: Vec<usize> = (0..bytes.len()).filter(|i| bytes[*i].is_none()).collect();
+    assert_eq!(masked, vec![0x9, 0xa, 0x10, 0x11]);
+
+    let relocs = HashSet::from([0xd]);
+    let bytes = masked_bytes(&binary, SegOff::new(0, 0), SegOff::new(0, 0x14), &relocs);
+    assert!(bytes[0xd].is_none() && bytes[0xe].is_none());
+  }
+
+  #[test]
+  fn masking_indexed_and_offsets() {
+    let dat = [
+      0x8b, 0x87, 0x00, 0x20, // 0000: mov ax,WORD PTR [bx+0x2000]
+      0x8b, 0x47, 0x10,       // 0004: mov ax,WORD PTR [bx+0x10]
+      0x8b, 0x87, 0x10, 0x00, // 0007: mov ax,WORD PTR [bx+0x10]
+      0x8b, 0x86, 0x00, 0x20, // 000b: mov ax,WORD PTR [bp+0x2000]
+      0xbe, 0x12, 0x00,       // 000f: mov si,0x12
+      0x68, 0x13, 0x00,       // 0012: push 0x13
+      0xb8, 0x40, 0x00,       // 0015: mov ax,0x40
+      0xcb,                   // 0018: retf
+    ];
+    let masked = |cfg: Option<&Config>| {
+      let binary = Binary::from_raw(&dat, cfg);
+      let bytes = masked_bytes(&binary, SegOff::new(0, 0), SegOff::new(0, dat.len() as u16), &HashSet::new());
+      (0..bytes.len()).filter(|i| bytes[*i].is_none()).collect::<Vec<usize>>()
+    };
+    assert_eq!(masked(None), vec![0x2, 0x3]);
+
+    // With a global at 0x10, its index and address are masked too
+    let mut cfg = Config::empty();
+    cfg.globals = vec![Global { name: "g_foo".to_string(), offset: 0x10, typ: Type::U32 }];
+    assert_eq!(masked(Some(&cfg)), vec![0x2, 0x3, 0x9, 0xa, 0x10, 0x11, 0x13, 0x14]);
+  }
+
+  #[test]
+  fn build_and_match() {
+    let binary = Binary::from_raw(&FUNC, None);
+    let sig = Signature::build(&func("_getfoo", 0, 0x14), &binary, &HashSet::new()).unwrap();
+    assert_eq!(sig.size, 0x14);
+    assert_eq!(sig.crc_len, 0);
+
+    let text = sig.to_string();
+    assert_eq!(text, format!("558BEC8B5E06D1E3A1....03870000E8....5DCB{} 00 0000 0014 far None 1 _getfoo", "..".repeat(12)));
+    assert_eq!(text.parse::<Signature>().unwrap(), sig);
+
+    // Linked elsewhere, with a different global and helper address
+    let mut other = vec![0x90, 0x90, 0x90];
+    other.extend_from_slice(&FUNC);
+    other[3 + 0x9] = 0x78;
+    other[3 + 0x10] = 0x55;
+    let binary = Binary::from_raw(&other, None);
+    let sigs = vec![sig];
+    let found = scan(&binary, SegOff::new(0, 0), SegOff::new(0, other.len() as u16), &sigs);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].start, SegOff::new(0, 3));
+    assert_eq!(found[0].end(), SegOff::new(0, 0x17));
+
+    // Anything else in the fixed bytes doesn't match
+    other[3 + 0x4] = 0x5f;
+    let binary = Binary::from_raw(&other, None);
+    assert!(scan(&binary, SegOff::new(0, 0), SegOff::new(0, other.len() as u16), &sigs).is_empty());
+  }
+
+  #[test]
+  fn too_short() {
+    let binary = Binary::from_raw(&[0x55, 0x8b, 0xec, 0x5d, 0xcb], None);
+    assert!(Signature::build(&func("_nop", 0, 5), &binary, &HashSet::new()).is_err());
+  }
+}
@@ -0,0 +1,158 @@
+use super::func_details::FuncDetails;
+use super::reg_usage;
+use crate::asm::instr::{Opcode, Operand};
+use crate::binary::Binary;
+use crate::config::Config;
+use crate::segoff::SegOff;
+use crate::types::{ArraySize, Type};
+use std::collections::BTreeMap;
+
+// Shorter printable runs are mostly coincidences in tables of small numbers
+pub const MIN_LEN: usize = 4;
+
+// How far back from a "push reg" we look for the "mov reg,imm" that loaded it
+const MAX_LOOKBEHIND: usize = 4;
+
+fn is_printable(b: u8) -> bool {
+  (0x20..0x7f).contains(&b) || b == b'\t' || b == b'\n' || b == b'\r'
+}
+
+// The NUL-terminated printable string at the start of `dat` (without the NUL)
+pub fn string_at(dat: &[u8]) -> Option<&[u8]> {
+  let len = dat.iter().position(|b| !is_printable(*b))?;
+  if len == 0 || dat[len] != 0 { return None; }
+  Some(&dat[..len])
+}
+
+// Every run of at least MIN_LEN printable bytes followed by a NUL, as (offset, text)
+pub fn scan(dat: &[u8], base: u16) -> Vec<(u16, &[u8])> {
+  let mut out = vec![];
+  let mut i = 0;
+  while i < dat.len() {
+    match string_at(&dat[i..]) {
+      Some(text) if text.len() >= MIN_LEN => {
+        out.push((base.wrapping_add(i as u16), text));
+        i += text.len() + 1;
+      }
+      _ => i += 1,
+    }
+  }
+  out
+}
+
+// As a C string literal, safe to put inside a comment
+pub fn c_literal(text: &[u8]) -> String {
+  let mut s = String::from("\"");
+  for b in text {
+    match *b {
+      b'\\' => s += "\\\\",
+      b'"'  => s += "\\\"",
+      b'\n' => s += "\\n",
+      b'\r' => s += "\\r",
+      b'\t' => s += "\\t",
+      b if is_printable(b) => s.push(b as char),
+      b => s += &format!("\\x{:02x}", b),
+    }
+  }
+  s += "\"";
+  s.replace("*/", "*\\/")
+}
+
+// Immediate values pushed (directly, or just loaded into the pushed register) => the pushes
+pub fn pushed_immediates(functions: &BTreeMap<SegOff, Result<FuncDetails, String>>) -> BTreeMap<u16, Vec<SegOff>> {
+  let mut out: BTreeMap<u16, Vec<SegOff>> = BTreeMap::new();
+  for result in functions.values() {
+    let Ok(details) = result else { continue };
+    for ins in details.instrs.values() {
+      if ins.opcode != Opcode::OP_PUSH { continue; }
+      let val = match ins.operands[0] {
+        Operand::Imm(imm) => Some(imm.val),
+        Operand::Reg(r) => {
+          // e.g. "mov ax,0x412 ; push ds ; push ax"
+          let mut found = None;
+          let mut cur = ins.addr;
+          for _ in 0..MAX_LOOKBEHIND {
+            let Some((_, prev)) = details.instrs.range(..cur).next_back() else { break };
+            if prev.end_addr() != cur { break; }
+            if prev.opcode == Opcode::OP_MOV && prev.operands[0] == Operand::Reg(r) {
+              if let Operand::Imm(imm) = prev.operands[1] { found = Some(imm.val); }
+              break;
+            }
+            let Some(eff) = reg_usage::effects(prev) else { break };
+            if eff.writes.contains(r.0) { break; }
+            cur = prev.addr;
+          }
+          found
+        }
+        _ => None,
+      };
+      if let Some(val) = val {
+        out.entry(val).or_default().push(ins.addr);
+      }
+    }
+  }
+  out
+}
+
+// Globals the config types as u8[N] that hold a string, keyed by offset, as C literals
+pub fn config_strings(cfg: &Config, binary: &Binary) -> BTreeMap<u16, String> {
+  let mut out = BTreeMap::new();
+  let Some((ds_start, ds_end)) = binary.data_segment() else { return out };
+  let dat = binary.region(ds_start, ds_end);
+  for g in &cfg.globals {
+    let Type::Array(elem, ArraySize::Known(n)) = &g.typ else { continue };
+    if **elem != Type::U8 { continue; }
+    let start = g.offset as usize;
+    let end = std::cmp::min(start + n, dat.len());
+    if start >= end { continue; }
+    if let Some(text) = string_at(&dat[start..end]) {
+      out.insert(g.offset, c_literal(text));
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::analyze::code_segment::{CodeSegment, Region};
+  use crate::segoff::Seg;
+
+  #[test]
+  fn scanning() {
+    let dat = b"\x01\x02abc\0Hello %d\n\0\xff\xffdata.bin\0xyzzy";
+    let found: Vec<(u16, &[u8])> = scan(dat, 0x100);
+    assert_eq!(found, vec![(0x106, &b"Hello %d\n"[..]), (0x112, &b"data.bin"[..])]);
+    assert_eq!(string_at(b"ab\0"), Some(&b"ab"[..]));
+    assert_eq!(string_at(b"ab"), None);
+  }
+
+  #[test]
+  fn literals() {
+    assert_eq!(c_literal(b"say \"hi\"\n"), "\"say \\\"hi\\\"\\n\"");
+    assert_eq!(c_literal(b"/* x */"), "\"/* x *\\/\"");
+  }
+
+  #[test]
+  fn pushes() {
+    let dat = [
+      0xb8, 0x12, 0x04, // 0000: mov ax,0x412
+      0x1e,             // 0003: push ds
+      0x50,             // 0004: push ax
+      0x68, 0x34, 0x05, // 0005: push 0x534
+      0xb8, 0x00, 0x06, // 0008: mov ax,0x600
+      0x40,             // 000b: inc ax
+      0x50,             // 000c: push ax
+      0xc3,             // 000d: ret
+    ];
+    let binary = Binary::from_raw(&dat, None);
+    let code_seg = CodeSegment { primary: Region { seg: Seg::Normal(0), skip_off: 0, size: dat.len() as u32 }, stub: None };
+    let start = SegOff::new(0, 0);
+    let mut functions = BTreeMap::new();
+    functions.insert(start, FuncDetails::build(start, &code_seg, &binary));
+
+    let pushed = pushed_immediates(&functions);
+    assert_eq!(pushed.keys().copied().collect::<Vec<_>>(), vec![0x412, 0x534]);
+    assert_eq!(pushed[&0x412], vec![SegOff::new(0, 4)]);
+  }
+}
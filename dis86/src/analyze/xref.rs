@@ -0,0 +1,179 @@
+use super::func_details::FuncDetails;
+use super::reg_usage::{self, Acc};
+use crate::asm::instr::{Instr, Operand, Reg, Size};
+use crate::config::{Config, Global, TextSectionRegion};
+use crate::segoff::{Off, SegOff};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+// What a memory operand refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Target {
+  Data(u16),    // DS-relative
+  Text(SegOff), // CS-relative, in the code segment of the accessing instruction
+}
+
+impl fmt::Display for Target {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Target::Data(off) => write!(f, "ds:{:04x}", off),
+      Target::Text(addr) => write!(f, "cs:{}", addr),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+  Read,
+  Write,
+  ReadWrite,
+}
+
+impl fmt::Display for AccessKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      AccessKind::Read      => write!(f, "R"),
+      AccessKind::Write     => write!(f, "W"),
+      AccessKind::ReadWrite => write!(f, "RW"),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Xref {
+  pub func: SegOff,       // start of the accessing function
+  pub addr: SegOff,       // the accessing instruction
+  pub size: Option<u16>,  // None for FPU state and the like
+  pub kind: AccessKind,
+  pub indexed: bool,      // "[bx+0x3a2c]": the target is the base of an array (or an unlucky struct field offset)
+}
+
+// Every DS-relative and CS-relative memory access with a constant address in the scanned functions
+#[derive(Debug, Default)]
+pub struct XrefDb {
+  pub xrefs: BTreeMap<Target, Vec<Xref>>,
+}
+
+fn size_in_bytes(sz: Size) -> Option<u16> {
+  match sz {
+    Size::Size8    => Some(1),
+    Size::Size16   => Some(2),
+    Size::Size32   => Some(4),
+    Size::Size64   => Some(8),
+    Size::Size80   => Some(10),
+    Size::SizeNone => None,
+  }
+}
+
+impl XrefDb {
+  pub fn build(functions: &BTreeMap<SegOff, Result<FuncDetails, String>>) -> XrefDb {
+    let mut db = XrefDb::default();
+    for (func, result) in functions {
+      let Ok(details) = result else { continue };
+      for ins in details.instrs.values() {
+        db.add_instr(*func, ins);
+      }
+    }
+    db
+  }
+
+  fn add_instr(&mut self, func: SegOff, ins: &Instr) {
+    // Unmodeled instructions are assumed to only read their memory operands
+    let acc = reg_usage::operand_access(ins).unwrap_or(&[]);
+    for (i, operand) in ins.operands.as_slice().iter().enumerate() {
+      let Operand::Mem(m) = operand else { continue };
+      let Some(off) = m.off else { continue };
+      // BP-based addressing is the stack frame
+      if m.reg1 == Some(Reg::BP) || m.reg2.is_some() { continue; }
+      let target = match m.sreg {
+        Reg::DS => Target::Data(off),
+        Reg::CS => Target::Text(SegOff { seg: ins.addr.seg, off: Off(off) }),
+        _ => continue,
+      };
+      let kind = match acc.get(i).copied().unwrap_or(Acc::R) {
+        Acc::R  => AccessKind::Read,
+        Acc::W  => AccessKind::Write,
+        Acc::RW => AccessKind::ReadWrite,
+      };
+      let xref = Xref { func, addr: ins.addr, size: size_in_bytes(m.sz), kind, indexed: m.reg1.is_some() };
+      self.xrefs.entry(target).or_default().push(xref);
+    }
+  }
+
+  // Accesses to anything in [start, end)
+  pub fn range(&self, start: Target, end: Target) -> impl Iterator<Item=(Target, &Xref)> {
+    self.xrefs.range(start..end).flat_map(|(t, xrefs)| xrefs.iter().map(move |x| (*t, x)))
+  }
+
+  pub fn global(&self, g: &Global) -> Vec<(Target, &Xref)> {
+    let end = g.offset as u32 + g.typ.size_in_bytes().unwrap_or(1) as u32;
+    self.xrefs.range(Target::Data(g.offset)..)
+      .take_while(|(t, _)| matches!(t, Target::Data(off) if (*off as u32) < end))
+      .flat_map(|(t, xrefs)| xrefs.iter().map(move |x| (*t, x)))
+      .collect()
+  }
+
+  pub fn text_region(&self, r: &TextSectionRegion) -> Vec<(Target, &Xref)> {
+    self.range(Target::Text(r.start), Target::Text(r.end)).collect()
+  }
+
+  // The distinct access sizes used at a target
+  pub fn sizes_at(&self, target: Target) -> BTreeSet<u16> {
+    self.xrefs.get(&target).into_iter().flatten().filter_map(|x| x.size).collect()
+  }
+
+  // DS offsets accessed directly that aren't covered by any annotated global
+  pub fn unannotated_data(&self, cfg: &Config) -> BTreeMap<u16, Vec<&Xref>> {
+    let mut out = BTreeMap::new();
+    for (target, xrefs) in &self.xrefs {
+      let Target::Data(off) = target else { continue };
+      if cfg.global_lookup(*off).is_some() { continue; }
+      let direct: Vec<&Xref> = xrefs.iter().filter(|x| !x.indexed).collect();
+      if !direct.is_empty() {
+        out.insert(*off, direct);
+      }
+    }
+    out
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::analyze::code_segment::{CodeSegment, Region};
+  use crate::binary::Binary;
+  use crate::segoff::Seg;
+
+  #[test]
+  fn accesses() {
+    let dat = [
+      0xa1, 0x2c, 0x3a,             // 0000: mov ax,WORD PTR ds:[0x3a2c]
+      0xff, 0x06, 0x2c, 0x3a,       // 0003: inc WORD PTR ds:[0x3a2c]
+      0xa2, 0x2e, 0x3a,             // 0007: mov BYTE PTR ds:[0x3a2e],al
+      0x8a, 0x87, 0x00, 0x40,       // 000a: mov al,BYTE PTR [bx+0x4000]
+      0x8b, 0x46, 0x06,             // 000e: mov ax,WORD PTR [bp+0x6]
+      0x2e, 0x8b, 0x1e, 0x20, 0x00, // 0011: mov bx,WORD PTR cs:[0x20]
+      0xc3,                         // 0016: ret
+    ];
+    let binary = Binary::from_raw(&dat, None);
+    let code_seg = CodeSegment { primary: Region { seg: Seg::Normal(0), skip_off: 0, size: dat.len() as u32 }, stub: None };
+    let start = SegOff::new(0, 0);
+    let mut functions = BTreeMap::new();
+    functions.insert(start, FuncDetails::build(start, &code_seg, &binary));
+
+    let db = XrefDb::build(&functions);
+    let targets: Vec<Target> = db.xrefs.keys().copied().collect();
+    assert_eq!(targets, vec![Target::Data(0x3a2c), Target::Data(0x3a2e), Target::Data(0x4000), Target::Text(SegOff::new(0, 0x20))]);
+
+    let kinds: Vec<AccessKind> = db.xrefs[&Target::Data(0x3a2c)].iter().map(|x| x.kind).collect();
+    assert_eq!(kinds, vec![AccessKind::Read, AccessKind::ReadWrite]);
+    assert_eq!(db.xrefs[&Target::Data(0x3a2e)][0].kind, AccessKind::Write);
+    assert!(db.xrefs[&Target::Data(0x4000)][0].indexed);
+
+    assert_eq!(db.sizes_at(Target::Data(0x3a2c)), BTreeSet::from([2]));
+    assert_eq!(db.range(Target::Data(0x3a2c), Target::Data(0x3a2f)).count(), 3);
+
+    let unannotated: Vec<u16> = db.unannotated_data(&Config::empty()).into_keys().collect();
+    assert_eq!(unannotated, vec![0x3a2c, 0x3a2e]);
+  }
+}
@@ -1,8 +1,8 @@
 use pico_args;
 use crate::binary::{self, Binary};
 use crate::segoff::SegOff;
-use crate::asm::decode::Decoder;
 use crate::asm::intel_syntax;
+use crate::asm::nasm_syntax;
 use crate::config::Config;
 use crate::decompile::ir;
 use crate::decompile::gen;
@@ -13,6 +13,10 @@ use crate::decompile::opt;
 use crate::decompile::fuse;
 use crate::decompile::control_flow;
 use crate::spec::{self, Spec};
+use crate::app_analyze;
+use crate::analyze::analyze::Analyze;
+use crate::analyze::strings;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
 
@@ -24,9 +28,27 @@ fn print_help() {
   println!("  --config          path to binary configuration file (required)");
   println!("  --binary-exe      path to MZ format exe on the filesystem (exactly 1 --binary-* flag required)");
   println!("  --binary-raw      path to raw binary on the filesystem (exactly 1 --binary-* flag required)");
+  println!("  --binary-com      path to COM file on the filesystem, addressed as 0000:0100 (exactly 1 --binary-* flag required)");
   println!("");
   println!("MODE: ANALYZE");
   println!("  --analyze         analyze the binary using the configuration annotations");
+  println!("  --analyze-discover walk all code reachable from the entry point and report a byte map");
+  println!("  --analyze-args    emit annotations with args inferred from 'ret N' and caller stack cleanup");
+  println!("  --analyze-ret     emit annotations with return types inferred from the callers' use of DX:AX");
+  println!("  --analyze-regargs emit annotations with regargs inferred from registers read before written at entry");
+  println!("  --lint            check the config for mistakes against the binary, reporting each with its BSL key");
+  println!("  --analyze-strings emit annotations for the NUL-terminated strings found in the data segment");
+  println!("  --analyze-globals emit BSL globals entries proposed from unannotated data segment accesses");
+  println!("  --analyze-xrefs   report memory accesses to a global/text region name, a DS offset (0x3a2c) or 'unannotated'");
+  println!("  --analyze-sigs    emit annotations for library functions matched by the given signature file");
+  println!("  --import-map      emit BSL code segments, functions and globals from a TLINK/LINK .MAP file, skipping those in the config");
+  println!("  --import-tdinfo   like --import-map, but from the Turbo Debugger info appended to the exe, with structs and locals");
+  println!("  --analyze-coverage emit the annotation coverage with gaps to stdout in the given format: 'json' or 'csv'");
+  println!("  --coverage-trend  compare the annotation coverage against a previous csv report (optional)");
+  println!("  --analyze-callgraph emit the call graph to stdout in the given format: 'dot' or 'json'");
+  println!("  --callgraph-codeseg restrict the call graph to one code segment by name (optional)");
+  println!("  --callgraph-func  restrict the call graph to the callers/callees of a function by name (optional)");
+  println!("  --callgraph-depth levels of callers/callees to include with --callgraph-func (default: 1)");
   println!("");
   println!("MODE: ADDRESS RANGE");
   println!("  --start-addr      start seg:off address (maybe required)");
@@ -40,6 +62,7 @@ fn print_help() {
   println!("");
   println!("EMIT MODES:");
   println!("  --emit-dis        path to emit disassembly (optional)");
+  println!("  --emit-nasm       path to emit reassemblable NASM disassembly (optional)");
   println!("  --emit-ir-initial path to emit initial unoptimized SSA IR (optional)");
   println!("  --emit-ir-presym  path to emit pre-symbolized SSA IR (optional)");
   println!("  --emit-ir-sym     path to emit symbolized SSA IR (optional)");
@@ -53,6 +76,7 @@ fn print_help() {
   println!("");
   println!("IR BUILD FLAGS:");
   println!("  --build-pin-all");
+  println!("  --infer-ret       use inferred return types for functions without a 'ret' in the config (requires --binary-exe)");
   println!("");
   println!("CODEGEN FLAGS:");
   println!("  --codegen-hydra   emit code that integrates well with the hydra runtime (optional)");
@@ -77,9 +101,19 @@ struct Args {
   name: Option<String>,
   codeseg_name: Option<String>,
 
-  analyze: bool,
+  analyze: Option<app_analyze::Mode>,
+  analyze_callgraph: Option<app_analyze::GraphFormat>,
+  analyze_sigs: Option<String>,
+  analyze_xrefs: Option<app_analyze::XrefQuery>,
+  analyze_coverage: Option<app_analyze::ReportFormat>,
+  import_map: Option<String>,
+  coverage_trend: Option<String>,
+  callgraph_codeseg: Option<String>,
+  callgraph_func: Option<String>,
+  callgraph_depth: Option<usize>,
 
   emit_dis: Option<String>,
+  emit_nasm: Option<String>,
   emit_ir_initial: Option<String>,
   emit_ir_presym: Option<String>,
   emit_ir_sym: Option<String>,
@@ -92,6 +126,7 @@ struct Args {
   emit_code: Option<String>,
 
   build_pin_all: bool,
+  infer_ret: bool,
   codegen_hydra: bool,
 }
 
@@ -108,8 +143,9 @@ fn match_flag(args: &mut Vec<std::ffi::OsString>, flag: &str) -> bool {
 fn parse_binary_fmt(pargs: &mut pico_args::Arguments) -> Result<binary::Fmt, pico_args::Error> {
   let binary_exe = pargs.opt_value_from_str("--binary-exe")?;
   let binary_raw = pargs.opt_value_from_str("--binary-raw")?;
-  if 1 != binary_exe.is_some() as i32 + binary_raw.is_some() as i32 {
-    panic!("Exactly one of --binary-exe or --binary-raw must be set");
+  let binary_com = pargs.opt_value_from_str("--binary-com")?;
+  if 1 != binary_exe.is_some() as i32 + binary_raw.is_some() as i32 + binary_com.is_some() as i32 {
+    panic!("Exactly one of --binary-exe, --binary-raw or --binary-com must be set");
   }
   if let Some(path) = binary_exe {
     return Ok(binary::Fmt::Exe(path));
@@ -117,6 +153,9 @@ fn parse_binary_fmt(pargs: &mut pico_args::Arguments) -> Result<binary::Fmt, pic
   if let Some(path) = binary_raw {
     return Ok(binary::Fmt::Raw(path));
   }
+  if let Some(path) = binary_com {
+    return Ok(binary::Fmt::Com(path));
+  }
   unreachable!();
 }
 
@@ -133,12 +172,22 @@ fn parse_args() -> Result<Args, pico_args::Error> {
   let mut args = Args {
     config:          pargs.value_from_str("--config")?,
     binary:          parse_binary_fmt(&mut pargs)?,
-    analyze:         false,
+    analyze:         None,
+    analyze_callgraph: pargs.opt_value_from_str("--analyze-callgraph")?,
+    analyze_sigs:      pargs.opt_value_from_str("--analyze-sigs")?,
+    analyze_xrefs:     pargs.opt_value_from_str("--analyze-xrefs")?,
+    analyze_coverage:  pargs.opt_value_from_str("--analyze-coverage")?,
+    import_map:        pargs.opt_value_from_str("--import-map")?,
+    coverage_trend:    pargs.opt_value_from_str("--coverage-trend")?,
+    callgraph_codeseg: pargs.opt_value_from_str("--callgraph-codeseg")?,
+    callgraph_func:    pargs.opt_value_from_str("--callgraph-func")?,
+    callgraph_depth:   pargs.opt_value_from_str("--callgraph-depth")?,
     start_addr:      pargs.opt_value_from_str("--start-addr")?,
     end_addr:        pargs.opt_value_from_str("--end-addr")?,
     name:            pargs.opt_value_from_str("--name")?,
     codeseg_name:    pargs.opt_value_from_str("--codeseg-name")?,
     emit_dis:        pargs.opt_value_from_str("--emit-dis")?,
+    emit_nasm:       pargs.opt_value_from_str("--emit-nasm")?,
     emit_ir_initial: pargs.opt_value_from_str("--emit-ir-initial")?,
     emit_ir_opt:     pargs.opt_value_from_str("--emit-ir-opt")?,
     emit_ir_presym:  pargs.opt_value_from_str("--emit-ir-presym")?,
@@ -150,12 +199,70 @@ fn parse_args() -> Result<Args, pico_args::Error> {
     emit_ast:        pargs.opt_value_from_str("--emit-ast")?,
     emit_code:       pargs.opt_value_from_str("--emit-code")?,
     build_pin_all:   false,
+    infer_ret:       false,
     codegen_hydra:   false,
   };
 
   let mut remaining = pargs.finish();
-  args.analyze       = match_flag(&mut remaining, "--analyze");
+  let mut modes = vec![];
+  if match_flag(&mut remaining, "--analyze") {
+    modes.push(app_analyze::Mode::Annotations);
+  }
+  if match_flag(&mut remaining, "--analyze-discover") {
+    modes.push(app_analyze::Mode::Discover);
+  }
+  if match_flag(&mut remaining, "--analyze-args") {
+    modes.push(app_analyze::Mode::Args);
+  }
+  if match_flag(&mut remaining, "--analyze-ret") {
+    modes.push(app_analyze::Mode::Ret);
+  }
+  if match_flag(&mut remaining, "--analyze-regargs") {
+    modes.push(app_analyze::Mode::Regargs);
+  }
+  if match_flag(&mut remaining, "--lint") {
+    modes.push(app_analyze::Mode::Lint);
+  }
+  if match_flag(&mut remaining, "--analyze-strings") {
+    modes.push(app_analyze::Mode::Strings);
+  }
+  if match_flag(&mut remaining, "--analyze-globals") {
+    modes.push(app_analyze::Mode::Globals);
+  }
+  if let Some(query) = &args.analyze_xrefs {
+    modes.push(app_analyze::Mode::Xrefs(query.clone()));
+  }
+  if let Some(path) = &args.analyze_sigs {
+    modes.push(app_analyze::Mode::Signatures(path.clone()));
+  }
+  if match_flag(&mut remaining, "--import-tdinfo") {
+    modes.push(app_analyze::Mode::ImportTdinfo);
+  }
+  if let Some(path) = &args.import_map {
+    modes.push(app_analyze::Mode::ImportMap(path.clone()));
+  }
+  if args.analyze_coverage.is_some() || args.coverage_trend.is_some() {
+    modes.push(app_analyze::Mode::Coverage(app_analyze::CoverageOpts {
+      format: args.analyze_coverage.unwrap_or(app_analyze::ReportFormat::Csv),
+      trend: args.coverage_trend.clone(),
+    }));
+  }
+  if let Some(format) = args.analyze_callgraph {
+    modes.push(app_analyze::Mode::CallGraph(app_analyze::CallGraphOpts {
+      format,
+      codeseg_name: args.callgraph_codeseg.clone(),
+      func_name: args.callgraph_func.clone(),
+      depth: args.callgraph_depth.unwrap_or(1),
+    }));
+  }
+  // Only one mode runs, so don't silently pick one
+  if modes.len() > 1 {
+    return Err(pico_args::Error::ArgumentParsingFailed { cause: "only one analysis mode can be given".to_string() });
+  }
+  args.analyze = modes.pop();
+
   args.build_pin_all = match_flag(&mut remaining, "--build-pin-all");
+  args.infer_ret = match_flag(&mut remaining, "--infer-ret");
   args.codegen_hydra = match_flag(&mut remaining, "--codegen-hydra");
 
   // It's up to the caller what to do with the remaining arguments.
@@ -176,14 +283,36 @@ pub fn run() -> i32 {
     }
   };
 
-  let cfg = Config::from_path(&args.config).unwrap();
+  let mut cfg = Config::from_path(&args.config).unwrap();
 
-  if args.analyze {
+  if let Some(mode) = &args.analyze {
     let binary::Fmt::Exe(path) = &args.binary else { panic!("expected --binary-exe in --analyze mode") };
-    return crate::app_analyze::run(&cfg, path);
+    return app_analyze::run(&cfg, path, mode);
+  }
+
+  if args.infer_ret && !matches!(args.binary, binary::Fmt::Exe(_)) {
+    eprintln!("Error: --infer-ret requires --binary-exe");
+    return 1;
+  }
+
+  let mut binary = Binary::from_fmt(&args.binary, Some(&cfg)).unwrap();
+
+  if args.infer_ret {
+    let analyze = Analyze::from_binary(&cfg, binary);
+    let inferred = analyze.infer_ret_types();
+    for func in &mut cfg.funcs {
+      if func.ret.is_some() { continue; }
+      let Some(typ) = inferred.get(&func.start) else { continue };
+      eprintln!("INFO: Inferred return type '{}' for {}", typ, func.name);
+      func.ret = Some(typ.clone());
+    }
+    // Calls find their callee's return type through the binary's copy of the config
+    binary = analyze.into_binary();
+    binary.set_config(Some(&cfg));
   }
 
-  let binary = Binary::from_fmt(&args.binary, Some(&cfg)).unwrap();
+  // String literals for the char arrays in the config, to comment the pointers passed to calls
+  let strings = strings::config_strings(&cfg, &binary);
 
   let specs =
     if let Some(name) = &args.name {
@@ -195,7 +324,7 @@ pub fn run() -> i32 {
     };
 
   for spec in specs {
-    let ret = decompile_spec(&args, &cfg, &binary, spec);
+    let ret = decompile_spec(&args, &cfg, &binary, &strings, spec);
     if ret != 0 {
       eprintln!("Error: Failed to decompile.");
       return ret;
@@ -205,12 +334,23 @@ pub fn run() -> i32 {
   0
 }
 
-fn decompile_spec(args: &Args, cfg: &Config, binary: &Binary, spec: Spec<'_>) -> i32 {
-  let region = binary.region_iter(spec.start, spec.end);
-  let decoder = Decoder::new(region);
+fn decompile_spec(args: &Args, cfg: &Config, binary: &Binary, strings: &BTreeMap<u16, String>, spec: Spec<'_>) -> i32 {
+  if let Some(path) = args.emit_nasm.as_ref() {
+    let text = nasm_syntax::format(binary.region_iter(spec.start, spec.end)).unwrap();
+    write_to_path(path, &text);
+    return 0;
+  }
+
+  let decoded = match binary.decode_code(spec.start, spec.end) {
+    Ok(decoded) => decoded,
+    Err(err) => {
+      eprintln!("Error: {}", err);
+      return 1;
+    }
+  };
   let mut instr_list = vec![];
   let mut raw_list = vec![];
-  for (instr, raw) in decoder {
+  for (instr, raw) in decoded {
     instr_list.push(instr);
     raw_list.push(raw);
   }
@@ -220,7 +360,7 @@ fn decompile_spec(args: &Args, cfg: &Config, binary: &Binary, spec: Spec<'_>) ->
     for i in 0..instr_list.len() {
       let instr = &instr_list[i];
       let raw = &raw_list[i];
-      buf += &intel_syntax::format(instr.addr, Some(&instr), raw, true).unwrap();
+      buf += &intel_syntax::format(instr.addr, Some(&instr), raw, true, Some(binary)).unwrap();
       buf += "\n";
     }
     write_to_path(path, &buf);
@@ -242,6 +382,9 @@ fn decompile_spec(args: &Args, cfg: &Config, binary: &Binary, spec: Spec<'_>) ->
   }
 
   sym::symbolize(&mut ir, &cfg);
+  if let Some(func) = spec.func {
+    sym::name_locals(&mut ir, &func.locals);
+  }
   if let Some(path) = args.emit_ir_sym.as_ref() {
     write_to_path(path, &format!("{}", ir));
     return 0;
@@ -286,7 +429,7 @@ fn decompile_spec(args: &Args, cfg: &Config, binary: &Binary, spec: Spec<'_>) ->
   }
 
   let ret = spec.func.map(|f| f.return_type_defaulted());
-  let ast = ast::Function::from_ir(&cfg, &spec.name, ret, &ir, &ctrlflow);
+  let ast = ast::Function::from_ir(&cfg, strings, &spec.name, ret, &ir, &ctrlflow);
   if let Some(path) = args.emit_ast.as_ref() {
     let text = format!("{:#?}", ast);
     write_to_path(path, &text);
@@ -1,11 +1,239 @@
 use crate::analyze::analyze::Analyze;
+use crate::analyze::call_graph;
+use crate::analyze::coverage;
+use crate::analyze::signature;
+use crate::analyze::xref::{Target, Xref, XrefDb};
+use crate::binfmt::map;
 use crate::config::Config;
+use crate::segoff::SegOff;
 
-pub fn run(cfg: &Config, exe_path: &str) -> i32 {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+  Dot,
+  Json,
+}
+
+impl std::str::FromStr for GraphFormat {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, String> {
+    match s {
+      "dot"  => Ok(GraphFormat::Dot),
+      "json" => Ok(GraphFormat::Json),
+      _ => Err(format!("Unknown graph format '{}', expected 'dot' or 'json'", s)),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct CallGraphOpts {
+  pub format: GraphFormat,
+  pub codeseg_name: Option<String>, // only functions in this code segment
+  pub func_name: Option<String>,    // only callers/callees of this function ...
+  pub depth: usize,                 // ... within this many levels
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+  Json,
+  Csv,
+}
+
+impl std::str::FromStr for ReportFormat {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, String> {
+    match s {
+      "json" => Ok(ReportFormat::Json),
+      "csv"  => Ok(ReportFormat::Csv),
+      _ => Err(format!("Unknown report format '{}', expected 'json' or 'csv'", s)),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct CoverageOpts {
+  pub format: ReportFormat,
+  pub trend: Option<String>, // compare against this previous CSV report instead
+}
+
+#[derive(Debug, Clone)]
+pub enum XrefQuery {
+  Name(String), // accesses to this global or text section region
+  Offset(u16),  // accesses to exactly this DS offset
+  Unannotated,  // DS offsets accessed directly that no global covers
+}
+
+impl std::str::FromStr for XrefQuery {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, String> {
+    if s == "unannotated" {
+      return Ok(XrefQuery::Unannotated);
+    }
+    if let Some(hex) = s.strip_prefix("0x") {
+      let off = u16::from_str_radix(hex, 16).map_err(|_| format!("Invalid offset '{}'", s))?;
+      return Ok(XrefQuery::Offset(off));
+    }
+    Ok(XrefQuery::Name(s.to_string()))
+  }
+}
+
+#[derive(Debug, Clone)]
+pub enum Mode {
+  Annotations, // scan from the config functions and emit annotations for everything found
+  Discover,    // walk everything reachable from the entry point and report a byte map with coverage
+  Args,        // like Annotations, but with args inferred from the callee's "ret N" and the callers' cleanup
+  Ret,         // like Annotations, but with return types inferred from the callers' use of DX:AX
+  Regargs,     // like Annotations, but with regargs inferred from registers read before written at entry
+  Xrefs(XrefQuery),
+  Lint,        // cross-check the config against the binary and report every problem
+  Strings,     // find the strings in the data segment and emit annotations for them
+  Globals,     // propose globals for the DS offsets accessed directly that no global covers
+  Signatures(String), // scan the code segments for the library functions in this signature file
+  ImportMap(String),  // emit BSL for what this linker MAP file has that the config doesn't
+  ImportTdinfo,       // ... or the Turbo Debugger info appended to the exe
+  Coverage(CoverageOpts),
+  CallGraph(CallGraphOpts),
+}
+
+fn call_graph(a: &Analyze, cfg: &Config, opts: &CallGraphOpts) -> i32 {
+  let mut g = a.call_graph();
+  if let Some(name) = &opts.codeseg_name {
+    let Some(cs) = cfg.code_seg_lookup_by_name(name) else {
+      eprintln!("Error: Unknown code segment '{}'", name);
+      return 1;
+    };
+    g = g.restrict_to_segment(cs.seg);
+  }
+  if let Some(name) = &opts.func_name {
+    let Some(func) = cfg.func_lookup_by_name(name) else {
+      eprintln!("Error: Unknown function '{}'", name);
+      return 1;
+    };
+    g = g.restrict_to_neighborhood(func.start, opts.depth);
+  }
+
+  let text = match opts.format {
+    GraphFormat::Dot  => call_graph::gen_graphviz_dotfile(&g).unwrap(),
+    GraphFormat::Json => call_graph::gen_json(&g).unwrap(),
+  };
+  print!("{}", text);
+  0
+}
+
+fn coverage(a: &Analyze, opts: &CoverageOpts) -> i32 {
+  let cov = a.coverage();
+  let text = match &opts.trend {
+    Some(path) => {
+      let prev = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read '{}': {}", path, err))
+        .and_then(|text| coverage::parse_csv(&text));
+      match prev {
+        Ok(prev) => coverage::gen_trend(&prev, &cov).unwrap(),
+        Err(err) => {
+          eprintln!("Error: {}", err);
+          return 1;
+        }
+      }
+    }
+    None => match opts.format {
+      ReportFormat::Json => coverage::gen_json(&cov).unwrap(),
+      ReportFormat::Csv  => coverage::gen_csv(&cov).unwrap(),
+    },
+  };
+  print!("{}", text);
+  0
+}
+
+fn func_name(cfg: &Config, addr: SegOff) -> String {
+  cfg.func_lookup(addr).map(|f| f.name.clone()).unwrap_or_else(|| format!("<{}>", addr))
+}
+
+fn print_xrefs<'a>(cfg: &Config, xrefs: impl Iterator<Item=(Target, &'a Xref)>) {
+  for (target, x) in xrefs {
+    let size = x.size.map(|sz| sz.to_string()).unwrap_or("-".to_string());
+    let indexed = if x.indexed { "indexed" } else { "" };
+    println!("  {:<16} {}  {:<2} {:<2} {:<30} {}", target.to_string(), x.addr, x.kind, size, func_name(cfg, x.func), indexed);
+  }
+}
+
+fn xrefs(db: &XrefDb, cfg: &Config, query: &XrefQuery) -> i32 {
+  match query {
+    XrefQuery::Name(name) => {
+      if let Some(g) = cfg.globals.iter().find(|g| &g.name == name) {
+        println!("Xrefs to {} (ds:{:04x}, {})", g.name, g.offset, g.typ);
+        print_xrefs(cfg, db.global(g).into_iter());
+      } else if let Some(r) = cfg.text_section.iter().find(|r| &r.name == name) {
+        println!("Xrefs to {} ({} to {}, {})", r.name, r.start, r.end, r.typ);
+        print_xrefs(cfg, db.text_region(r).into_iter());
+      } else {
+        eprintln!("Error: Unknown global or text section region '{}'", name);
+        return 1;
+      }
+    }
+    XrefQuery::Offset(off) => {
+      let target = Target::Data(*off);
+      let sizes: Vec<String> = db.sizes_at(target).iter().map(|sz| sz.to_string()).collect();
+      println!("Xrefs to {} (sizes: {})", target, sizes.join(", "));
+      print_xrefs(cfg, db.xrefs.get(&target).into_iter().flatten().map(|x| (target, x)));
+    }
+    XrefQuery::Unannotated => {
+      println!("Unannotated DS offsets");
+      for (off, xrefs) in db.unannotated_data(cfg) {
+        let sizes: Vec<String> = db.sizes_at(Target::Data(off)).iter().map(|sz| sz.to_string()).collect();
+        let mut funcs: Vec<String> = xrefs.iter().map(|x| func_name(cfg, x.func)).collect();
+        funcs.dedup();
+        println!("  ds:{:04x}  sizes: {:<6} accesses: {:<4} funcs: {}", off, sizes.join(","), xrefs.len(), funcs.join(", "));
+      }
+    }
+  }
+  0
+}
+
+pub fn run(cfg: &Config, exe_path: &str, mode: &Mode) -> i32 {
   let a = Analyze::new(cfg, exe_path);
-  a.scan_for_all_functions(true);
+  match mode {
+    Mode::Annotations      => a.scan_for_all_functions(true),
+    Mode::Discover         => a.discover_all_code_and_report(),
+    Mode::Args             => a.infer_args_and_report(),
+    Mode::Ret              => a.infer_ret_and_report(),
+    Mode::Regargs          => a.infer_regargs_and_report(),
+    Mode::Signatures(path) => {
+      let sigs = match signature::load(path) {
+        Ok(sigs) => sigs,
+        Err(err) => {
+          eprintln!("Error: {}", err);
+          return 1;
+        }
+      };
+      a.match_signatures_and_report(&sigs);
+    }
+    Mode::ImportMap(path)  => {
+      let map = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read '{}': {}", path, err))
+        .and_then(|text| map::parse(&text));
+      match map {
+        Ok(map) => a.import_map_and_report(&map),
+        Err(err) => {
+          eprintln!("Error: {}", err);
+          return 1;
+        }
+      }
+    }
+    Mode::ImportTdinfo     => {
+      let Some(info) = a.tdinfo() else {
+        eprintln!("Error: No Turbo Debugger info found in the exe");
+        return 1;
+      };
+      a.import_tdinfo_and_report(&info);
+    }
+    Mode::Lint             => return if a.lint_and_report() == 0 { 0 } else { 1 },
+    Mode::Strings          => a.discover_strings_and_report(),
+    Mode::Globals          => a.propose_globals_and_report(),
+    Mode::Xrefs(query)     => return xrefs(&a.xrefs(), cfg, query),
+    Mode::Coverage(opts)   => return coverage(&a, opts),
+    Mode::CallGraph(opts)  => return call_graph(&a, cfg, opts),
+  }
 
-  //a.analyze_code_segments_and_report();
+  //a.analyze_code_segments_and_report(None);
 
   1
 }
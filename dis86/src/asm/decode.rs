@@ -21,6 +21,11 @@ fn operand_reg(r: Reg) -> Result<Operand, String> {
   Ok(Operand::Reg(OperandReg(r)))
 }
 
+fn operand_sreg(num: u8) -> Result<Operand, String> {
+  if num > 3 { return Err(format!("Invalid segment register: {}", num)); }
+  operand_reg(Reg::sreg16(num))
+}
+
 fn operand_imm8(imm: u8) -> Result<Operand, String> {
   Ok(Operand::Imm(OperandImm {
     sz: Size::Size8,
@@ -130,22 +135,42 @@ fn operand_rm(bin: &mut RegionIter, sz: Size, modrm: u8, prefix_sreg: Option<Reg
 fn operand_rm8(bin: &mut RegionIter, modrm: u8, sreg: Option<Reg>) -> Result<Operand, String> { operand_rm(bin, Size::Size8, modrm, sreg) }
 fn operand_rm16(bin: &mut RegionIter, modrm: u8, sreg: Option<Reg>) -> Result<Operand, String> { operand_rm(bin, Size::Size16, modrm, sreg) }
 
-fn operand_m8(bin: &mut RegionIter, modrm: u8, sreg: Option<Reg>) -> Result<Operand, String> {
-  let oper = operand_rm(bin, Size::Size8, modrm, sreg)?;
-  if !matches!(oper, Operand::Mem(_)) { return Err(format!("Register used where memory operand was required")); }
-  Ok(oper)
-}
-
-fn operand_m16(bin: &mut RegionIter, modrm: u8, sreg: Option<Reg>) -> Result<Operand, String> {
-  let oper = operand_rm(bin, Size::Size16, modrm, sreg)?;
+fn operand_m(bin: &mut RegionIter, sz: Size, modrm: u8, sreg: Option<Reg>) -> Result<Operand, String> {
+  let oper = operand_rm(bin, sz, modrm, sreg)?;
   if !matches!(oper, Operand::Mem(_)) { return Err(format!("Register used where memory operand was required")); }
   Ok(oper)
 }
 
-fn operand_m32(bin: &mut RegionIter, modrm: u8, sreg: Option<Reg>) -> Result<Operand, String> {
-  let oper = operand_rm(bin, Size::Size32, modrm, sreg)?;
-  if !matches!(oper, Operand::Mem(_)) { return Err(format!("Register used where memory operand was required")); }
-  Ok(oper)
+fn operand_m16(bin: &mut RegionIter, modrm: u8, sreg: Option<Reg>) -> Result<Operand, String> { operand_m(bin, Size::Size16, modrm, sreg) }
+fn operand_m32(bin: &mut RegionIter, modrm: u8, sreg: Option<Reg>) -> Result<Operand, String> { operand_m(bin, Size::Size32, modrm, sreg) }
+fn operand_m64(bin: &mut RegionIter, modrm: u8, sreg: Option<Reg>) -> Result<Operand, String> { operand_m(bin, Size::Size64, modrm, sreg) }
+fn operand_m80(bin: &mut RegionIter, modrm: u8, sreg: Option<Reg>) -> Result<Operand, String> { operand_m(bin, Size::Size80, modrm, sreg) }
+fn operand_menv(bin: &mut RegionIter, modrm: u8, sreg: Option<Reg>) -> Result<Operand, String> { operand_m(bin, Size::SizeNone, modrm, sreg) }
+
+// The 8087 emulator libraries (Borland and Microsoft) link FPU instructions as software
+// interrupts that get patched back into real escapes at runtime when an FPU is present:
+//   INT 34h-3Bh: escape 0xd8-0xdf (the wait is implied)
+//   INT 3Ch:     escape with a segment override, the next byte is the escape with the
+//                segment encoded in its upper 2 bits
+//   INT 3Dh:     standalone fwait
+// Returns the replacement opcode1 and any segment override
+fn fpu_emu_escape(bin: &mut RegionIter, vec: u8) -> Result<(u8, Option<Reg>), String> {
+  match vec {
+    0x34..=0x3b => Ok((0xd8 + (vec - 0x34), None)),
+    0x3c => {
+      let b = bin.fetch()?;
+      let sreg = match b>>6 {
+        0 => Reg::DS,
+        1 => Reg::SS,
+        2 => Reg::CS,
+        3 => Reg::ES,
+        _ => unreachable!(),
+      };
+      Ok((0xd8 | (b&7), Some(sreg)))
+    }
+    0x3d => Ok((0x9b, None)),
+    _ => Err(format!("Not an 8087 emulator interrupt: 0x{:x}", vec)),
+  }
 }
 
 pub fn decode_one<'a>(bin: &mut RegionIter<'a>) -> Result<Option<(Instr, &'a [u8])>, String> {
@@ -164,24 +189,43 @@ pub fn decode_one_impl<'a>(bin: &mut RegionIter<'a>) -> Result<Option<(Instr, &'
     return Ok(None);
   }
 
-  // First parse any prefixes
+  // First parse any prefixes (when repeated, the last of each kind is the one that takes effect)
   let mut sreg = None;
   let mut rep = None;
+  let mut lock = false;
+  let mut prefixes = ArrayVec::new();
   loop {
-    match bin.peek() {
+    let b = bin.peek();
+    match b {
       0x26 => sreg = Some(Reg::ES),
       0x2e => sreg = Some(Reg::CS),
       0x36 => sreg = Some(Reg::SS),
       0x3e => sreg = Some(Reg::DS),
       0xf2 => rep = Some(Rep::NE),
       0xf3 => rep = Some(Rep::EQ),
+      0xf0 => lock = true,
       _ => break,
     }
+    if prefixes.len() == MAX_PREFIXES {
+      return Err(format!("Too many prefixes at {}", start_addr));
+    }
+    prefixes.push(b);
     bin.advance();
   }
 
   // Now parse the main level1 opcode
-  let opcode1 = bin.fetch()?;
+  let mut opcode1 = bin.fetch()?;
+
+  // Is this an 8087 emulator interrupt standing in for an FPU instruction?
+  let mut fpu_emu = None;
+  if opcode1 == 0xcd && matches!(bin.peek_checked(), Ok(0x34..=0x3d)) {
+    let vec = bin.fetch()?;
+    let (op, emu_sreg) = fpu_emu_escape(bin, vec)?;
+    opcode1 = op;
+    if emu_sreg.is_some() { sreg = emu_sreg; }
+    fpu_emu = Some(vec);
+  }
+
   let mut opcode2 = None;
   let mut ret = instr_fmt::lookup(opcode1, opcode2);
 
@@ -195,6 +239,13 @@ pub fn decode_one_impl<'a>(bin: &mut RegionIter<'a>) -> Result<Option<(Instr, &'
     bin.advance();
     opcode2 = Some(b);
     ret = instr_fmt::lookup(opcode1, opcode2);
+    if ret.err() == Some(instr_fmt::Error::NeedOpcode3Ext0F) {
+      ret = instr_fmt::lookup_0f_group(b, bin.peek_checked()?);
+    }
+  } else if ret.err() == Some(instr_fmt::Error::NeedOpcode2Fpu) {
+    let b = bin.peek_checked()?;
+    opcode2 = Some(b);
+    ret = instr_fmt::lookup_fpu(opcode1, b);
   }
 
   // Unpack
@@ -260,12 +311,18 @@ pub fn decode_one_impl<'a>(bin: &mut RegionIter<'a>) -> Result<Option<(Instr, &'
       // Explicit register operands
       instr_fmt::Oper::OPER_R8    => operand_reg(Reg::reg8(modrm_reg(modrm))),
       instr_fmt::Oper::OPER_R16   => operand_reg(Reg::reg16(modrm_reg(modrm))),
-      instr_fmt::Oper::OPER_SREG  => operand_reg(Reg::sreg16(modrm_reg(modrm))),
+      instr_fmt::Oper::OPER_SREG  => operand_sreg(modrm_reg(modrm)),
 
       // Explicit memory operands
-      instr_fmt::Oper::OPER_M8    => operand_m8(bin, modrm, sreg),
       instr_fmt::Oper::OPER_M16   => operand_m16(bin, modrm, sreg),
       instr_fmt::Oper::OPER_M32   => operand_m32(bin, modrm, sreg),
+      instr_fmt::Oper::OPER_M64   => operand_m64(bin, modrm, sreg),
+      instr_fmt::Oper::OPER_M80   => operand_m80(bin, modrm, sreg),
+      instr_fmt::Oper::OPER_MENV  => operand_menv(bin, modrm, sreg),
+
+      // FPU stack register operands
+      instr_fmt::Oper::OPER_ST    => operand_reg(Reg::ST0),
+      instr_fmt::Oper::OPER_STI   => operand_reg(Reg::st(modrm_rm(modrm))),
 
       // Explicit register or memory operands (modrm)
       instr_fmt::Oper::OPER_RM8   => operand_rm8(bin, modrm, sreg),
@@ -296,11 +353,14 @@ pub fn decode_one_impl<'a>(bin: &mut RegionIter<'a>) -> Result<Option<(Instr, &'
 
   let instr = Instr {
     rep,
+    lock,
+    prefixes,
     opcode: fmt.op,
     operands,
     addr: start_addr,
     n_bytes,
     intel_hidden_operand_bitmask: fmt.hidden,
+    fpu_emu,
   };
 
   let raw = bin.slice(start_addr, n_bytes);
@@ -584,6 +644,48 @@ mod tests {
     TestCase { addr: 0x0000, dat: &[0x69, 0xf6, 0xa0, 0x00],       asm: "imul   si,si,0xa0" },
     TestCase { addr: 0x0000, dat: &[0x69, 0x01, 0x79, 0x01],       asm: "imul   ax,WORD PTR ds:[bx+di],0x179" },
     TestCase { addr: 0x0000, dat: &[0x36, 0xac],                   asm: "lods   al,BYTE PTR ss:[si]" },
+    TestCase { addr: 0x0000, dat: &[0x62, 0x06, 0x34, 0x12],     asm: "bound  ax,DWORD PTR ds:0x1234" },
+    TestCase { addr: 0x0000, dat: &[0x6c],                       asm: "ins    BYTE PTR es:[di],dx" },
+    TestCase { addr: 0x0000, dat: &[0xf3, 0x6d],                 asm: "rep ins    WORD PTR es:[di],dx" },
+    TestCase { addr: 0x0000, dat: &[0x6e],                       asm: "outs   dx,BYTE PTR ds:[si]" },
+    TestCase { addr: 0x0000, dat: &[0x26, 0x6f],                 asm: "outs   dx,WORD PTR es:[si]" },
+    TestCase { addr: 0x0000, dat: &[0xd4, 0x0a],                 asm: "aam    0xa" },
+    TestCase { addr: 0x0000, dat: &[0xd5, 0x0a],                 asm: "aad    0xa" },
+    TestCase { addr: 0x0000, dat: &[0xf6, 0xfb],                 asm: "idiv   ah,al,bl" },
+    TestCase { addr: 0x0000, dat: &[0xf7, 0x7e, 0x04],           asm: "idiv   dx,ax,WORD PTR ss:[bp+0x4]" },
+    TestCase { addr: 0x0000, dat: &[0x0f, 0x06],                 asm: "clts" },
+    TestCase { addr: 0x0000, dat: &[0x0f, 0x01, 0x06, 0x00, 0x01],asm: "sgdt   ds:0x100" },
+    TestCase { addr: 0x0000, dat: &[0x0f, 0x01, 0x1f],           asm: "lidt   ds:[bx]" },
+    TestCase { addr: 0x0000, dat: &[0x0f, 0x01, 0xe0],           asm: "smsw   ax" },
+    TestCase { addr: 0x0000, dat: &[0x0f, 0x01, 0x76, 0xfe],     asm: "lmsw   WORD PTR ss:[bp-0x2]" },
+    TestCase { addr: 0x0000, dat: &[0xd8, 0x06, 0x34, 0x12],       asm: "fadd   DWORD PTR ds:0x1234" },
+    TestCase { addr: 0x0000, dat: &[0xdc, 0x4e, 0x04],             asm: "fmul   QWORD PTR ss:[bp+0x4]" },
+    TestCase { addr: 0x0000, dat: &[0xdb, 0x2f],                   asm: "fld    TBYTE PTR ds:[bx]" },
+    TestCase { addr: 0x0000, dat: &[0xdf, 0x6e, 0xf8],             asm: "fild   QWORD PTR ss:[bp-0x8]" },
+    TestCase { addr: 0x0000, dat: &[0xde, 0x0e, 0x10, 0x00],       asm: "fimul  WORD PTR ds:0x10" },
+    TestCase { addr: 0x0000, dat: &[0xd9, 0x36, 0x20, 0x00],       asm: "fnstenv  ds:0x20" },
+    TestCase { addr: 0x0000, dat: &[0xdd, 0x7e, 0xfe],             asm: "fnstsw  WORD PTR ss:[bp-0x2]" },
+    TestCase { addr: 0x0000, dat: &[0x26, 0xdd, 0x07],             asm: "fld    QWORD PTR es:[bx]" },
+    TestCase { addr: 0x0000, dat: &[0xd8, 0xc1],                   asm: "fadd   st(0),st(1)" },
+    TestCase { addr: 0x0000, dat: &[0xdc, 0xe9],                   asm: "fsub   st(1),st(0)" },
+    TestCase { addr: 0x0000, dat: &[0xde, 0xf9],                   asm: "fdivp  st(1),st(0)" },
+    TestCase { addr: 0x0000, dat: &[0xd9, 0xc9],                   asm: "fxch   st(1)" },
+    TestCase { addr: 0x0000, dat: &[0xdd, 0xd8],                   asm: "fstp   st(0)" },
+    TestCase { addr: 0x0000, dat: &[0xde, 0xd9],                   asm: "fcompp" },
+    TestCase { addr: 0x0000, dat: &[0xd9, 0xe8],                   asm: "fld1" },
+    TestCase { addr: 0x0000, dat: &[0xd9, 0xe0],                   asm: "fchs" },
+    TestCase { addr: 0x0000, dat: &[0xdb, 0xe3],                   asm: "fninit" },
+    TestCase { addr: 0x0000, dat: &[0xdf, 0xe0],                   asm: "fnstsw  ax" },
+    TestCase { addr: 0x0000, dat: &[0x9b],                         asm: "fwait" },
+    TestCase { addr: 0x0000, dat: &[0xcd, 0x35, 0x06, 0x34, 0x12], asm: "fld    DWORD PTR ds:0x1234" },
+    TestCase { addr: 0x0000, dat: &[0xcd, 0x3a, 0xe9],             asm: "fsubp  st(1),st(0)" },
+    TestCase { addr: 0x0000, dat: &[0xcd, 0x3c, 0x1d, 0x07],       asm: "fld    QWORD PTR ds:[bx]" },
+    TestCase { addr: 0x0000, dat: &[0xcd, 0x3c, 0x9d, 0x07],       asm: "fld    QWORD PTR cs:[bx]" },
+    TestCase { addr: 0x0000, dat: &[0xcd, 0x3c, 0xdd, 0x07],       asm: "fld    QWORD PTR es:[bx]" },
+    TestCase { addr: 0x0000, dat: &[0xcd, 0x3d],                   asm: "fwait" },
+    TestCase { addr: 0x0000, dat: &[0xf0, 0x87, 0x07],             asm: "lock xchg   ax,WORD PTR ds:[bx]" },
+    TestCase { addr: 0x0000, dat: &[0x26, 0x2e, 0x8b, 0x07],       asm: "es mov    ax,WORD PTR cs:[bx]" },
+    TestCase { addr: 0x0000, dat: &[0xf2, 0xf3, 0xa5],             asm: "repne rep movs   WORD PTR es:[di],WORD PTR ds:[si]" },
   ];
 
   #[test]
@@ -594,10 +696,43 @@ mod tests {
       let mut bin = RegionIter::new(test.dat, addr);
       let (ins, bytes) = decode_one(&mut bin).unwrap().unwrap();
       println!("{:?}", ins);
-      let asm = crate::asm::intel_syntax::format(addr, Some(&ins), &bytes, false).unwrap();
+      let asm = crate::asm::intel_syntax::format(addr, Some(&ins), &bytes, false, None).unwrap();
       if asm != test.asm {
         panic!("Failed ({}/{}) | Expected: '{}' | Got: '{}'\n\nRAW:\n{:?}", n, TESTS.len(), test.asm, asm, ins);
       }
+      assert_eq!(ins.n_bytes as usize, test.dat.len());
     }
   }
+
+  #[test]
+  fn test_prefixes() {
+    use crate::segoff::*;
+    let addr = SegOff { seg: Seg::Normal(0), off: Off(0) };
+
+    let mut bin = RegionIter::new(&[0x3e, 0xf0, 0x26, 0xf3, 0xa4], addr);
+    let (ins, _) = decode_one(&mut bin).unwrap().unwrap();
+    assert!(ins.lock);
+    assert_eq!(ins.rep, Some(Rep::EQ));
+    assert_eq!(ins.prefixes.as_slice(), &[0x3e, 0xf0, 0x26, 0xf3]);
+    assert_eq!(ins.superseded_prefixes().collect::<Vec<_>>(), vec![0x3e]);
+
+    let mut bin = RegionIter::new(&[0x26; 12], addr);
+    assert!(decode_one(&mut bin).is_err());
+  }
+
+  #[test]
+  fn test_fpu_emu() {
+    use crate::segoff::*;
+    let addr = SegOff { seg: Seg::Normal(0), off: Off(0) };
+
+    let mut bin = RegionIter::new(&[0xcd, 0x3b, 0xe0], addr);
+    let (ins, _) = decode_one(&mut bin).unwrap().unwrap();
+    assert_eq!(ins.opcode, Opcode::OP_FNSTSW);
+    assert_eq!(ins.fpu_emu, Some(0x3b));
+
+    let mut bin = RegionIter::new(&[0xcd, 0x21], addr);
+    let (ins, _) = decode_one(&mut bin).unwrap().unwrap();
+    assert_eq!(ins.opcode, Opcode::OP_INT);
+    assert_eq!(ins.fpu_emu, None);
+  }
 }
@@ -0,0 +1,496 @@
+use crate::asm::instr::*;
+use crate::asm::instr_fmt::{self, InstrFmt, Oper};
+use crate::asm::intel_syntax::instr_str;
+
+// Encoding an instruction is the reverse of decoding: every format in the decoder table for
+// the opcode is tried and the shortest one that can represent the operands wins. Decoding the
+// result always yields the same instruction, but not necessarily the original bytes: redundant
+// prefixes are dropped and immediates/displacements use the narrowest available form.
+//
+// Relative operands are re-targeted so that the branch destination is preserved, even if the
+// new encoding has a different length than `ins.n_bytes`.
+//
+// encode_exact() is the byte-preserving alternative: the decoded prefixes are kept as they were
+// and only encodings of the original length are considered.
+pub fn encode(ins: &Instr) -> Result<Vec<u8>, String> {
+  let mut best: Option<(bool, Vec<u8>)> = None;
+  for (fmt, bytes) in encodings(ins) {
+    let hidden_mismatch = fmt.hidden != ins.intel_hidden_operand_bitmask;
+    let better = match &best {
+      None => true,
+      Some((best_mismatch, best_bytes)) => (hidden_mismatch, bytes.len()) < (*best_mismatch, best_bytes.len()),
+    };
+    if better {
+      best = Some((hidden_mismatch, bytes));
+    }
+  }
+
+  match best {
+    Some((_, bytes)) => Ok(bytes),
+    None => Err(format!("No encoding found for instruction: '{}' | {:?}", instr_str(ins), ins.operands)),
+  }
+}
+
+// Encode to the bytes `raw` the instruction was decoded from, or failing that (e.g. the operands
+// were changed) to another encoding of the same length
+pub fn encode_exact(ins: &Instr, raw: &[u8]) -> Result<Vec<u8>, String> {
+  let mut best: Option<(bool, Vec<u8>)> = None;
+  for fmt in instr_fmt::formats(ins.opcode) {
+    for disp16 in [false, true] {
+      let Ok(bytes) = encode_fmt(ins, fmt, Options { keep_prefixes: true, disp16 }) else { continue };
+      if bytes == raw { return Ok(bytes); }
+      let hidden_mismatch = fmt.hidden != ins.intel_hidden_operand_bitmask;
+      if bytes.len() == raw.len() && best.as_ref().is_none_or(|(best_mismatch, _)| *best_mismatch && !hidden_mismatch) {
+        best = Some((hidden_mismatch, bytes));
+      }
+    }
+  }
+
+  match best {
+    Some((_, bytes)) => Ok(bytes),
+    None => Err(format!("No {} byte encoding found for instruction: '{}' | {:?}", raw.len(), instr_str(ins), ins.operands)),
+  }
+}
+
+// Every format that can represent the instruction (in table order) along with its encoding
+pub(crate) fn encodings(ins: &Instr) -> Vec<(&'static InstrFmt, Vec<u8>)> {
+  instr_fmt::formats(ins.opcode)
+    .filter_map(|fmt| Some((fmt, encode_fmt(ins, fmt, Options::default()).ok()?)))
+    .collect()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Options {
+  keep_prefixes: bool, // emit the decoded prefixes as-is instead of the minimal ones
+  disp16: bool,        // don't narrow displacements to 8 bits
+}
+
+fn implied_reg(oper: Oper) -> Option<Reg> {
+  Some(match oper {
+    Oper::OPER_AX => Reg::AX,
+    Oper::OPER_CX => Reg::CX,
+    Oper::OPER_DX => Reg::DX,
+    Oper::OPER_BX => Reg::BX,
+    Oper::OPER_SP => Reg::SP,
+    Oper::OPER_BP => Reg::BP,
+    Oper::OPER_SI => Reg::SI,
+    Oper::OPER_DI => Reg::DI,
+    Oper::OPER_AL => Reg::AL,
+    Oper::OPER_CL => Reg::CL,
+    Oper::OPER_DL => Reg::DL,
+    Oper::OPER_BL => Reg::BL,
+    Oper::OPER_AH => Reg::AH,
+    Oper::OPER_CH => Reg::CH,
+    Oper::OPER_DH => Reg::DH,
+    Oper::OPER_BH => Reg::BH,
+    Oper::OPER_ES => Reg::ES,
+    Oper::OPER_CS => Reg::CS,
+    Oper::OPER_SS => Reg::SS,
+    Oper::OPER_DS => Reg::DS,
+    Oper::OPER_FLAGS => Reg::FLAGS,
+    Oper::OPER_ST => Reg::ST0,
+    _ => return None,
+  })
+}
+
+// Register number (as used in ModRM fields) relative to the first register of its class
+fn reg_num(operand: &Operand, first: Reg, count: u8) -> Result<u8, String> {
+  let Operand::Reg(OperandReg(r)) = operand else {
+    return Err(format!("Expected register operand, got {:?}", operand));
+  };
+  let num = (*r as u8).wrapping_sub(first as u8);
+  if num >= count {
+    return Err(format!("Register {} is not in the same class as {}", r.name(), first.name()));
+  }
+  Ok(num)
+}
+
+fn segment_num(sreg: Reg) -> u8 {
+  match sreg {
+    Reg::ES => 0,
+    Reg::CS => 1,
+    Reg::SS => 2,
+    Reg::DS => 3,
+    _ => panic!("Not a segment register: {:?}", sreg),
+  }
+}
+
+fn segment_prefix(sreg: Reg) -> u8 {
+  0x26 | (segment_num(sreg) << 3)
+}
+
+// Returns (mode, rm, default segment) for a ModRM memory operand and appends any displacement
+fn encode_mem(mem: &OperandMem, disp16: bool, tail: &mut Vec<u8>) -> Result<(u8, u8, Reg), String> {
+  let rm = match (mem.reg1, mem.reg2) {
+    (None, None) => {
+      let Some(off) = mem.off else { return Err("Memory operand without registers or offset".to_string()) };
+      tail.extend_from_slice(&off.to_le_bytes());
+      return Ok((0, 6, Reg::DS));
+    }
+    (Some(Reg::BX), Some(Reg::SI)) => 0,
+    (Some(Reg::BX), Some(Reg::DI)) => 1,
+    (Some(Reg::BP), Some(Reg::SI)) => 2,
+    (Some(Reg::BP), Some(Reg::DI)) => 3,
+    (Some(Reg::SI), None)          => 4,
+    (Some(Reg::DI), None)          => 5,
+    (Some(Reg::BP), None)          => 6,
+    (Some(Reg::BX), None)          => 7,
+    _ => return Err(format!("Unencodable memory operand: {:?}", mem)),
+  };
+  let sreg = if matches!(rm, 2 | 3 | 6) { Reg::SS } else { Reg::DS };
+
+  // NOTE: [bp] has no displacement-free form (that's direct addressing), so it gets a zero disp8
+  let off = match mem.off {
+    None if rm == 6 => Some(0),
+    off => off,
+  };
+  let mode = match off {
+    None => 0,
+    Some(off) if !disp16 && off == off as u8 as i8 as u16 => {
+      tail.push(off as u8);
+      1
+    }
+    Some(off) => {
+      tail.extend_from_slice(&off.to_le_bytes());
+      2
+    }
+  };
+
+  Ok((mode, rm, sreg))
+}
+
+fn encode_fmt(ins: &Instr, fmt: &InstrFmt, opts: Options) -> Result<Vec<u8>, String> {
+  let opers: Vec<Oper> = fmt.oper.iter().copied().take_while(|o| *o != Oper::OPER_NONE).collect();
+  if opers.len() != ins.operands.len() {
+    return Err("Operand count mismatch".to_string());
+  }
+
+  let is_fpu = instr_fmt::is_fpu_escape(fmt.op1);
+
+  // The ModRM byte is pieced together from the opcode2 and the operands
+  let mut modrm_reg = 0;
+  let mut modrm_rm = None;
+  let mut modrm_fixed = None;
+  if fmt.op2 != -1 && fmt.op1 != 0x0f {
+    if is_fpu && fmt.op2 >= 0xc0 { modrm_fixed = Some(fmt.op2 as u8); }
+    else { modrm_reg = fmt.op2 as u8; }
+  }
+
+  // Bytes following the ModRM (displacements and immediates) in operand order
+  let mut tail = vec![];
+  // Segment used by each memory operand: (actual, default)
+  let mut sregs = vec![];
+  let mut rel = None;
+
+  for (i, (oper, operand)) in opers.iter().zip(ins.operands.as_slice()).enumerate() {
+    if let Some(r) = implied_reg(*oper) {
+      if *operand != Operand::Reg(OperandReg(r)) {
+        return Err(format!("Expected implied register {}", r.name()));
+      }
+      continue;
+    }
+
+    match oper {
+      Oper::OPER_LIT1 | Oper::OPER_LIT3 => {
+        let val = if *oper == Oper::OPER_LIT1 { 1 } else { 3 };
+        if *operand != Operand::Imm(OperandImm { sz: Size::Size8, val }) {
+          return Err(format!("Expected implied literal {}", val));
+        }
+      }
+      Oper::OPER_SRC8 | Oper::OPER_SRC16 | Oper::OPER_DST8 | Oper::OPER_DST16 => {
+        let (sz, reg, default) = match oper {
+          Oper::OPER_SRC8  => (Size::Size8, Reg::SI, Reg::DS),
+          Oper::OPER_SRC16 => (Size::Size16, Reg::SI, Reg::DS),
+          Oper::OPER_DST8  => (Size::Size8, Reg::DI, Reg::ES),
+          _                => (Size::Size16, Reg::DI, Reg::ES),
+        };
+        let Operand::Mem(m) = operand else { return Err("Expected string operand".to_string()) };
+        if m.sz != sz || m.reg1 != Some(reg) || m.reg2.is_some() || m.off.is_some() {
+          return Err("Mismatched string operand".to_string());
+        }
+        sregs.push((m.sreg, default));
+      }
+      Oper::OPER_R8   => modrm_reg = reg_num(operand, Reg::AL, 8)?,
+      Oper::OPER_R16  => modrm_reg = reg_num(operand, Reg::AX, 8)?,
+      Oper::OPER_SREG => modrm_reg = reg_num(operand, Reg::ES, 4)?,
+      Oper::OPER_STI  => {
+        let Some(fixed) = modrm_fixed else { return Err("Stack register without a fixed ModRM".to_string()) };
+        modrm_fixed = Some(fixed | reg_num(operand, Reg::ST0, 8)?);
+      }
+      Oper::OPER_M16 | Oper::OPER_M32 | Oper::OPER_M64 | Oper::OPER_M80 | Oper::OPER_MENV |
+      Oper::OPER_RM8 | Oper::OPER_RM16 => {
+        let sz = match oper {
+          Oper::OPER_RM8  => Size::Size8,
+          Oper::OPER_M16  => Size::Size16,
+          Oper::OPER_RM16 => Size::Size16,
+          Oper::OPER_M32  => Size::Size32,
+          Oper::OPER_M64  => Size::Size64,
+          Oper::OPER_M80  => Size::Size80,
+          _               => Size::SizeNone,
+        };
+        match operand {
+          Operand::Mem(m) if m.sz == sz => {
+            let (mode, rm, default) = encode_mem(m, opts.disp16, &mut tail)?;
+            modrm_rm = Some((mode, rm));
+            sregs.push((m.sreg, default));
+          }
+          Operand::Reg(_) if *oper == Oper::OPER_RM8 => modrm_rm = Some((3, reg_num(operand, Reg::AL, 8)?)),
+          Operand::Reg(_) if *oper == Oper::OPER_RM16 => modrm_rm = Some((3, reg_num(operand, Reg::AX, 8)?)),
+          _ => return Err("Mismatched ModRM operand".to_string()),
+        }
+      }
+      Oper::OPER_IMM8 => {
+        let Operand::Imm(OperandImm { sz: Size::Size8, val }) = operand else { return Err("Expected imm8".to_string()) };
+        tail.push(*val as u8);
+      }
+      Oper::OPER_IMM8_EXT => {
+        let Operand::Imm(OperandImm { sz: Size::Size16, val }) = operand else { return Err("Expected imm16".to_string()) };
+        if *val != *val as u8 as i8 as u16 {
+          return Err("Immediate doesn't fit a sign-extended imm8".to_string());
+        }
+        tail.push(*val as u8);
+      }
+      Oper::OPER_IMM16 => {
+        let Operand::Imm(OperandImm { sz: Size::Size16, val }) = operand else { return Err("Expected imm16".to_string()) };
+        tail.extend_from_slice(&val.to_le_bytes());
+      }
+      Oper::OPER_FAR32 => {
+        let Operand::Far(far) = operand else { return Err("Expected far address".to_string()) };
+        tail.extend_from_slice(&far.off.to_le_bytes());
+        tail.extend_from_slice(&far.seg.to_le_bytes());
+      }
+      Oper::OPER_MOFF8 | Oper::OPER_MOFF16 => {
+        let sz = if *oper == Oper::OPER_MOFF8 { Size::Size8 } else { Size::Size16 };
+        let Operand::Mem(m) = operand else { return Err("Expected memory offset".to_string()) };
+        let (None, None, Some(off)) = (m.reg1, m.reg2, m.off) else { return Err("Expected memory offset".to_string()) };
+        if m.sz != sz { return Err("Mismatched memory offset size".to_string()); }
+        tail.extend_from_slice(&off.to_le_bytes());
+        sregs.push((m.sreg, Reg::DS));
+      }
+      Oper::OPER_REL8 | Oper::OPER_REL16 => {
+        let Operand::Rel(r) = operand else { return Err("Expected relative operand".to_string()) };
+        if i != opers.len()-1 { return Err("Relative operand must be last".to_string()); }
+        rel = Some((*oper, ins.rel_addr(r)));
+      }
+      _ => unreachable!(),
+    }
+  }
+
+  let mut out = vec![];
+
+  // Prefixes (in the order NASM emits them): every memory operand picks up the segment
+  // override, so they must all agree
+  let sreg = match sregs.iter().find(|(actual, default)| actual != default) {
+    None => None,
+    Some((sreg, _)) => {
+      if sregs.iter().any(|(actual, _)| actual != sreg) {
+        return Err("Memory operands require conflicting segment overrides".to_string());
+      }
+      Some(*sreg)
+    }
+  };
+  if opts.keep_prefixes {
+    // The decoder already resolved these into the operands, so they're consistent with them
+    out.extend_from_slice(ins.prefixes.as_slice());
+  } else {
+    if ins.lock { out.push(0xf0); }
+    match ins.rep {
+      None => (),
+      Some(Rep::NE) => out.push(0xf2),
+      Some(Rep::EQ) => out.push(0xf3),
+    }
+    if ins.fpu_emu != Some(0x3c) {
+      if let Some(sreg) = sreg { out.push(segment_prefix(sreg)); }
+    }
+  }
+
+  // Opcode
+  match ins.fpu_emu {
+    None if fmt.op1 == 0x0f => out.extend_from_slice(&[0x0f, fmt.op2 as u8]),
+    None if fmt.op1 > 0xff => out.extend_from_slice(&[(fmt.op1 >> 8) as u8, fmt.op1 as u8]),
+    None => out.push(fmt.op1 as u8),
+    Some(0x3c) if is_fpu => {
+      let seg = sregs.first().map(|(actual, _)| *actual).unwrap_or(Reg::DS);
+      let seg_bits = match seg {
+        Reg::DS => 0,
+        Reg::SS => 1,
+        Reg::CS => 2,
+        _       => 3,
+      };
+      // The escape byte with its top 2 bits replaced by the segment
+      out.extend_from_slice(&[0xcd, 0x3c, seg_bits << 6 | (fmt.op1 as u8 & 0x3f)]);
+    }
+    Some(0x3d) if fmt.op1 == 0x9b => out.extend_from_slice(&[0xcd, 0x3d]),
+    Some(vec) if is_fpu && vec as i16 == 0x34 + (fmt.op1 - 0xd8) => out.extend_from_slice(&[0xcd, vec]),
+    Some(vec) => return Err(format!("Invalid 8087 emulator interrupt 0x{:x} for {}", vec, ins.opcode.name())),
+  }
+
+  // ModRM
+  if fmt.requires_modrm() {
+    let modrm = match (modrm_fixed, modrm_rm) {
+      (Some(fixed), _) => fixed,
+      (None, Some((mode, rm))) => mode << 6 | modrm_reg << 3 | rm,
+      (None, None) => return Err("Missing ModRM operand".to_string()),
+    };
+    out.push(modrm);
+  }
+
+  out.extend_from_slice(&tail);
+
+  // Relative branch target is measured from the end of the instruction
+  if let Some((oper, target)) = rel {
+    let sz = if oper == Oper::OPER_REL8 { 1 } else { 2 };
+    let end = ins.addr.add_offset((out.len() + sz) as u16);
+    let val = target.off.0.wrapping_sub(end.off.0);
+    if sz == 1 {
+      if val != val as u8 as i8 as u16 {
+        return Err("Branch target out of rel8 range".to_string());
+      }
+      out.push(val as u8);
+    } else {
+      out.extend_from_slice(&val.to_le_bytes());
+    }
+  }
+
+  Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::asm::decode::decode_one;
+  use crate::region::RegionIter;
+  use crate::segoff::*;
+
+  fn decode(dat: &[u8]) -> Result<Instr, String> {
+    let addr = SegOff { seg: Seg::Normal(0x1000), off: Off(0x0100) };
+    let mut bin = RegionIter::new(dat, addr);
+    Ok(decode_one(&mut bin)?.ok_or("Empty".to_string())?.0)
+  }
+
+  // Rel operands are compared by their branch target since the encoding length may change
+  fn assert_same(a: &Instr, b: &Instr) {
+    assert_eq!(a.opcode, b.opcode);
+    assert_eq!(a.rep, b.rep);
+    assert_eq!(a.lock, b.lock);
+    assert_eq!(a.fpu_emu, b.fpu_emu);
+    assert_eq!(a.intel_hidden_operand_bitmask, b.intel_hidden_operand_bitmask);
+    assert_eq!(a.operands.len(), b.operands.len());
+    for (x, y) in a.operands.as_slice().iter().zip(b.operands.as_slice()) {
+      match (x, y) {
+        (Operand::Rel(x), Operand::Rel(y)) => assert_eq!(a.rel_addr(x), b.rel_addr(y)),
+        _ => assert_eq!(x, y),
+      }
+    }
+  }
+
+  const CANONICAL: &[&[u8]] = &[
+    &[0xba, 0xa7, 0x0e],
+    &[0x8b, 0x2e, 0x02, 0x00],
+    &[0xa3, 0x7d, 0x00],
+    &[0x2e, 0x89, 0x16, 0x60, 0x02],
+    &[0x26, 0x8b, 0x47, 0x04],
+    &[0x8b, 0x46, 0x00],
+    &[0x8b, 0x84, 0x34, 0x12],
+    &[0x83, 0xc4, 0xfc],
+    &[0x81, 0xec, 0x00, 0x01],
+    &[0xc7, 0x46, 0xfe, 0x34, 0x12],
+    &[0x6b, 0x1e, 0x79, 0x1e, 0x6b],
+    &[0xc4, 0x3e, 0x75, 0x00],
+    &[0x9a, 0x78, 0x56, 0x34, 0x12],
+    &[0xff, 0x5e, 0x06],
+    &[0xe8, 0x52, 0x01],
+    &[0x74, 0xfe],
+    &[0xf3, 0xa5],
+    &[0xf2, 0x2e, 0xae],
+    &[0x26, 0xac],
+    &[0xd1, 0xe0],
+    &[0xc1, 0xe8, 0x04],
+    &[0xcc],
+    &[0xc8, 0x04, 0x00, 0x00],
+    &[0x0f, 0x94, 0xc0],
+    &[0x0f, 0x01, 0x1f],
+    &[0xd8, 0x06, 0x34, 0x12],
+    &[0xdc, 0xe9],
+    &[0xd9, 0xe8],
+    &[0x26, 0xdd, 0x07],
+    &[0xcd, 0x35, 0x06, 0x34, 0x12],
+    &[0xcd, 0x3c, 0x1d, 0x07],
+    &[0xcd, 0x3c, 0x5d, 0x07],
+    &[0xcd, 0x3c, 0x9d, 0x07],
+    &[0xcd, 0x3c, 0xdd, 0x07],
+    &[0xcd, 0x3d],
+  ];
+
+  #[test]
+  fn test_canonical() {
+    for dat in CANONICAL {
+      let ins = decode(dat).unwrap();
+      assert_eq!(&encode(&ins).unwrap(), dat, "'{}'", instr_str(&ins));
+      assert_eq!(&encode_exact(&ins, dat).unwrap(), dat, "'{}'", instr_str(&ins));
+    }
+  }
+
+  #[test]
+  fn test_shortest() {
+    // add sp,0xfffc as imm16 => imm8 sign-extended
+    assert_eq!(encode(&decode(&[0x81, 0xc4, 0xfc, 0xff]).unwrap()).unwrap(), [0x83, 0xc4, 0xfc]);
+    // mov ax,[bx+0x0004] as disp16 => disp8
+    assert_eq!(encode(&decode(&[0x8b, 0x87, 0x04, 0x00]).unwrap()).unwrap(), [0x8b, 0x47, 0x04]);
+    // redundant ds: prefix dropped
+    assert_eq!(encode(&decode(&[0x3e, 0x8b, 0x07]).unwrap()).unwrap(), [0x8b, 0x07]);
+    // near jmp to a close target => short jmp to the same target
+    let ins = decode(&[0xe9, 0x10, 0x00]).unwrap();
+    assert_eq!(encode(&ins).unwrap(), [0xeb, 0x11]);
+  }
+
+  #[test]
+  fn test_exact() {
+    for dat in [
+      &[0x81, 0xc4, 0xfc, 0xff][..],
+      &[0x8b, 0x87, 0x04, 0x00],
+      &[0x3e, 0x8b, 0x07],
+      &[0xe9, 0x10, 0x00],
+      &[0x8b, 0xec],
+      &[0x89, 0xe5],
+      &[0x26, 0x2e, 0xf3, 0xa4],
+    ] {
+      let ins = decode(dat).unwrap();
+      assert_eq!(encode_exact(&ins, dat).unwrap(), dat, "'{}'", instr_str(&ins));
+    }
+
+    // Changed operands still keep the length
+    let mut ins = decode(&[0xe9, 0x10, 0x00]).unwrap();
+    ins.operands = crate::util::arrayvec::ArrayVec::new();
+    ins.operands.push(Operand::Rel(OperandRel { val: 0x20 }));
+    assert_eq!(encode_exact(&ins, &[0xe9, 0x10, 0x00]).unwrap(), [0xe9, 0x20, 0x00]);
+  }
+
+  #[test]
+  fn test_roundtrip_all() {
+    // Every opcode1 (and 0x0f opcode2) against every ModRM, with some trailing bytes
+    // for displacements and immediates
+    let mut prefixes: Vec<Vec<u8>> = (0..=255u8).map(|b| vec![b]).collect();
+    prefixes.extend((0..=255u8).map(|b| vec![0x0f, b]));
+    prefixes.extend((0x34..=0x3d).map(|b| vec![0xcd, b]));
+    for prefix in &prefixes {
+      for modrm in 0..=255u8 {
+        let mut dat = prefix.clone();
+        dat.extend_from_slice(&[modrm, 0x12, 0x34, 0x56, 0x78, 0x9a]);
+        let Ok(ins) = decode(&dat) else { continue };
+        let bytes = encode(&ins).unwrap_or_else(|err| panic!("'{}': {}", instr_str(&ins), err));
+        let ins2 = decode(&bytes).unwrap();
+        assert_eq!(ins2.n_bytes as usize, bytes.len());
+        assert_same(&ins, &ins2);
+
+        // Byte-exact, except for ModRM bits the decoder ignores (e.g. the reg field of "pop r/m16")
+        let raw = &dat[..ins.n_bytes as usize];
+        let exact = encode_exact(&ins, raw).unwrap_or_else(|err| panic!("'{}': {}", instr_str(&ins), err));
+        if exact != raw {
+          assert_eq!(exact.len(), raw.len(), "'{}'", instr_str(&ins));
+          assert_same(&ins, &decode(&exact).unwrap());
+        }
+      }
+    }
+  }
+}
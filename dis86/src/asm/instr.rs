@@ -2,14 +2,20 @@ use crate::segoff::SegOff;
 use crate::util::arrayvec::ArrayVec;
 pub use crate::asm::instr_fmt::Opcode;
 
+// Prefix bytes kept per instruction (the 286 rejects anything over 10 bytes long anyway)
+pub const MAX_PREFIXES: usize = 8;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Instr {
   pub rep: Option<Rep>,
+  pub lock: bool,
+  pub prefixes: ArrayVec<u8, MAX_PREFIXES>, // raw prefix bytes as encoded, the last of each kind wins
   pub opcode: Opcode,
   pub operands: ArrayVec<Operand, 3>,
   pub addr: SegOff,
   pub n_bytes: u16,
   pub intel_hidden_operand_bitmask: u8,
+  pub fpu_emu: Option<u8>, // 8087 emulator interrupt (INT 34h-3Dh) this instr was encoded as
 }
 
 impl Instr {
@@ -19,6 +25,29 @@ impl Instr {
   pub fn rel_addr(&self, rel: &OperandRel) -> SegOff {
     self.end_addr().add_offset(rel.val)
   }
+  // Prefix bytes that had no effect because a later prefix of the same kind overrode them
+  pub fn superseded_prefixes(&self) -> impl Iterator<Item=u8> + '_ {
+    let prefixes = self.prefixes.as_slice();
+    prefixes.iter().enumerate()
+      .filter(|(i, b)| prefixes[i+1..].iter().any(|later| prefix_kind(*later) == prefix_kind(**b)))
+      .map(|(_, b)| *b)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefixKind {
+  Segment,
+  Rep,
+  Lock,
+}
+
+pub fn prefix_kind(b: u8) -> Option<PrefixKind> {
+  match b {
+    0x26 | 0x2e | 0x36 | 0x3e => Some(PrefixKind::Segment),
+    0xf2 | 0xf3 => Some(PrefixKind::Rep),
+    0xf0 => Some(PrefixKind::Lock),
+    _ => None,
+  }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -64,6 +93,9 @@ pub enum Size {
   Size8,
   Size16,
   Size32,
+  Size64,
+  Size80,
+  SizeNone, // Memory operands without a natural access size (e.g. FPU environment/state)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -97,6 +129,14 @@ pub enum Reg {
   DS,
   IP,
   FLAGS,
+  ST0,
+  ST1,
+  ST2,
+  ST3,
+  ST4,
+  ST5,
+  ST6,
+  ST7,
 }
 
 impl Reg {
@@ -115,6 +155,11 @@ impl Reg {
     unsafe { std::mem::transmute(Reg::ES as u8 + num) }
   }
 
+  pub fn st(num: u8) -> Reg {
+    assert!(num <= 7);
+    unsafe { std::mem::transmute(Reg::ST0 as u8 + num) }
+  }
+
   pub fn name(&self) -> &'static str {
     match self {
       Reg::AX => "ax",
@@ -139,6 +184,14 @@ impl Reg {
       Reg::DS => "ds",
       Reg::IP => "ip",
       Reg::FLAGS => "flags",
+      Reg::ST0 => "st(0)",
+      Reg::ST1 => "st(1)",
+      Reg::ST2 => "st(2)",
+      Reg::ST3 => "st(3)",
+      Reg::ST4 => "st(4)",
+      Reg::ST5 => "st(5)",
+      Reg::ST6 => "st(6)",
+      Reg::ST7 => "st(7)",
     }
   }
 }
@@ -172,6 +225,14 @@ const REG_INFO: &[RegInfo] = &[
   RegInfo { name: "DS",    sz: Size::Size16, seg: true  },
   RegInfo { name: "IP",    sz: Size::Size16, seg: false },
   RegInfo { name: "FLAGS", sz: Size::Size16, seg: false },
+  RegInfo { name: "ST0",   sz: Size::Size80, seg: false },
+  RegInfo { name: "ST1",   sz: Size::Size80, seg: false },
+  RegInfo { name: "ST2",   sz: Size::Size80, seg: false },
+  RegInfo { name: "ST3",   sz: Size::Size80, seg: false },
+  RegInfo { name: "ST4",   sz: Size::Size80, seg: false },
+  RegInfo { name: "ST5",   sz: Size::Size80, seg: false },
+  RegInfo { name: "ST6",   sz: Size::Size80, seg: false },
+  RegInfo { name: "ST7",   sz: Size::Size80, seg: false },
 ];
 
 impl Reg {
@@ -205,6 +266,14 @@ impl Reg {
       "DS" => Some(Reg::DS),
       "IP" => Some(Reg::IP),
       "FLAGS" => Some(Reg::FLAGS),
+      "ST0" => Some(Reg::ST0),
+      "ST1" => Some(Reg::ST1),
+      "ST2" => Some(Reg::ST2),
+      "ST3" => Some(Reg::ST3),
+      "ST4" => Some(Reg::ST4),
+      "ST5" => Some(Reg::ST5),
+      "ST6" => Some(Reg::ST6),
+      "ST7" => Some(Reg::ST7),
       _ => None,
     }
   }
@@ -47,9 +47,15 @@ pub enum Oper {
   OPER_SREG,   // Second register field from ModRM byte (interpreted as an SREG)
 
   // Explicit memory operands
-  OPER_M8,     // Memory operand to address 8-bit from ModRM (no reg allowed)
   OPER_M16,    // Memory operand to address 16-bit from ModRM (no reg allowed)
   OPER_M32,    // Memory operand to address 32-bit from ModRM (no reg allowed)
+  OPER_M64,    // Memory operand to address 64-bit from ModRM (no reg allowed)
+  OPER_M80,    // Memory operand to address 80-bit from ModRM (no reg allowed)
+  OPER_MENV,   // Memory operand to an FPU environment/state image from ModRM (no reg allowed)
+
+  // FPU stack register operands
+  OPER_ST,     // Implied top-of-stack: st(0)
+  OPER_STI,    // Stack register st(i) from the RM field of the ModRM byte
 
   // Explicit register or memory operands (modrm)
   OPER_RM8,    // Either Register of memory operand, always 8-bit
@@ -76,16 +82,20 @@ pub enum Oper {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Opcode {
   OP_AAA,
+  OP_AAD,
+  OP_AAM,
   OP_AAS,
   OP_ADC,
   OP_ADD,
   OP_AND,
+  OP_BOUND,
   OP_CALL,
   OP_CALLF,
   OP_CBW,
   OP_CLC,
   OP_CLD,
   OP_CLI,
+  OP_CLTS,
   OP_CMC,
   OP_CMP,
   OP_CMPS,
@@ -95,7 +105,85 @@ pub enum Opcode {
   OP_DEC,
   OP_DIV,
   OP_ENTER,
+  OP_F2XM1,
+  OP_FABS,
+  OP_FADD,
+  OP_FADDP,
+  OP_FBLD,
+  OP_FBSTP,
+  OP_FCHS,
+  OP_FCOM,
+  OP_FCOMP,
+  OP_FCOMPP,
+  OP_FCOS,
+  OP_FDECSTP,
+  OP_FDIV,
+  OP_FDIVP,
+  OP_FDIVR,
+  OP_FDIVRP,
+  OP_FFREE,
+  OP_FIADD,
+  OP_FICOM,
+  OP_FICOMP,
+  OP_FIDIV,
+  OP_FIDIVR,
+  OP_FILD,
+  OP_FIMUL,
+  OP_FINCSTP,
+  OP_FIST,
+  OP_FISTP,
+  OP_FISUB,
+  OP_FISUBR,
+  OP_FLD,
+  OP_FLD1,
+  OP_FLDCW,
+  OP_FLDENV,
+  OP_FLDL2E,
+  OP_FLDL2T,
+  OP_FLDLG2,
+  OP_FLDLN2,
+  OP_FLDPI,
+  OP_FLDZ,
+  OP_FMUL,
+  OP_FMULP,
+  OP_FNCLEX,
+  OP_FNDISI,
+  OP_FNENI,
+  OP_FNINIT,
+  OP_FNOP,
+  OP_FNSAVE,
+  OP_FNSETPM,
+  OP_FNSTCW,
+  OP_FNSTENV,
+  OP_FNSTSW,
+  OP_FPATAN,
+  OP_FPREM,
+  OP_FPREM1,
+  OP_FPTAN,
+  OP_FRNDINT,
+  OP_FRSTOR,
+  OP_FSCALE,
+  OP_FSIN,
+  OP_FSINCOS,
+  OP_FSQRT,
+  OP_FST,
+  OP_FSTP,
+  OP_FSUB,
+  OP_FSUBP,
+  OP_FSUBR,
+  OP_FSUBRP,
+  OP_FTST,
+  OP_FUCOM,
+  OP_FUCOMP,
+  OP_FUCOMPP,
+  OP_FWAIT,
+  OP_FXAM,
+  OP_FXCH,
+  OP_FXTRACT,
+  OP_FYL2X,
+  OP_FYL2XP1,
   OP_HLT,
+  OP_IDIV,
   OP_IMUL,
   OP_IMUL_TRUNC,
   OP_IN,
@@ -129,6 +217,9 @@ pub enum Opcode {
   OP_LEA,
   OP_LEAVE,
   OP_LES,
+  OP_LGDT,
+  OP_LIDT,
+  OP_LMSW,
   OP_LODS,
   OP_LOOP,
   OP_LOOPE,
@@ -171,11 +262,14 @@ pub enum Opcode {
   OP_SETLE,
   OP_SETP,
   OP_SETS,
+  OP_SGDT,
   OP_SETNE,
   OP_SETNP,
   OP_SETNS,
   OP_SHL,
   OP_SHR,
+  OP_SIDT,
+  OP_SMSW,
   OP_STC,
   OP_STD,
   OP_STI,
@@ -191,16 +285,20 @@ impl Opcode {
   pub fn name(&self) -> &'static str {
     match self {
       Opcode::OP_AAA =>     "aaa",
+      Opcode::OP_AAD =>     "aad",
+      Opcode::OP_AAM =>     "aam",
       Opcode::OP_AAS =>     "aas",
       Opcode::OP_ADC =>     "adc",
       Opcode::OP_ADD =>     "add",
       Opcode::OP_AND =>     "and",
+      Opcode::OP_BOUND =>   "bound",
       Opcode::OP_CALL =>    "call",
       Opcode::OP_CALLF =>   "callf",
       Opcode::OP_CBW =>     "cbw",
       Opcode::OP_CLC =>     "clc",
       Opcode::OP_CLD =>     "cld",
       Opcode::OP_CLI =>     "cli",
+      Opcode::OP_CLTS =>    "clts",
       Opcode::OP_CMC =>     "cmc",
       Opcode::OP_CMP =>     "cmp",
       Opcode::OP_CMPS =>    "cmps",
@@ -210,7 +308,85 @@ impl Opcode {
       Opcode::OP_DEC =>     "dec",
       Opcode::OP_DIV =>     "div",
       Opcode::OP_ENTER =>   "enter",
+      Opcode::OP_F2XM1 =>   "f2xm1",
+      Opcode::OP_FABS =>    "fabs",
+      Opcode::OP_FADD =>    "fadd",
+      Opcode::OP_FADDP =>   "faddp",
+      Opcode::OP_FBLD =>    "fbld",
+      Opcode::OP_FBSTP =>   "fbstp",
+      Opcode::OP_FCHS =>    "fchs",
+      Opcode::OP_FCOM =>    "fcom",
+      Opcode::OP_FCOMP =>   "fcomp",
+      Opcode::OP_FCOMPP =>  "fcompp",
+      Opcode::OP_FCOS =>    "fcos",
+      Opcode::OP_FDECSTP => "fdecstp",
+      Opcode::OP_FDIV =>    "fdiv",
+      Opcode::OP_FDIVP =>   "fdivp",
+      Opcode::OP_FDIVR =>   "fdivr",
+      Opcode::OP_FDIVRP =>  "fdivrp",
+      Opcode::OP_FFREE =>   "ffree",
+      Opcode::OP_FIADD =>   "fiadd",
+      Opcode::OP_FICOM =>   "ficom",
+      Opcode::OP_FICOMP =>  "ficomp",
+      Opcode::OP_FIDIV =>   "fidiv",
+      Opcode::OP_FIDIVR =>  "fidivr",
+      Opcode::OP_FILD =>    "fild",
+      Opcode::OP_FIMUL =>   "fimul",
+      Opcode::OP_FINCSTP => "fincstp",
+      Opcode::OP_FIST =>    "fist",
+      Opcode::OP_FISTP =>   "fistp",
+      Opcode::OP_FISUB =>   "fisub",
+      Opcode::OP_FISUBR =>  "fisubr",
+      Opcode::OP_FLD =>     "fld",
+      Opcode::OP_FLD1 =>    "fld1",
+      Opcode::OP_FLDCW =>   "fldcw",
+      Opcode::OP_FLDENV =>  "fldenv",
+      Opcode::OP_FLDL2E =>  "fldl2e",
+      Opcode::OP_FLDL2T =>  "fldl2t",
+      Opcode::OP_FLDLG2 =>  "fldlg2",
+      Opcode::OP_FLDLN2 =>  "fldln2",
+      Opcode::OP_FLDPI =>   "fldpi",
+      Opcode::OP_FLDZ =>    "fldz",
+      Opcode::OP_FMUL =>    "fmul",
+      Opcode::OP_FMULP =>   "fmulp",
+      Opcode::OP_FNCLEX =>  "fnclex",
+      Opcode::OP_FNDISI =>  "fndisi",
+      Opcode::OP_FNENI =>   "fneni",
+      Opcode::OP_FNINIT =>  "fninit",
+      Opcode::OP_FNOP =>    "fnop",
+      Opcode::OP_FNSAVE =>  "fnsave",
+      Opcode::OP_FNSETPM => "fnsetpm",
+      Opcode::OP_FNSTCW =>  "fnstcw",
+      Opcode::OP_FNSTENV => "fnstenv",
+      Opcode::OP_FNSTSW =>  "fnstsw",
+      Opcode::OP_FPATAN =>  "fpatan",
+      Opcode::OP_FPREM =>   "fprem",
+      Opcode::OP_FPREM1 =>  "fprem1",
+      Opcode::OP_FPTAN =>   "fptan",
+      Opcode::OP_FRNDINT => "frndint",
+      Opcode::OP_FRSTOR =>  "frstor",
+      Opcode::OP_FSCALE =>  "fscale",
+      Opcode::OP_FSIN =>    "fsin",
+      Opcode::OP_FSINCOS => "fsincos",
+      Opcode::OP_FSQRT =>   "fsqrt",
+      Opcode::OP_FST =>     "fst",
+      Opcode::OP_FSTP =>    "fstp",
+      Opcode::OP_FSUB =>    "fsub",
+      Opcode::OP_FSUBP =>   "fsubp",
+      Opcode::OP_FSUBR =>   "fsubr",
+      Opcode::OP_FSUBRP =>  "fsubrp",
+      Opcode::OP_FTST =>    "ftst",
+      Opcode::OP_FUCOM =>   "fucom",
+      Opcode::OP_FUCOMP =>  "fucomp",
+      Opcode::OP_FUCOMPP => "fucompp",
+      Opcode::OP_FWAIT =>   "fwait",
+      Opcode::OP_FXAM =>    "fxam",
+      Opcode::OP_FXCH =>    "fxch",
+      Opcode::OP_FXTRACT => "fxtract",
+      Opcode::OP_FYL2X =>   "fyl2x",
+      Opcode::OP_FYL2XP1 => "fyl2xp1",
       Opcode::OP_HLT =>     "hlt",
+      Opcode::OP_IDIV =>    "idiv",
       Opcode::OP_IMUL =>    "imul",
       Opcode::OP_IMUL_TRUNC => "imul",
       Opcode::OP_IN =>      "in",
@@ -244,6 +420,9 @@ impl Opcode {
       Opcode::OP_LEA =>     "lea",
       Opcode::OP_LEAVE =>   "leave",
       Opcode::OP_LES =>     "les",
+      Opcode::OP_LGDT =>    "lgdt",
+      Opcode::OP_LIDT =>    "lidt",
+      Opcode::OP_LMSW =>    "lmsw",
       Opcode::OP_LODS =>    "lods",
       Opcode::OP_LOOP =>    "loop",
       Opcode::OP_LOOPE =>   "loope",
@@ -282,6 +461,7 @@ impl Opcode {
       Opcode::OP_SETBE =>   "setbe",
       Opcode::OP_SETA =>    "seta",
       Opcode::OP_SETS =>    "sets",
+      Opcode::OP_SGDT =>    "sgdt",
       Opcode::OP_SETNS =>   "setns",
       Opcode::OP_SETP =>    "setp",
       Opcode::OP_SETNP =>   "setnp",
@@ -291,6 +471,8 @@ impl Opcode {
       Opcode::OP_SETG =>    "setg",
       Opcode::OP_SHL =>     "shl",
       Opcode::OP_SHR =>     "shr",
+      Opcode::OP_SIDT =>    "sidt",
+      Opcode::OP_SMSW =>    "smsw",
       Opcode::OP_STC =>     "stc",
       Opcode::OP_STD =>     "std",
       Opcode::OP_STI =>     "sti",
@@ -315,14 +497,21 @@ pub struct InstrFmt {
 
 impl InstrFmt {
   pub fn requires_modrm(&self) -> bool {
+    // FPU escapes always carry a ModRM byte, even when it only selects the operation
+    if is_fpu_escape(self.op1) {
+      return true;
+    }
     for o in &self.oper {
       match o {
         Oper::OPER_R8   => return true,
         Oper::OPER_R16  => return true,
         Oper::OPER_SREG => return true,
-        Oper::OPER_M8   => return true,
         Oper::OPER_M16  => return true,
         Oper::OPER_M32  => return true,
+        Oper::OPER_M64  => return true,
+        Oper::OPER_M80  => return true,
+        Oper::OPER_MENV => return true,
+        Oper::OPER_STI  => return true,
         Oper::OPER_RM8  => return true,
         Oper::OPER_RM16 => return true,
         _ => (),
@@ -348,6 +537,14 @@ const INSTR_TBL: &[InstrFmt] = &[
   InstrFmt {  op: Opcode::OP_OR,        op1: 0x0c,     op2: -1,   oper: [Oper::OPER_AL,      Oper::OPER_IMM8,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_OR,        op1: 0x0d,     op2: -1,   oper: [Oper::OPER_AX,      Oper::OPER_IMM16,   Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_PUSH,      op1: 0x0e,     op2: -1,   oper: [Oper::OPER_CS,      Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_CLTS,      op1: 0x0f,     op2:  6,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  // TWO-BYTE GROUP: 0x0f 0x01 (keyed by the full opcode in op1)
+  InstrFmt {  op: Opcode::OP_SGDT,      op1: 0x0f01,   op2:  0,   oper: [Oper::OPER_MENV,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_SIDT,      op1: 0x0f01,   op2:  1,   oper: [Oper::OPER_MENV,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_LGDT,      op1: 0x0f01,   op2:  2,   oper: [Oper::OPER_MENV,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_LIDT,      op1: 0x0f01,   op2:  3,   oper: [Oper::OPER_MENV,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_SMSW,      op1: 0x0f01,   op2:  4,   oper: [Oper::OPER_RM16,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_LMSW,      op1: 0x0f01,   op2:  6,   oper: [Oper::OPER_RM16,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_SETO,      op1: 0x0f,     op2: 0x90, oper: [Oper::OPER_RM8,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_SETNO,     op1: 0x0f,     op2: 0x91, oper: [Oper::OPER_RM8,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_SETB,      op1: 0x0f,     op2: 0x92, oper: [Oper::OPER_RM8,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
@@ -450,7 +647,7 @@ const INSTR_TBL: &[InstrFmt] = &[
   InstrFmt {  op: Opcode::OP_POP,       op1: 0x5f,     op2: -1,   oper: [Oper::OPER_DI,      Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_PUSHA,     op1: 0x60,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_POPA,      op1: 0x61,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INVAL,     op1: 0x62,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_BOUND,     op1: 0x62,     op2: -1,   oper: [Oper::OPER_R16,     Oper::OPER_M32,     Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_INVAL,     op1: 0x63,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_INVAL,     op1: 0x64,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_INVAL,     op1: 0x65,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
@@ -460,10 +657,10 @@ const INSTR_TBL: &[InstrFmt] = &[
   InstrFmt {  op: Opcode::OP_IMUL_TRUNC,op1: 0x69,     op2: -1,   oper: [Oper::OPER_R16,     Oper::OPER_RM16,    Oper::OPER_IMM16],     hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_PUSH,      op1: 0x6a,     op2: -1,   oper: [Oper::OPER_IMM8,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_IMUL_TRUNC,op1: 0x6b,     op2: -1,   oper: [Oper::OPER_R16,     Oper::OPER_RM16,    Oper::OPER_IMM8],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INS,       op1: 0x6c,     op2: -1,   oper: [Oper::OPER_M8,      Oper::OPER_DX,      Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INS,       op1: 0x6d,     op2: -1,   oper: [Oper::OPER_M16,     Oper::OPER_DX,      Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_OUTS,      op1: 0x6e,     op2: -1,   oper: [Oper::OPER_DX,      Oper::OPER_M8,      Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_OUTS,      op1: 0x6f,     op2: -1,   oper: [Oper::OPER_DX,      Oper::OPER_M16,     Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_INS,       op1: 0x6c,     op2: -1,   oper: [Oper::OPER_DST8,    Oper::OPER_DX,      Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_INS,       op1: 0x6d,     op2: -1,   oper: [Oper::OPER_DST16,   Oper::OPER_DX,      Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_OUTS,      op1: 0x6e,     op2: -1,   oper: [Oper::OPER_DX,      Oper::OPER_SRC8,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_OUTS,      op1: 0x6f,     op2: -1,   oper: [Oper::OPER_DX,      Oper::OPER_SRC16,   Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_JO,        op1: 0x70,     op2: -1,   oper: [Oper::OPER_REL8,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_JNO,       op1: 0x71,     op2: -1,   oper: [Oper::OPER_REL8,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_JB,        op1: 0x72,     op2: -1,   oper: [Oper::OPER_REL8,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
@@ -535,7 +732,7 @@ const INSTR_TBL: &[InstrFmt] = &[
   InstrFmt {  op: Opcode::OP_CBW,       op1: 0x98,     op2: -1,   oper: [Oper::OPER_AX,      Oper::OPER_AL,      Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_CWD,       op1: 0x99,     op2: -1,   oper: [Oper::OPER_DX,      Oper::OPER_AX,      Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_CALLF,     op1: 0x9a,     op2: -1,   oper: [Oper::OPER_FAR32,   Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INVAL,     op1: 0x9b,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FWAIT,     op1: 0x9b,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_PUSHF,     op1: 0x9c,     op2: -1,   oper: [Oper::OPER_FLAGS,   Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x1 },
   InstrFmt {  op: Opcode::OP_POPF,      op1: 0x9d,     op2: -1,   oper: [Oper::OPER_FLAGS,   Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x1 },
   InstrFmt {  op: Opcode::OP_SAHF,      op1: 0x9e,     op2: -1,   oper: [Oper::OPER_AH,      Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
@@ -634,18 +831,138 @@ const INSTR_TBL: &[InstrFmt] = &[
   InstrFmt {  op: Opcode::OP_SHR,       op1: 0xd3,     op2:  5,   oper: [Oper::OPER_RM16,    Oper::OPER_CL,      Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_SHL,       op1: 0xd3,     op2:  6,   oper: [Oper::OPER_RM16,    Oper::OPER_CL,      Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_SAR,       op1: 0xd3,     op2:  7,   oper: [Oper::OPER_RM16,    Oper::OPER_CL,      Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INVAL,     op1: 0xd4,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INVAL,     op1: 0xd5,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_AAM,       op1: 0xd4,     op2: -1,   oper: [Oper::OPER_AX,      Oper::OPER_IMM8,    Oper::OPER_NONE],      hidden: 0x1 },
+  InstrFmt {  op: Opcode::OP_AAD,       op1: 0xd5,     op2: -1,   oper: [Oper::OPER_AX,      Oper::OPER_IMM8,    Oper::OPER_NONE],      hidden: 0x1 },
   InstrFmt {  op: Opcode::OP_INVAL,     op1: 0xd6,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_XLAT,      op1: 0xd7,     op2: -1,   oper: [Oper::OPER_AL,      Oper::OPER_DS,      Oper::OPER_BX],        hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INVAL,     op1: 0xd8,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INVAL,     op1: 0xd9,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INVAL,     op1: 0xda,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INVAL,     op1: 0xdb,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INVAL,     op1: 0xdc,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INVAL,     op1: 0xdd,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INVAL,     op1: 0xde,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_INVAL,     op1: 0xdf,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  // FPU ESCAPE: 0xd8
+  InstrFmt {  op: Opcode::OP_FADD,      op1: 0xd8,     op2:  0,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FMUL,      op1: 0xd8,     op2:  1,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FCOM,      op1: 0xd8,     op2:  2,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FCOMP,     op1: 0xd8,     op2:  3,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSUB,      op1: 0xd8,     op2:  4,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSUBR,     op1: 0xd8,     op2:  5,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FDIV,      op1: 0xd8,     op2:  6,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FDIVR,     op1: 0xd8,     op2:  7,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FADD,      op1: 0xd8,     op2: 0xc0, oper: [Oper::OPER_ST,      Oper::OPER_STI,     Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FMUL,      op1: 0xd8,     op2: 0xc8, oper: [Oper::OPER_ST,      Oper::OPER_STI,     Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FCOM,      op1: 0xd8,     op2: 0xd0, oper: [Oper::OPER_STI,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FCOMP,     op1: 0xd8,     op2: 0xd8, oper: [Oper::OPER_STI,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSUB,      op1: 0xd8,     op2: 0xe0, oper: [Oper::OPER_ST,      Oper::OPER_STI,     Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSUBR,     op1: 0xd8,     op2: 0xe8, oper: [Oper::OPER_ST,      Oper::OPER_STI,     Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FDIV,      op1: 0xd8,     op2: 0xf0, oper: [Oper::OPER_ST,      Oper::OPER_STI,     Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FDIVR,     op1: 0xd8,     op2: 0xf8, oper: [Oper::OPER_ST,      Oper::OPER_STI,     Oper::OPER_NONE],      hidden: 0x0 },
+  // FPU ESCAPE: 0xd9
+  InstrFmt {  op: Opcode::OP_FLD,       op1: 0xd9,     op2:  0,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FST,       op1: 0xd9,     op2:  2,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSTP,      op1: 0xd9,     op2:  3,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FLDENV,    op1: 0xd9,     op2:  4,   oper: [Oper::OPER_MENV,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FLDCW,     op1: 0xd9,     op2:  5,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FNSTENV,   op1: 0xd9,     op2:  6,   oper: [Oper::OPER_MENV,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FNSTCW,    op1: 0xd9,     op2:  7,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FLD,       op1: 0xd9,     op2: 0xc0, oper: [Oper::OPER_STI,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FXCH,      op1: 0xd9,     op2: 0xc8, oper: [Oper::OPER_STI,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FNOP,      op1: 0xd9,     op2: 0xd0, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FCHS,      op1: 0xd9,     op2: 0xe0, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FABS,      op1: 0xd9,     op2: 0xe1, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FTST,      op1: 0xd9,     op2: 0xe4, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FXAM,      op1: 0xd9,     op2: 0xe5, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FLD1,      op1: 0xd9,     op2: 0xe8, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FLDL2T,    op1: 0xd9,     op2: 0xe9, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FLDL2E,    op1: 0xd9,     op2: 0xea, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FLDPI,     op1: 0xd9,     op2: 0xeb, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FLDLG2,    op1: 0xd9,     op2: 0xec, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FLDLN2,    op1: 0xd9,     op2: 0xed, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FLDZ,      op1: 0xd9,     op2: 0xee, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_F2XM1,     op1: 0xd9,     op2: 0xf0, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FYL2X,     op1: 0xd9,     op2: 0xf1, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FPTAN,     op1: 0xd9,     op2: 0xf2, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FPATAN,    op1: 0xd9,     op2: 0xf3, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FXTRACT,   op1: 0xd9,     op2: 0xf4, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FPREM1,    op1: 0xd9,     op2: 0xf5, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FDECSTP,   op1: 0xd9,     op2: 0xf6, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FINCSTP,   op1: 0xd9,     op2: 0xf7, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FPREM,     op1: 0xd9,     op2: 0xf8, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FYL2XP1,   op1: 0xd9,     op2: 0xf9, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSQRT,     op1: 0xd9,     op2: 0xfa, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSINCOS,   op1: 0xd9,     op2: 0xfb, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FRNDINT,   op1: 0xd9,     op2: 0xfc, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSCALE,    op1: 0xd9,     op2: 0xfd, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSIN,      op1: 0xd9,     op2: 0xfe, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FCOS,      op1: 0xd9,     op2: 0xff, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  // FPU ESCAPE: 0xda
+  InstrFmt {  op: Opcode::OP_FIADD,     op1: 0xda,     op2:  0,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FIMUL,     op1: 0xda,     op2:  1,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FICOM,     op1: 0xda,     op2:  2,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FICOMP,    op1: 0xda,     op2:  3,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FISUB,     op1: 0xda,     op2:  4,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FISUBR,    op1: 0xda,     op2:  5,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FIDIV,     op1: 0xda,     op2:  6,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FIDIVR,    op1: 0xda,     op2:  7,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FUCOMPP,   op1: 0xda,     op2: 0xe9, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  // FPU ESCAPE: 0xdb
+  InstrFmt {  op: Opcode::OP_FILD,      op1: 0xdb,     op2:  0,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FIST,      op1: 0xdb,     op2:  2,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FISTP,     op1: 0xdb,     op2:  3,   oper: [Oper::OPER_M32,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FLD,       op1: 0xdb,     op2:  5,   oper: [Oper::OPER_M80,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSTP,      op1: 0xdb,     op2:  7,   oper: [Oper::OPER_M80,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FNENI,     op1: 0xdb,     op2: 0xe0, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FNDISI,    op1: 0xdb,     op2: 0xe1, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FNCLEX,    op1: 0xdb,     op2: 0xe2, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FNINIT,    op1: 0xdb,     op2: 0xe3, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FNSETPM,   op1: 0xdb,     op2: 0xe4, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  // FPU ESCAPE: 0xdc
+  InstrFmt {  op: Opcode::OP_FADD,      op1: 0xdc,     op2:  0,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FMUL,      op1: 0xdc,     op2:  1,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FCOM,      op1: 0xdc,     op2:  2,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FCOMP,     op1: 0xdc,     op2:  3,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSUB,      op1: 0xdc,     op2:  4,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSUBR,     op1: 0xdc,     op2:  5,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FDIV,      op1: 0xdc,     op2:  6,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FDIVR,     op1: 0xdc,     op2:  7,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FADD,      op1: 0xdc,     op2: 0xc0, oper: [Oper::OPER_STI,     Oper::OPER_ST,      Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FMUL,      op1: 0xdc,     op2: 0xc8, oper: [Oper::OPER_STI,     Oper::OPER_ST,      Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSUBR,     op1: 0xdc,     op2: 0xe0, oper: [Oper::OPER_STI,     Oper::OPER_ST,      Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSUB,      op1: 0xdc,     op2: 0xe8, oper: [Oper::OPER_STI,     Oper::OPER_ST,      Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FDIVR,     op1: 0xdc,     op2: 0xf0, oper: [Oper::OPER_STI,     Oper::OPER_ST,      Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FDIV,      op1: 0xdc,     op2: 0xf8, oper: [Oper::OPER_STI,     Oper::OPER_ST,      Oper::OPER_NONE],      hidden: 0x0 },
+  // FPU ESCAPE: 0xdd
+  InstrFmt {  op: Opcode::OP_FLD,       op1: 0xdd,     op2:  0,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FST,       op1: 0xdd,     op2:  2,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSTP,      op1: 0xdd,     op2:  3,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FRSTOR,    op1: 0xdd,     op2:  4,   oper: [Oper::OPER_MENV,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FNSAVE,    op1: 0xdd,     op2:  6,   oper: [Oper::OPER_MENV,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FNSTSW,    op1: 0xdd,     op2:  7,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FFREE,     op1: 0xdd,     op2: 0xc0, oper: [Oper::OPER_STI,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FST,       op1: 0xdd,     op2: 0xd0, oper: [Oper::OPER_STI,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSTP,      op1: 0xdd,     op2: 0xd8, oper: [Oper::OPER_STI,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FUCOM,     op1: 0xdd,     op2: 0xe0, oper: [Oper::OPER_STI,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FUCOMP,    op1: 0xdd,     op2: 0xe8, oper: [Oper::OPER_STI,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  // FPU ESCAPE: 0xde
+  InstrFmt {  op: Opcode::OP_FIADD,     op1: 0xde,     op2:  0,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FIMUL,     op1: 0xde,     op2:  1,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FICOM,     op1: 0xde,     op2:  2,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FICOMP,    op1: 0xde,     op2:  3,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FISUB,     op1: 0xde,     op2:  4,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FISUBR,    op1: 0xde,     op2:  5,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FIDIV,     op1: 0xde,     op2:  6,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FIDIVR,    op1: 0xde,     op2:  7,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FADDP,     op1: 0xde,     op2: 0xc0, oper: [Oper::OPER_STI,     Oper::OPER_ST,      Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FMULP,     op1: 0xde,     op2: 0xc8, oper: [Oper::OPER_STI,     Oper::OPER_ST,      Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FCOMPP,    op1: 0xde,     op2: 0xd9, oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSUBRP,    op1: 0xde,     op2: 0xe0, oper: [Oper::OPER_STI,     Oper::OPER_ST,      Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FSUBP,     op1: 0xde,     op2: 0xe8, oper: [Oper::OPER_STI,     Oper::OPER_ST,      Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FDIVRP,    op1: 0xde,     op2: 0xf0, oper: [Oper::OPER_STI,     Oper::OPER_ST,      Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FDIVP,     op1: 0xde,     op2: 0xf8, oper: [Oper::OPER_STI,     Oper::OPER_ST,      Oper::OPER_NONE],      hidden: 0x0 },
+  // FPU ESCAPE: 0xdf
+  InstrFmt {  op: Opcode::OP_FILD,      op1: 0xdf,     op2:  0,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FIST,      op1: 0xdf,     op2:  2,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FISTP,     op1: 0xdf,     op2:  3,   oper: [Oper::OPER_M16,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FBLD,      op1: 0xdf,     op2:  4,   oper: [Oper::OPER_M80,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FILD,      op1: 0xdf,     op2:  5,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FBSTP,     op1: 0xdf,     op2:  6,   oper: [Oper::OPER_M80,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FISTP,     op1: 0xdf,     op2:  7,   oper: [Oper::OPER_M64,     Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_FNSTSW,    op1: 0xdf,     op2: 0xe0, oper: [Oper::OPER_AX,      Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_LOOPNE,    op1: 0xe0,     op2: -1,   oper: [Oper::OPER_CX,      Oper::OPER_REL8,    Oper::OPER_NONE],      hidden: 0x1 },
   InstrFmt {  op: Opcode::OP_LOOPE,     op1: 0xe1,     op2: -1,   oper: [Oper::OPER_CX,      Oper::OPER_REL8,    Oper::OPER_NONE],      hidden: 0x1 },
   InstrFmt {  op: Opcode::OP_LOOP,      op1: 0xe2,     op2: -1,   oper: [Oper::OPER_CX,      Oper::OPER_REL8,    Oper::OPER_NONE],      hidden: 0x1 },
@@ -676,7 +993,7 @@ const INSTR_TBL: &[InstrFmt] = &[
   InstrFmt {  op: Opcode::OP_MUL,       op1: 0xf6,     op2:  4,   oper: [Oper::OPER_AX,      Oper::OPER_AL,      Oper::OPER_RM8],       hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_IMUL,      op1: 0xf6,     op2:  5,   oper: [Oper::OPER_AX,      Oper::OPER_AL,      Oper::OPER_RM8],       hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_DIV,       op1: 0xf6,     op2:  6,   oper: [Oper::OPER_AH,      Oper::OPER_AL,      Oper::OPER_RM8],       hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_DIV,       op1: 0xf6,     op2:  7,   oper: [Oper::OPER_AH,      Oper::OPER_AL,      Oper::OPER_RM8],       hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_IDIV,      op1: 0xf6,     op2:  7,   oper: [Oper::OPER_AH,      Oper::OPER_AL,      Oper::OPER_RM8],       hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_TEST,      op1: 0xf7,     op2:  0,   oper: [Oper::OPER_RM16,    Oper::OPER_IMM16,   Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_TEST,      op1: 0xf7,     op2:  1,   oper: [Oper::OPER_RM16,    Oper::OPER_IMM16,   Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_NOT,       op1: 0xf7,     op2:  2,   oper: [Oper::OPER_RM16,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
@@ -684,7 +1001,7 @@ const INSTR_TBL: &[InstrFmt] = &[
   InstrFmt {  op: Opcode::OP_MUL,       op1: 0xf7,     op2:  4,   oper: [Oper::OPER_DX,      Oper::OPER_AX,      Oper::OPER_RM16],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_IMUL,      op1: 0xf7,     op2:  5,   oper: [Oper::OPER_DX,      Oper::OPER_AX,      Oper::OPER_RM16],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_DIV,       op1: 0xf7,     op2:  6,   oper: [Oper::OPER_DX,      Oper::OPER_AX,      Oper::OPER_RM16],      hidden: 0x0 },
-  InstrFmt {  op: Opcode::OP_DIV,       op1: 0xf7,     op2:  7,   oper: [Oper::OPER_DX,      Oper::OPER_AX,      Oper::OPER_RM16],      hidden: 0x0 },
+  InstrFmt {  op: Opcode::OP_IDIV,      op1: 0xf7,     op2:  7,   oper: [Oper::OPER_DX,      Oper::OPER_AX,      Oper::OPER_RM16],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_CLC,       op1: 0xf8,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_STC,       op1: 0xf9,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
   InstrFmt {  op: Opcode::OP_CLI,       op1: 0xfa,     op2: -1,   oper: [Oper::OPER_NONE,    Oper::OPER_NONE,    Oper::OPER_NONE],      hidden: 0x0 },
@@ -707,6 +1024,12 @@ pub enum Error {
   NotFound,
   NeedOpcode2,
   NeedOpcode2Ext0F,
+  NeedOpcode2Fpu,
+  NeedOpcode3Ext0F,
+}
+
+pub fn is_fpu_escape(op1: i16) -> bool {
+  (0xd8..=0xdf).contains(&op1)
 }
 
 pub fn lookup(opcode1: u8, opcode2: Option<u8>) -> Result<&'static InstrFmt, Error> {
@@ -731,26 +1054,172 @@ pub fn lookup(opcode1: u8, opcode2: Option<u8>) -> Result<&'static InstrFmt, Err
     }
   }
 
+  if op1 == 0x0f && op2 != -1 && INSTR_TBL.iter().any(|fmt| fmt.op1 == 0x0f00 | op2) {
+    return Err(Error::NeedOpcode3Ext0F)
+  }
+
   if op1_found && op2 == -1 {
-    if op1 != 0x0f {
-      return Err(Error::NeedOpcode2)
-    } else {
+    if op1 == 0x0f {
       return Err(Error::NeedOpcode2Ext0F)
+    } else if is_fpu_escape(op1) {
+      return Err(Error::NeedOpcode2Fpu)
+    } else {
+      return Err(Error::NeedOpcode2)
     }
   }
 
   Err(Error::NotFound)
 }
 
+// All formats that decode to the given opcode (in table order)
+pub fn formats(op: Opcode) -> impl Iterator<Item = &'static InstrFmt> {
+  INSTR_TBL.iter().filter(move |fmt| fmt.op == op)
+}
+
+// Two-byte groups (0x0f 0x01) are keyed with the full opcode in op1 and the
+// ModRM reg-field as op2
+pub fn lookup_0f_group(opcode2: u8, modrm: u8) -> Result<&'static InstrFmt, Error> {
+  let op1 = 0x0f00 | opcode2 as i16;
+  let op2 = ((modrm>>3)&7) as i16;
+  INSTR_TBL.iter().find(|fmt| fmt.op1 == op1 && fmt.op2 == op2).ok_or(Error::NotFound)
+}
+
+// FPU escapes (0xd8-0xdf) are keyed on the ModRM byte:
+//   - Memory forms use the reg-field as opcode2 (0-7), like any other group
+//   - Register forms use the whole ModRM byte (0xc0-0xff) as opcode2. Forms taking
+//     an st(i) operand are listed once with the RM field cleared.
+pub fn lookup_fpu(opcode1: u8, modrm: u8) -> Result<&'static InstrFmt, Error> {
+  if modrm < 0xc0 {
+    return lookup(opcode1, Some((modrm>>3)&7));
+  }
+
+  if let Ok(fmt) = lookup(opcode1, Some(modrm)) {
+    return Ok(fmt);
+  }
+
+  let fmt = lookup(opcode1, Some(modrm & 0xf8))?;
+  if !fmt.oper.contains(&Oper::OPER_STI) {
+    return Err(Error::NotFound);
+  }
+  Ok(fmt)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  // Expected mnemonic for every opcode1 slot: 'grp' needs a ModRM reg-field,
+  // '0f' a second opcode byte, 'fpu' an FPU escape and '-' is invalid / a prefix
+  const OPCODE1_MAP: [[&str; 16]; 16] = [
+    ["add",   "add",   "add",   "add",   "add",   "add",   "push",  "pop",   "or",    "or",    "or",    "or",    "or",    "or",    "push",  "0f"],
+    ["adc",   "adc",   "adc",   "adc",   "adc",   "adc",   "push",  "pop",   "sbb",   "sbb",   "sbb",   "sbb",   "sbb",   "sbb",   "push",  "pop"],
+    ["and",   "and",   "and",   "and",   "and",   "and",   "-",     "daa",   "sub",   "sub",   "sub",   "sub",   "sub",   "sub",   "-",     "das"],
+    ["xor",   "xor",   "xor",   "xor",   "xor",   "xor",   "-",     "aaa",   "cmp",   "cmp",   "cmp",   "cmp",   "cmp",   "cmp",   "-",     "aas"],
+    ["inc",   "inc",   "inc",   "inc",   "inc",   "inc",   "inc",   "inc",   "dec",   "dec",   "dec",   "dec",   "dec",   "dec",   "dec",   "dec"],
+    ["push",  "push",  "push",  "push",  "push",  "push",  "push",  "push",  "pop",   "pop",   "pop",   "pop",   "pop",   "pop",   "pop",   "pop"],
+    ["pusha", "popa",  "bound", "-",     "-",     "-",     "-",     "-",     "push",  "imul",  "push",  "imul",  "ins",   "ins",   "outs",  "outs"],
+    ["jo",    "jno",   "jb",    "jae",   "je",    "jne",   "jbe",   "ja",    "js",    "jns",   "jp",    "jnp",   "jl",    "jge",   "jle",   "jg"],
+    ["grp",   "grp",   "grp",   "grp",   "test",  "test",  "xchg",  "xchg",  "mov",   "mov",   "mov",   "mov",   "mov",   "lea",   "mov",   "pop"],
+    ["nop",   "xchg",  "xchg",  "xchg",  "xchg",  "xchg",  "xchg",  "xchg",  "cbw",   "cwd",   "callf", "fwait", "pushf", "popf",  "sahf",  "lahf"],
+    ["mov",   "mov",   "mov",   "mov",   "movs",  "movs",  "cmps",  "cmps",  "test",  "test",  "stos",  "stos",  "lods",  "lods",  "scas",  "scas"],
+    ["mov",   "mov",   "mov",   "mov",   "mov",   "mov",   "mov",   "mov",   "mov",   "mov",   "mov",   "mov",   "mov",   "mov",   "mov",   "mov"],
+    ["grp",   "grp",   "ret",   "ret",   "les",   "lds",   "grp",   "grp",   "enter", "leave", "retf",  "retf",  "int",   "int",   "into",  "iret"],
+    ["grp",   "grp",   "grp",   "grp",   "aam",   "aad",   "-",     "xlat",  "fpu",   "fpu",   "fpu",   "fpu",   "fpu",   "fpu",   "fpu",   "fpu"],
+    ["loopne","loope", "loop",  "jcxz",  "in",    "in",    "out",   "out",   "call",  "jmp",   "jmpf",  "jmp",   "in",    "in",    "out",   "out"],
+    ["-",     "-",     "-",     "-",     "hlt",   "cmc",   "grp",   "grp",   "clc",   "stc",   "cli",   "sti",   "cld",   "std",   "grp",   "grp"],
+  ];
+
+  const GROUP_MAP: &[(u8, [&str; 8])] = &[
+    (0x80, ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"]),
+    (0x81, ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"]),
+    (0x82, ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"]),
+    (0x83, ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"]),
+    (0xc0, ["rol", "ror", "rcl", "rcr", "shl", "shr", "shl", "sar"]),
+    (0xc1, ["rol", "ror", "rcl", "rcr", "shl", "shr", "shl", "sar"]),
+    (0xc6, ["mov", "-", "-", "-", "-", "-", "-", "-"]),
+    (0xc7, ["mov", "-", "-", "-", "-", "-", "-", "-"]),
+    (0xd0, ["rol", "ror", "rcl", "rcr", "shl", "shr", "shl", "sar"]),
+    (0xd1, ["rol", "ror", "rcl", "rcr", "shl", "shr", "shl", "sar"]),
+    (0xd2, ["rol", "ror", "rcl", "rcr", "shl", "shr", "shl", "sar"]),
+    (0xd3, ["rol", "ror", "rcl", "rcr", "shl", "shr", "shl", "sar"]),
+    (0xf6, ["test", "test", "not", "neg", "mul", "imul", "div", "idiv"]),
+    (0xf7, ["test", "test", "not", "neg", "mul", "imul", "div", "idiv"]),
+    (0xfe, ["inc", "dec", "-", "-", "-", "-", "-", "-"]),
+    (0xff, ["inc", "dec", "call", "callf", "jmp", "jmpf", "push", "-"]),
+  ];
+
+  const OPCODE2_0F_MAP: &[(u8, &str)] = &[
+    (0x06, "clts"), (0x90, "seto"), (0x91, "setno"), (0x92, "setb"), (0x93, "setae"), (0x94, "sete"),
+    (0x95, "setne"), (0x96, "setbe"), (0x97, "seta"), (0x98, "sets"), (0x99, "setns"), (0x9a, "setp"),
+    (0x9b, "setnp"), (0x9c, "setl"), (0x9d, "setge"), (0x9e, "setle"), (0x9f, "setg"),
+  ];
+
+  #[test]
+  fn test_opcode1_coverage() {
+    for op1 in 0..=255u8 {
+      let expect = OPCODE1_MAP[(op1>>4) as usize][(op1&15) as usize];
+      let got = match lookup(op1, None) {
+        Ok(fmt) if fmt.op == Opcode::OP_INVAL => "-",
+        Ok(fmt) => fmt.op.name(),
+        Err(Error::NeedOpcode2) => "grp",
+        Err(Error::NeedOpcode2Ext0F) => "0f",
+        Err(Error::NeedOpcode2Fpu) => "fpu",
+        Err(err) => panic!("Unexpected error for opcode1 0x{:02x}: {:?}", op1, err),
+      };
+      assert_eq!(got, expect, "opcode1 0x{:02x}", op1);
+    }
+  }
+
+  #[test]
+  fn test_opcode2_coverage() {
+    for (op1, names) in GROUP_MAP {
+      assert_eq!(OPCODE1_MAP[(op1>>4) as usize][(op1&15) as usize], "grp");
+      for op2 in 0..8u8 {
+        let got = lookup(*op1, Some(op2)).map(|fmt| fmt.op.name()).unwrap_or("-");
+        assert_eq!(got, names[op2 as usize], "opcode1 0x{:02x} /{}", op1, op2);
+      }
+    }
+    for op1 in 0..=255u8 {
+      if OPCODE1_MAP[(op1>>4) as usize][(op1&15) as usize] == "grp" {
+        assert!(GROUP_MAP.iter().any(|(x, _)| *x == op1), "missing group table for 0x{:02x}", op1);
+      }
+    }
+  }
+
+  #[test]
+  fn test_0f_coverage() {
+    for op2 in 0..=255u8 {
+      let expect = OPCODE2_0F_MAP.iter().find(|(x, _)| *x == op2).map(|(_, name)| *name);
+      match lookup(0x0f, Some(op2)) {
+        Ok(fmt) => assert_eq!(Some(fmt.op.name()), expect, "0f 0x{:02x}", op2),
+        Err(Error::NeedOpcode3Ext0F) => assert_eq!(op2, 0x01),
+        Err(_) => assert_eq!(None, expect, "0f 0x{:02x}", op2),
+      }
+    }
+
+    let grp = ["sgdt", "sidt", "lgdt", "lidt", "smsw", "-", "lmsw", "-"];
+    for reg in 0..8u8 {
+      let got = lookup_0f_group(0x01, reg<<3).map(|fmt| fmt.op.name()).unwrap_or("-");
+      assert_eq!(got, grp[reg as usize], "0f 01 /{}", reg);
+    }
+  }
+
   #[test]
   fn test() {
     assert_eq!(lookup(0x05, None).unwrap().op, Opcode::OP_ADD);
     assert_eq!(lookup(0x05, Some(0x05)).err().unwrap(), Error::NotFound);
     assert_eq!(lookup(0x80, None).err().unwrap(), Error::NeedOpcode2);
     assert_eq!(lookup(0x80, Some(0x01)).unwrap().op, Opcode::OP_OR);
+    assert_eq!(lookup(0xd8, None).err().unwrap(), Error::NeedOpcode2Fpu);
+  }
+
+  #[test]
+  fn test_fpu() {
+    assert_eq!(lookup_fpu(0xd8, 0x06).unwrap().op, Opcode::OP_FADD);
+    assert_eq!(lookup_fpu(0xd9, 0xe0).unwrap().op, Opcode::OP_FCHS);
+    assert_eq!(lookup_fpu(0xd9, 0xcb).unwrap().op, Opcode::OP_FXCH);
+    assert_eq!(lookup_fpu(0xdd, 0x3e).unwrap().op, Opcode::OP_FNSTSW);
+    assert_eq!(lookup_fpu(0xd9, 0x08).err().unwrap(), Error::NotFound);
+    assert_eq!(lookup_fpu(0xd9, 0xe7).err().unwrap(), Error::NotFound);
   }
 }
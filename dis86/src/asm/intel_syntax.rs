@@ -1,10 +1,22 @@
 use crate::asm::instr::*;
-use crate::segoff::SegOff;
+use crate::segoff::{Seg, Off, SegOff};
 use std::fmt::Write;
 
 type Result<T> = std::result::Result<T, std::fmt::Error>;
 
-fn format_operand(s: &mut String, ins: &Instr, oper: &Operand) -> Result<()> {
+// Indexed accesses ("[bx+disp16]") with displacements at least this large are taken to be into a
+// global array rather than a struct
+pub const INDEXED_DISP_MIN: u16 = 0x100;
+
+// Resolves addresses to names so operands can be shown symbolically
+pub trait Symbols {
+  // Name of the function at a call/jump target
+  fn code_symbol(&self, from: SegOff, to: SegOff) -> Option<String>;
+  // Name of the global at a DS-relative offset
+  fn data_symbol(&self, off: u16) -> Option<String>;
+}
+
+fn format_operand(s: &mut String, ins: &Instr, bytes: &[u8], oper: &Operand, syms: Option<&dyn Symbols>) -> Result<()> {
   match oper {
     Operand::Reg(o) => write!(s, "{}", o.0.name())?,
     Operand::Mem(o) => {
@@ -12,21 +24,34 @@ fn format_operand(s: &mut String, ins: &Instr, oper: &Operand) -> Result<()> {
         Size::Size8  => write!(s, "BYTE PTR ")?,
         Size::Size16 => write!(s, "WORD PTR ")?,
         Size::Size32 => write!(s, "DWORD PTR ")?,
+        Size::Size64 => write!(s, "QWORD PTR ")?,
+        Size::Size80 => write!(s, "TBYTE PTR ")?,
+        Size::SizeNone => (),
       };
       write!(s, "{}:", o.sreg.name())?;
 
       if o.reg1.is_none() && o.reg2.is_none() {
         if o.off.is_some() {
-          write!(s, "0x{:x}", o.off.unwrap())?;
+          let name = if o.sreg == Reg::DS { syms.and_then(|y| y.data_symbol(o.off.unwrap())) } else { None };
+          match name {
+            Some(name) => write!(s, "{}", name)?,
+            None => write!(s, "0x{:x}", o.off.unwrap())?,
+          }
         }
       } else {
         write!(s, "[")?;
         if o.reg1.is_some() { write!(s, "{}", o.reg1.unwrap().name())?; }
         if o.reg2.is_some() { write!(s, "+{}", o.reg2.unwrap().name())?; }
-        if o.off.is_some() {
-          let disp = o.off.unwrap() as i16;
-          if disp >= 0 { write!(s, "+0x{:x}", disp as u16)?; }
-          else         { write!(s, "-0x{:x}", (-disp) as u16)?; }
+        if let Some(off) = o.off {
+          // Indexing into a global, e.g. "[bx+g_table+0x4]". Only a 16-bit displacement (mod 10) that's
+          // too large for a struct field can be one.
+          let disp16 = bytes.get(ins.prefixes.len() + 1).is_some_and(|modrm| modrm >> 6 == 2);
+          let global = o.sreg == Reg::DS && disp16 && off >= INDEXED_DISP_MIN;
+          let name = if global { syms.and_then(|y| y.data_symbol(off)) } else { None };
+          let disp = off as i16;
+          if let Some(name) = name { write!(s, "+{}", name)?; }
+          else if disp >= 0 { write!(s, "+0x{:x}", disp as u16)?; }
+          else              { write!(s, "-0x{:x}", (-disp) as u16)?; }
         }
         write!(s, "]")?;
       }
@@ -34,15 +59,37 @@ fn format_operand(s: &mut String, ins: &Instr, oper: &Operand) -> Result<()> {
     Operand::Imm(o) => write!(s, "0x{:x}", o.val)?,
     Operand::Rel(o) => {
       let effective = ins.rel_addr(o);
-      write!(s, "0x{:x}", effective.off.0)?;
+      match syms.and_then(|y| y.code_symbol(ins.addr, effective)) {
+        Some(name) => write!(s, "{}", name)?,
+        None => write!(s, "0x{:x}", effective.off.0)?,
+      }
+    }
+    Operand::Far(o) => {
+      let dest = SegOff { seg: Seg::Normal(o.seg), off: Off(o.off) };
+      match syms.and_then(|y| y.code_symbol(ins.addr, dest)) {
+        Some(name) => write!(s, "{}", name)?,
+        None => write!(s, "0x{:x}:0x{:x}", o.seg, o.off)?,
+      }
     }
-    Operand::Far(o) => write!(s, "0x{:x}:0x{:x}", o.seg, o.off)?,
   };
 
   Ok(())
 }
 
-fn format_instr_impl(s: &mut String, ins: &Instr, bytes: &[u8], with_detail: bool) -> Result<()> {
+fn prefix_name(b: u8) -> &'static str {
+  match b {
+    0x26 => "es",
+    0x2e => "cs",
+    0x36 => "ss",
+    0x3e => "ds",
+    0xf0 => "lock",
+    0xf2 => "repne",
+    0xf3 => "rep",
+    _ => unreachable!(),
+  }
+}
+
+fn format_instr_impl(s: &mut String, ins: &Instr, bytes: &[u8], with_detail: bool, syms: Option<&dyn Symbols>) -> Result<()> {
   if with_detail {
     write!(s, "{}:\t", ins.addr)?;
     for b in bytes {
@@ -53,10 +100,16 @@ fn format_instr_impl(s: &mut String, ins: &Instr, bytes: &[u8], with_detail: boo
     write!(s, "{:1$}\t", "", remain)?;
   }
 
-  match ins.rep {
-    None => (),
-    Some(Rep::NE) => write!(s, "repne ")?,
-    Some(Rep::EQ) => write!(s, "rep ")?,
+  // Prefixes in their original order. A segment override only goes without saying when it's the one
+  // shown on a memory operand, anything else (overridden, or with no memory operand) is shown on its own
+  // so odd encodings aren't hidden.
+  let mem_shown = ins.operands.as_slice().iter().enumerate()
+    .any(|(i, o)| matches!(o, Operand::Mem(_)) && ((1u8<<i) & ins.intel_hidden_operand_bitmask) == 0);
+  let prefixes = ins.prefixes.as_slice();
+  for (i, b) in prefixes.iter().enumerate() {
+    let overridden = prefixes[i+1..].iter().any(|later| prefix_kind(*later) == prefix_kind(*b));
+    if prefix_kind(*b) == Some(PrefixKind::Segment) && !overridden && mem_shown { continue; }
+    write!(s, "{} ", prefix_name(*b))?;
   }
 
   write!(s, "{:<5}", ins.opcode.name())?;
@@ -68,7 +121,7 @@ fn format_instr_impl(s: &mut String, ins: &Instr, bytes: &[u8], with_detail: boo
     }
     if first { write!(s, "  ")?; }
     else { write!(s, ",")?; }
-    format_operand(s, ins, oper)?;
+    format_operand(s, ins, bytes, oper, syms)?;
     first = false;
   }
   Ok(())
@@ -89,15 +142,62 @@ fn format_data_impl(s: &mut String, addr: SegOff, bytes: &[u8], with_detail: boo
 }
 
 // FIXME: THIS IS KLUDGY
-pub fn format(addr: SegOff, ins: Option<&Instr>, bytes: &[u8], with_detail: bool) -> Result<String> {
+pub fn format(addr: SegOff, ins: Option<&Instr>, bytes: &[u8], with_detail: bool, syms: Option<&dyn Symbols>) -> Result<String> {
   let mut s = String::new();
   match ins {
-    Some(ins) => format_instr_impl(&mut s, ins, bytes, with_detail)?,
+    Some(ins) => format_instr_impl(&mut s, ins, bytes, with_detail, syms)?,
     None => format_data_impl(&mut s, addr, bytes, with_detail)?,
   }
   Ok(s.trim_end().to_string())
 }
 
 pub fn instr_str(ins: &Instr) -> String {
-  format(ins.addr, Some(ins), &[], false).unwrap()
+  format(ins.addr, Some(ins), &[], false, None).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::asm::decode::decode_one;
+  use crate::region::RegionIter;
+
+  struct TestSymbols;
+  impl Symbols for TestSymbols {
+    fn code_symbol(&self, _from: SegOff, to: SegOff) -> Option<String> {
+      (to.off.0 == 0x0056).then(|| "F_foo".to_string())
+    }
+    fn data_symbol(&self, off: u16) -> Option<String> {
+      if off < 0x10 { return Some(format!("g_low+0x{:x}", off)); }
+      (0x3a2c..0x3a34).contains(&off).then(|| format!("g_bar+0x{:x}", off - 0x3a2c))
+    }
+  }
+
+  fn format_sym(dat: &[u8]) -> String {
+    let addr = SegOff { seg: Seg::Normal(0), off: Off(0) };
+    let (ins, bytes) = decode_one(&mut RegionIter::new(dat, addr)).unwrap().unwrap();
+    format(addr, Some(&ins), bytes, false, Some(&TestSymbols)).unwrap()
+  }
+
+  #[test]
+  fn test_symbols() {
+    assert_eq!(format_sym(&[0x9a, 0x56, 0x00, 0x34, 0x12]), "callf  F_foo");
+    assert_eq!(format_sym(&[0xe8, 0x53, 0x00]),             "call   F_foo");
+    assert_eq!(format_sym(&[0xe8, 0x54, 0x00]),             "call   0x57");
+    assert_eq!(format_sym(&[0xa1, 0x30, 0x3a]),             "mov    ax,WORD PTR ds:g_bar+0x4");
+    assert_eq!(format_sym(&[0x26, 0xa1, 0x30, 0x3a]),       "mov    ax,WORD PTR es:0x3a30");
+    assert_eq!(format_sym(&[0xa1, 0x00, 0x10]),             "mov    ax,WORD PTR ds:0x1000");
+    assert_eq!(format_sym(&[0x8b, 0x87, 0x30, 0x3a]),       "mov    ax,WORD PTR ds:[bx+g_bar+0x4]");
+    assert_eq!(format_sym(&[0x8b, 0x86, 0x30, 0x3a]),       "mov    ax,WORD PTR ss:[bp+0x3a30]");
+    // Struct fields aren't globals, even when one happens to cover the displacement
+    assert_eq!(format_sym(&[0x8b, 0x44, 0x04]),             "mov    ax,WORD PTR ds:[si+0x4]");
+    assert_eq!(format_sym(&[0x8b, 0x84, 0x04, 0x00]),       "mov    ax,WORD PTR ds:[si+0x4]");
+  }
+
+  #[test]
+  fn test_prefixes() {
+    assert_eq!(format_sym(&[0x2e, 0x90]),                   "cs nop");
+    assert_eq!(format_sym(&[0x3e, 0xf0, 0x26, 0xf3, 0xa4]), "ds lock rep movs   BYTE PTR es:[di],BYTE PTR es:[si]");
+    assert_eq!(format_sym(&[0xf3, 0x26, 0xa4]),             "rep movs   BYTE PTR es:[di],BYTE PTR es:[si]");
+    assert_eq!(format_sym(&[0x26, 0xf3, 0x2e, 0x8b, 0x07]), "es rep mov    ax,WORD PTR cs:[bx]");
+  }
 }
@@ -1,4 +1,6 @@
 mod instr_fmt;
 pub mod instr;
 pub mod decode;
+pub mod encode;
 pub mod intel_syntax;
+pub mod nasm_syntax;
@@ -0,0 +1,486 @@
+use crate::asm::instr::*;
+use crate::asm::instr_fmt::{self, InstrFmt, Oper};
+use crate::asm::decode::decode_one;
+use crate::asm::encode;
+use crate::asm::intel_syntax::instr_str;
+use crate::region::RegionIter;
+use crate::segoff::SegOff;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+type Result<T> = std::result::Result<T, std::fmt::Error>;
+
+// NASM picks its own encoding for an instruction, so the text is only emitted when we can show
+// that NASM will choose the original bytes: the instruction is rendered once for every format
+// that can encode it, and NASM is modeled as choosing the shortest (first in table order) of
+// the encodings that render to identical text. Register-register forms NASM would encode with
+// the other direction bit ("mov bp,sp" as 8b ec rather than 89 e5) go through a macro that emits
+// the ModRM form explicitly. Anything else (redundant prefixes, non-minimal displacements, alias
+// opcodes, 8087 emulator interrupts, ...) is emitted as `db` instead.
+
+fn reg_name(r: Reg) -> &'static str {
+  match r {
+    Reg::ST0 => "st0",
+    Reg::ST1 => "st1",
+    Reg::ST2 => "st2",
+    Reg::ST3 => "st3",
+    Reg::ST4 => "st4",
+    Reg::ST5 => "st5",
+    Reg::ST6 => "st6",
+    Reg::ST7 => "st7",
+    _ => r.name(),
+  }
+}
+
+// Opcodes whose implied register operands are not written in NASM syntax
+fn has_implicit_operands(op: Opcode) -> bool {
+  matches!(op,
+    Opcode::OP_AAA | Opcode::OP_AAS | Opcode::OP_DAA | Opcode::OP_DAS |
+    Opcode::OP_AAM | Opcode::OP_AAD | Opcode::OP_CBW | Opcode::OP_CWD |
+    Opcode::OP_LAHF | Opcode::OP_SAHF | Opcode::OP_MUL | Opcode::OP_IMUL |
+    Opcode::OP_DIV | Opcode::OP_IDIV | Opcode::OP_ENTER | Opcode::OP_LEAVE |
+    Opcode::OP_LES | Opcode::OP_LDS | Opcode::OP_JCXZ | Opcode::OP_LOOP |
+    Opcode::OP_LOOPE | Opcode::OP_LOOPNE | Opcode::OP_XLAT)
+}
+
+fn is_string_op(op: Opcode) -> bool {
+  matches!(op,
+    Opcode::OP_MOVS | Opcode::OP_CMPS | Opcode::OP_STOS | Opcode::OP_LODS |
+    Opcode::OP_SCAS | Opcode::OP_INS | Opcode::OP_OUTS)
+}
+
+fn is_jcc(op: Opcode) -> bool {
+  matches!(op,
+    Opcode::OP_JO | Opcode::OP_JNO | Opcode::OP_JB | Opcode::OP_JAE |
+    Opcode::OP_JE | Opcode::OP_JNE | Opcode::OP_JBE | Opcode::OP_JA |
+    Opcode::OP_JS | Opcode::OP_JNS | Opcode::OP_JP | Opcode::OP_JNP |
+    Opcode::OP_JL | Opcode::OP_JGE | Opcode::OP_JLE | Opcode::OP_JG)
+}
+
+fn mnemonic(ins: &Instr, fmt: &InstrFmt) -> String {
+  if is_string_op(ins.opcode) {
+    let byte = fmt.oper.iter().any(|o| matches!(o, Oper::OPER_SRC8 | Oper::OPER_DST8));
+    return format!("{}{}", ins.opcode.name(), if byte { "b" } else { "w" });
+  }
+  match ins.opcode {
+    Opcode::OP_INT if fmt.oper[0] == Oper::OPER_LIT3 => "int3",
+    Opcode::OP_CALLF => "call",
+    Opcode::OP_JMPF => "jmp",
+    Opcode::OP_XLAT => "xlatb",
+    Opcode::OP_FNSETPM => "fsetpm",
+    op => op.name(),
+  }.to_string()
+}
+
+fn default_sreg(mem: &OperandMem) -> Reg {
+  if mem.reg1 == Some(Reg::BP) { Reg::SS } else { Reg::DS }
+}
+
+fn format_target(s: &mut String, target: SegOff, labels: &HashSet<SegOff>) -> Result<()> {
+  if labels.contains(&target) {
+    write!(s, "L_{:04x}", target.off.0)
+  } else {
+    write!(s, "0x{:x}", target.off.0)
+  }
+}
+
+// Returns false if NASM can't be made to produce the encoding
+fn format_mem(s: &mut String, mem: &OperandMem, default: Reg, size: Option<&str>) -> Result<bool> {
+  if let Some(size) = size { write!(s, "{} ", size)?; }
+  write!(s, "[")?;
+
+  // NASM drops a zero displacement unless forced to keep it
+  if mem.off == Some(0) && mem.reg1.is_some() {
+    if mem.sreg != default { return Ok(false); }
+    write!(s, "byte ")?;
+  }
+
+  if mem.sreg != default { write!(s, "{}:", mem.sreg.name())?; }
+  if mem.reg1.is_none() && mem.reg2.is_none() {
+    write!(s, "0x{:x}", mem.off.unwrap_or(0))?;
+  } else {
+    if let Some(r) = mem.reg1 { write!(s, "{}", r.name())?; }
+    if let Some(r) = mem.reg2 { write!(s, "+{}", r.name())?; }
+    if let Some(off) = mem.off {
+      let disp = off as i16;
+      if disp >= 0 { write!(s, "+0x{:x}", disp as u16)?; }
+      else         { write!(s, "-0x{:x}", (-(disp as i32)) as u16)?; }
+    }
+  }
+  write!(s, "]")?;
+  Ok(true)
+}
+
+fn mem_size(ins: &Instr, oper: Oper) -> Option<&'static str> {
+  match ins.opcode {
+    Opcode::OP_LEA | Opcode::OP_LES | Opcode::OP_LDS | Opcode::OP_BOUND => return None,
+    Opcode::OP_CALLF | Opcode::OP_JMPF => return Some("far"),
+    _ => (),
+  }
+  match oper {
+    Oper::OPER_RM8  | Oper::OPER_MOFF8  => Some("byte"),
+    Oper::OPER_RM16 | Oper::OPER_MOFF16 | Oper::OPER_M16 => Some("word"),
+    Oper::OPER_M32  => Some("dword"),
+    Oper::OPER_M64  => Some("qword"),
+    Oper::OPER_M80  => Some("tword"),
+    _               => None,
+  }
+}
+
+// Render the instruction as it would be written for a specific format (None if it can't be)
+fn render(ins: &Instr, fmt: &InstrFmt, labels: &HashSet<SegOff>) -> Result<Option<String>> {
+  if ins.fpu_emu.is_some() {
+    return Ok(None);
+  }
+
+  let mut opers: Vec<(Oper, Operand)> = fmt.oper.iter().copied().zip(ins.operands.as_slice().iter().copied()).collect();
+
+  // NASM puts the first register of a register-register xchg in the reg-field (the reverse
+  // of the decoder table), and always prefers the short form when ax is involved
+  if matches!(fmt.op1, 0x86 | 0x87) && opers.iter().all(|(_, o)| matches!(o, Operand::Reg(_))) {
+    if opers.iter().any(|(_, o)| *o == Operand::Reg(OperandReg(Reg::AX))) {
+      return Ok(None);
+    }
+    opers.reverse();
+  }
+
+  let mut s = String::new();
+
+  // NASM keeps lock and rep in the same prefix slot, so it can't emit both
+  if ins.lock {
+    if ins.rep.is_some() { return Ok(None); }
+    write!(s, "lock ")?;
+  }
+
+  match ins.rep {
+    None => (),
+    Some(Rep::NE) => write!(s, "repne ")?,
+    Some(Rep::EQ) if matches!(ins.opcode, Opcode::OP_CMPS | Opcode::OP_SCAS) => write!(s, "repe ")?,
+    Some(Rep::EQ) => write!(s, "rep ")?,
+  }
+
+  // String operands are implied, but any segment override becomes an instruction prefix
+  if is_string_op(ins.opcode) {
+    for (oper, operand) in &opers {
+      let default = match oper {
+        Oper::OPER_SRC8 | Oper::OPER_SRC16 => Reg::DS,
+        Oper::OPER_DST8 | Oper::OPER_DST16 => Reg::ES,
+        _ => continue,
+      };
+      let Operand::Mem(m) = operand else { continue };
+      if m.sreg != default {
+        write!(s, "{} ", m.sreg.name())?;
+        break;
+      }
+    }
+  }
+
+  write!(s, "{}", mnemonic(ins, fmt))?;
+
+  let mut first = true;
+  for (i, (oper, operand)) in opers.iter().enumerate() {
+    if implied_operand(ins, fmt, i, *oper) {
+      continue;
+    }
+
+    let mut t = String::new();
+    match (oper, operand) {
+      (_, Operand::Reg(r)) => write!(t, "{}", reg_name(r.0))?,
+      (Oper::OPER_MOFF8 | Oper::OPER_MOFF16, Operand::Mem(m)) => {
+        if !format_mem(&mut t, m, Reg::DS, mem_size(ins, *oper))? { return Ok(None); }
+      }
+      (_, Operand::Mem(m)) => {
+        if !format_mem(&mut t, m, default_sreg(m), mem_size(ins, *oper))? { return Ok(None); }
+      }
+      (Oper::OPER_LIT1, _) => write!(t, "1")?,
+      (Oper::OPER_IMM8_EXT, Operand::Imm(imm)) => {
+        let val = imm.val as i16;
+        if val >= 0 { write!(t, "byte 0x{:x}", val)?; }
+        else        { write!(t, "byte -0x{:x}", -val)?; }
+      }
+      (Oper::OPER_IMM16, Operand::Imm(imm)) => {
+        // Keep NASM from picking a sign-extended imm8 form
+        let has_short = instr_fmt::formats(ins.opcode).any(|f| f.oper.contains(&Oper::OPER_IMM8_EXT));
+        if has_short && imm.val == imm.val as u8 as i8 as u16 { write!(t, "strict word ")?; }
+        write!(t, "0x{:x}", imm.val)?;
+      }
+      (_, Operand::Imm(imm)) => write!(t, "0x{:x}", imm.val)?,
+      (_, Operand::Far(far)) => write!(t, "0x{:x}:0x{:x}", far.seg, far.off)?,
+      (_, Operand::Rel(rel)) => {
+        let jmp = ins.opcode == Opcode::OP_JMP;
+        if *oper == Oper::OPER_REL8 && (jmp || is_jcc(ins.opcode)) { write!(t, "short ")?; }
+        if *oper == Oper::OPER_REL16 && jmp { write!(t, "near ")?; }
+        format_target(&mut t, ins.rel_addr(rel), labels)?;
+      }
+    }
+
+    if first { write!(s, "{:1$}", "", 8usize.saturating_sub(s.len()).max(1))?; }
+    else { write!(s, ", ")?; }
+    s += &t;
+    first = false;
+  }
+
+  Ok(Some(s))
+}
+
+fn implied_operand(ins: &Instr, fmt: &InstrFmt, idx: usize, oper: Oper) -> bool {
+  if ((1u8<<idx) & fmt.hidden) != 0 {
+    return true;
+  }
+  match oper {
+    Oper::OPER_FLAGS | Oper::OPER_LIT3 => true,
+    Oper::OPER_SRC8 | Oper::OPER_SRC16 | Oper::OPER_DST8 | Oper::OPER_DST16 => true,
+    Oper::OPER_DX if matches!(ins.opcode, Opcode::OP_INS | Opcode::OP_OUTS) => true,
+    Oper::OPER_AL | Oper::OPER_AX if matches!(ins.opcode, Opcode::OP_LODS | Opcode::OP_STOS | Opcode::OP_SCAS) => true,
+    _ => has_implicit_operands(ins.opcode) && is_implied_reg(oper),
+  }
+}
+
+fn is_implied_reg(oper: Oper) -> bool {
+  matches!(oper,
+    Oper::OPER_AX | Oper::OPER_CX | Oper::OPER_DX | Oper::OPER_BX |
+    Oper::OPER_SP | Oper::OPER_BP | Oper::OPER_SI | Oper::OPER_DI |
+    Oper::OPER_AL | Oper::OPER_CL | Oper::OPER_DL | Oper::OPER_BL |
+    Oper::OPER_AH | Oper::OPER_CH | Oper::OPER_DH | Oper::OPER_BH |
+    Oper::OPER_ES | Oper::OPER_CS | Oper::OPER_SS | Oper::OPER_DS)
+}
+
+// The NASM text for an instruction, if reassembling it reproduces `raw`
+fn instr_text(ins: &Instr, raw: &[u8], labels: &HashSet<SegOff>) -> Result<Option<String>> {
+  let encodings = encode::encodings(ins);
+  let Some((fmt, _)) = encodings.iter().find(|(_, bytes)| bytes == raw) else { return Ok(None) };
+  let Some(text) = render(ins, fmt, labels)? else { return Ok(None) };
+
+  let mut chosen: Option<&Vec<u8>> = None;
+  for (other, bytes) in &encodings {
+    if render(ins, other, labels)?.as_ref() != Some(&text) { continue; }
+    if chosen.map(|c| bytes.len() < c.len()).unwrap_or(true) {
+      chosen = Some(bytes);
+    }
+  }
+
+  Ok(if chosen.map(|c| c == raw).unwrap_or(false) { Some(text) } else { None })
+}
+
+// A register-register instruction in a specific ModRM form, emitted as "<mnemonic>_<opcode> dst, src"
+#[derive(Debug, Clone)]
+struct RegRegMacro {
+  op: u8,
+  reg_first: bool, // the first operand goes in the reg field (otherwise it's the r/m)
+}
+
+// Numbers of the registers the macros take, as used in ModRM fields
+const MACRO_REGS: [(Reg, u8); 20] = [
+  (Reg::AX, 0), (Reg::CX, 1), (Reg::DX, 2), (Reg::BX, 3), (Reg::SP, 4), (Reg::BP, 5), (Reg::SI, 6), (Reg::DI, 7),
+  (Reg::AL, 0), (Reg::CL, 1), (Reg::DL, 2), (Reg::BL, 3), (Reg::AH, 4), (Reg::CH, 5), (Reg::DH, 6), (Reg::BH, 7),
+  (Reg::ES, 0), (Reg::CS, 1), (Reg::SS, 2), (Reg::DS, 3),
+];
+
+fn reg_reg_macro(ins: &Instr, raw: &[u8]) -> Option<(String, RegRegMacro)> {
+  let [op, modrm] = *raw else { return None };
+  if modrm >> 6 != 3 || ins.fpu_emu.is_some() { return None; }
+  let [Operand::Reg(_), Operand::Reg(_)] = ins.operands.as_slice() else { return None };
+  let encodings = encode::encodings(ins);
+  let (fmt, _) = encodings.iter().find(|(_, bytes)| bytes == raw)?;
+  let is_reg = |o: &Oper| matches!(o, Oper::OPER_R8 | Oper::OPER_R16 | Oper::OPER_SREG);
+  let is_rm = |o: &Oper| matches!(o, Oper::OPER_RM8 | Oper::OPER_RM16);
+  let reg_first = match &fmt.oper[..2] {
+    [a, b] if is_reg(a) && is_rm(b) => true,
+    [a, b] if is_rm(a) && is_reg(b) => false,
+    _ => return None,
+  };
+  Some((format!("{}_{:02x}", ins.opcode.name(), op), RegRegMacro { op, reg_first }))
+}
+
+fn format_macros(s: &mut String, macros: &BTreeMap<String, RegRegMacro>) -> Result<()> {
+  for (reg, num) in MACRO_REGS {
+    writeln!(s, "%define REG_{} {}", reg.name(), num)?;
+  }
+  writeln!(s)?;
+  for (name, m) in macros {
+    let (reg, rm) = if m.reg_first { (1, 2) } else { (2, 1) };
+    writeln!(s, "%macro {} 2", name)?;
+    writeln!(s, "  db 0x{:02x}, 0xc0 | (REG_%{} << 3) | REG_%{}", m.op, reg, rm)?;
+    writeln!(s, "%endmacro")?;
+  }
+  writeln!(s)
+}
+
+fn format_bytes(s: &mut String, bytes: &[u8]) -> Result<()> {
+  write!(s, "  db      ")?;
+  for (i, b) in bytes.iter().enumerate() {
+    if i != 0 { write!(s, ", ")?; }
+    write!(s, "0x{:02x}", b)?;
+  }
+  Ok(())
+}
+
+// Disassemble an entire region as a NASM source file that reassembles to the same bytes
+pub fn format(mut region: RegionIter) -> Result<String> {
+  let start = region.addr();
+
+  // Decode everything up front, any undecodable byte becomes data
+  let mut items: Vec<(SegOff, Option<Instr>, &[u8])> = vec![];
+  loop {
+    match decode_one(&mut region) {
+      Ok(None) => break,
+      Ok(Some((ins, raw))) => items.push((ins.addr, Some(ins), raw)),
+      Err(_) => {
+        let addr = region.addr();
+        items.push((addr, None, region.slice(addr, 1)));
+        region.advance();
+      }
+    }
+  }
+
+  // Label every branch target that starts an instruction or data byte
+  let starts: HashSet<SegOff> = items.iter().map(|(addr, _, _)| *addr).collect();
+  let mut labels = HashSet::new();
+  for (_, ins, _) in &items {
+    let Some(ins) = ins else { continue };
+    for oper in ins.operands.as_slice() {
+      let Operand::Rel(rel) = oper else { continue };
+      let target = ins.rel_addr(rel);
+      if starts.contains(&target) { labels.insert(target); }
+    }
+  }
+
+  let mut s = String::new();
+  let mut macros = BTreeMap::new();
+  let mut data: Vec<u8> = vec![];
+  for (addr, ins, raw) in &items {
+    if !data.is_empty() && (labels.contains(addr) || ins.is_some() || data.len() == 16) {
+      format_bytes(&mut s, &data)?;
+      writeln!(s)?;
+      data.clear();
+    }
+    if labels.contains(addr) {
+      writeln!(s, "L_{:04x}:", addr.off.0)?;
+    }
+
+    let Some(ins) = ins else {
+      data.extend_from_slice(raw);
+      continue;
+    };
+    if let Some(text) = instr_text(ins, raw, &labels)? {
+      writeln!(s, "  {}", text)?;
+    } else if let Some((name, m)) = reg_reg_macro(ins, raw) {
+      let [Operand::Reg(dst), Operand::Reg(src)] = ins.operands.as_slice() else { unreachable!() };
+      writeln!(s, "  {:7} {}, {}", name, dst.0.name(), src.0.name())?;
+      macros.insert(name, m);
+    } else {
+      format_bytes(&mut s, raw)?;
+      writeln!(s, "  ; {}", instr_str(ins))?;
+    }
+  }
+  if !data.is_empty() {
+    format_bytes(&mut s, &data)?;
+    writeln!(s)?;
+  }
+
+  let mut out = String::new();
+  writeln!(out, "bits 16")?;
+  writeln!(out, "org 0x{:x}", start.off.0)?;
+  writeln!(out)?;
+  if !macros.is_empty() {
+    format_macros(&mut out, &macros)?;
+  }
+  out += &s;
+  Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::segoff::{Seg, Off};
+
+  const DAT: &[u8] = &[
+    0x55,                         // push bp
+    0x8b, 0xec,                   // mov bp,sp
+    0x26, 0x8b, 0x47, 0x04,       // mov ax,es:[bx+0x4]
+    0x3d, 0x05, 0x00,             // cmp ax,0x5
+    0x74, 0x02,                   // je 0x10e
+    0xf3, 0xa5,                   // rep movsw
+    0x81, 0xc4, 0xfc, 0xff,       // add sp,0xfffc (imm16 form)
+    0xeb, 0xed,                   // jmp 0x101
+    0xcd, 0x3d,                   // emulated fwait
+    0x0f, 0xff,                   // undecodable
+  ];
+
+  #[test]
+  fn test_format() {
+    let expected = "\
+bits 16
+org 0x100
+
+%define REG_ax 0
+%define REG_cx 1
+%define REG_dx 2
+%define REG_bx 3
+%define REG_sp 4
+%define REG_bp 5
+%define REG_si 6
+%define REG_di 7
+%define REG_al 0
+%define REG_cl 1
+%define REG_dl 2
+%define REG_bl 3
+%define REG_ah 4
+%define REG_ch 5
+%define REG_dh 6
+%define REG_bh 7
+%define REG_es 0
+%define REG_cs 1
+%define REG_ss 2
+%define REG_ds 3
+
+%macro mov_8b 2
+  db 0x8b, 0xc0 | (REG_%1 << 3) | REG_%2
+%endmacro
+
+  push    bp
+L_0101:
+  mov_8b  bp, sp
+  mov     ax, word [es:bx+0x4]
+  cmp     ax, strict word 0x5
+  je      short L_010e
+  rep movsw
+L_010e:
+  add     sp, strict word 0xfffc
+  jmp     short L_0101
+  db      0xcd, 0x3d  ; fwait
+  db      0x0f, 0xff
+";
+    let addr = SegOff { seg: Seg::Normal(0), off: Off(0x100) };
+    let text = format(RegionIter::new(DAT, addr)).unwrap();
+    assert_eq!(text, expected);
+  }
+
+  // Run with `cargo test -- --ignored` where NASM is installed
+  #[test]
+  #[ignore = "needs nasm"]
+  fn test_nasm_reassembles() {
+    use std::process::Command;
+
+    let mut dat = DAT.to_vec();
+    dat.extend_from_slice(&[
+      0x89, 0xe5,                   // mov bp,sp (the form NASM picks)
+      0x33, 0xc0,                   // xor ax,ax (not 31 c0)
+      0x8a, 0xe0,                   // mov ah,al (not 88 c4)
+      0x87, 0xc1,                   // xchg ax,cx (not 91)
+    ]);
+    let addr = SegOff { seg: Seg::Normal(0), off: Off(0x100) };
+    let text = format(RegionIter::new(&dat, addr)).unwrap();
+
+    let dir = std::env::temp_dir();
+    let src = dir.join(format!("dis86_nasm_{}.asm", std::process::id()));
+    let bin = dir.join(format!("dis86_nasm_{}.bin", std::process::id()));
+    std::fs::write(&src, &text).unwrap();
+    let out = Command::new("nasm").arg("-f").arg("bin").arg("-o").arg(&bin).arg(&src).output().expect("failed to run nasm");
+    assert!(out.status.success(), "{}\n{}", String::from_utf8_lossy(&out.stderr), text);
+    let assembled = std::fs::read(&bin).unwrap();
+    let _ = std::fs::remove_file(&src);
+    let _ = std::fs::remove_file(&bin);
+    assert_eq!(assembled, dat, "{}", text);
+  }
+}
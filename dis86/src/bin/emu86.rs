@@ -5,7 +5,7 @@ fn print_help() {
   println!("usage: {} OPTIONS", appname);
   println!("");
   println!("REQUIRED OPTIONS:");
-  println!("  --exe             path to MZ format exe on the filesystem (required)");
+  println!("  --exe             path to MZ format exe or COM file on the filesystem (required)");
 }
 
 #[derive(Debug)]
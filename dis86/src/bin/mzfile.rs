@@ -1,8 +1,12 @@
 use dis86::binfmt::mz;
+use dis86::binfmt::com::Com;
+use dis86::binfmt::unpack;
+use dis86::binfmt::tdinfo;
 use dis86::segoff::{Seg, Off, SegOff};
 use dis86::binary::Binary;
 use dis86::asm;
 use dis86::config::{self, Config};
+use dis86::analyze::signature::{self, Signature};
 use std::fs;
 use std::collections::HashMap;
 
@@ -13,10 +17,13 @@ struct Command {
 }
 
 const COMMANDS: &'static [Command] = &[
-  Command { name: "info",    func: cmd_info,    desc: "Decode the headers and print to stdout" },
+  Command { name: "info",    func: cmd_info,    desc: "Decode the headers (or COM file layout) and print to stdout" },
   Command { name: "extract", func: cmd_extract, desc: "Extract the main exe region and all overlay regions" },
+  Command { name: "unpack",  func: cmd_unpack,  desc: "Decompress an EXEPACK, LZEXE or PKLITE packed exe" },
+  Command { name: "tdinfo",  func: cmd_tdinfo,  desc: "Print the functions, locals and line numbers from Turbo Debugger info (exe or .TDS)" },
   Command { name: "map",     func: cmd_map,     desc: "Map addresses to destinations (useful for overlay stubs)" },
-  Command { name: "dis",     func: cmd_dis,     desc: "Disassemble entire file (in so far as practical)" },
+  Command { name: "dis",     func: cmd_dis,     desc: "Disassemble entire file, exe or COM (in so far as practical)" },
+  Command { name: "sigs",    func: cmd_sigs,    desc: "Build library function signatures from the annotated functions in a config" },
 ];
 
 fn cmd_info(args: &[String]) {
@@ -30,9 +37,22 @@ fn cmd_info(args: &[String]) {
     panic!("Failed to read file: {}", path);
   };
 
+  if !is_exe(&data) {
+    Com::decode(&data).unwrap().print();
+    return;
+  }
+
   let exe = mz::Exe::decode(&data).unwrap();
   //println!("{:#?}", exe);
   exe.print();
+  if let Some(packer) = unpack::detect(&exe) {
+    println!("Packed with {}: use 'unpack' to decompress", packer);
+  }
+}
+
+// Like DOS, go by the magic rather than the extension
+fn is_exe(data: &[u8]) -> bool {
+  data.starts_with(b"MZ") || data.starts_with(b"ZM")
 }
 
 fn cmd_extract(args: &[String]) {
@@ -64,6 +84,36 @@ fn cmd_extract(args: &[String]) {
   }
 }
 
+fn cmd_unpack(args: &[String]) {
+  if args.len() != 4 {
+    eprintln!("usage: {} unpack <in> <out>", args[0]);
+    std::process::exit(1);
+  }
+  let path = &args[2];
+  let out = &args[3];
+
+  let Ok(data) = fs::read(path) else {
+    panic!("Failed to read file: {}", path);
+  };
+  let exe = mz::Exe::decode(&data).unwrap();
+
+  let Some(packer) = unpack::detect(&exe) else {
+    eprintln!("Error: {} isn't packed with anything we recognize", path);
+    std::process::exit(1);
+  };
+  match unpack::unpack(&exe) {
+    Ok(Some(unpacked)) => {
+      fs::write(out, &unpacked.rawdata).unwrap();
+      println!("Unpacked {} ({}): {} relocations, {} byte image", path, packer, unpacked.relocs.len(), unpacked.exe_data().len());
+    }
+    Ok(None) => unreachable!(),
+    Err(err) => {
+      eprintln!("Error: {}", err);
+      std::process::exit(1);
+    }
+  }
+}
+
 fn find_segment_info<'a>(exe: &'a mz::Exe, addr: SegOff) -> &'a mz::SegInfo {
   let mut found = None;
   let Some(seginfo) = exe.seginfo.as_ref() else {
@@ -93,6 +143,30 @@ fn find_stub_info<'a>(exe: &'a mz::Exe, addr: SegOff) -> &'a mz::OverlayStub {
   panic!("Failed to find overlay stub entry for {}", addr);
 }
 
+fn cmd_tdinfo(args: &[String]) {
+  if args.len() != 3 {
+    eprintln!("usage: {} tdinfo <exe-or-tds-path>", args[0]);
+    std::process::exit(1);
+  }
+  let path = &args[2];
+
+  let Ok(data) = fs::read(path) else {
+    panic!("Failed to read file: {}", path);
+  };
+
+  let info = if is_exe(&data) {
+    let exe = mz::Exe::decode(&data).unwrap();
+    let Some(info) = tdinfo::find(&exe) else {
+      eprintln!("No Turbo Debugger info found in {}", path);
+      std::process::exit(1);
+    };
+    info
+  } else {
+    tdinfo::decode(&data).unwrap()
+  };
+  info.print();
+}
+
 fn cmd_map(args: &[String]) {
   if args.len() != 4 {
     eprintln!("usage: {} <exe-path> <seg:off>", args[0]);
@@ -130,6 +204,32 @@ fn cmd_map(args: &[String]) {
   }
 }
 
+fn cmd_sigs(args: &[String]) {
+  if args.len() < 4 {
+    eprintln!("usage: {} sigs <path> <config> [<name-prefix>]", args[0]);
+    std::process::exit(1);
+  }
+  let path = &args[2];
+  let cfg = Config::from_path(&args[3]).unwrap();
+  let prefix = args.get(4).map(|s| s.as_str()).unwrap_or("");
+
+  let Ok(data) = std::fs::read(path) else {
+    panic!("Failed to read file: {}", path);
+  };
+  let exe = mz::Exe::decode(&data).unwrap();
+  let binary = Binary::from_exe(&exe, Some(&cfg));
+  let relocs = signature::reloc_addrs(&binary);
+
+  println!("# Signatures built from {} with {}", path, args[3]);
+  for func in &cfg.funcs {
+    if !func.name.starts_with(prefix) { continue; }
+    match Signature::build(func, &binary, &relocs) {
+      Ok(sig) => println!("{}", sig),
+      Err(err) => eprintln!("Skipping {}", err),
+    }
+  }
+}
+
 fn cmd_dis(args: &[String]) {
   if args.len() < 3 {
     eprintln!("usage: {} dis <path> [<config>]", args[0]);
@@ -146,6 +246,13 @@ fn cmd_dis(args: &[String]) {
     panic!("Failed to read file: {}", path);
   };
 
+  if !is_exe(&data) {
+    let com = Com::decode(&data).unwrap();
+    let binary = Binary::from_com(&com, cfg.as_ref());
+    disassemble_code(&binary, com.entry(), com.end(), cfg.as_ref(), None);
+    return;
+  }
+
   let exe = mz::Exe::decode(&data).unwrap();
 
   let Some(seginfo) = exe.seginfo.as_ref() else {
@@ -256,14 +363,13 @@ fn disassemble_code(binary: &Binary, start: SegOff, end: SegOff, cfg: Option<&Co
       }
     }
 
-    print!("{}", &asm::intel_syntax::format(addr, instr.as_ref(), raw, true).unwrap());
+    // Call targets are symbolized by the formatter, flag the ones it couldn't resolve
+    print!("{}", &asm::intel_syntax::format(addr, instr.as_ref(), raw, true, Some(binary)).unwrap());
 
     if instr_is_callf(&instr) {
       if let asm::instr::Operand::Far(far) = &instr.as_ref().unwrap().operands[0] {
         let dest_addr = SegOff { seg: Seg::Normal(far.seg), off: Off(far.off) };
-        if let Some(func) = binary.lookup_call(addr, dest_addr) {
-          print!("  ; {}()", func.name);
-        } else {
+        if binary.lookup_call(addr, dest_addr).is_none() {
           print!("  ; ???");
         }
       }
@@ -272,9 +378,7 @@ fn disassemble_code(binary: &Binary, start: SegOff, end: SegOff, cfg: Option<&Co
     if instr_is_calln(&instr) {
       if let asm::instr::Operand::Rel(rel) = &instr.as_ref().unwrap().operands[0] {
         let dest_addr = instr.as_ref().unwrap().rel_addr(rel);
-        if let Some(func) = binary.lookup_call(addr, dest_addr) {
-          print!("  ; {}()", func.name);
-        } else {
+        if binary.lookup_call(addr, dest_addr).is_none() {
           print!("  ; ???");
         }
       }
@@ -282,6 +386,15 @@ fn disassemble_code(binary: &Binary, start: SegOff, end: SegOff, cfg: Option<&Co
 
     println!("");
 
+    // Microsoft LINK overlay calls: the overlay number and offset follow the "int 3Fh" inline
+    let is_int = instr.as_ref().is_some_and(|ins| ins.opcode == asm::instr::Opcode::OP_INT);
+    if let Some(dest) = binary.ms_overlay_call(addr).filter(|_| is_int) {
+      let n = std::cmp::min(3, region.bytes_remaining());
+      let raw = region.slice(region.addr(), n as u16);
+      println!("{}  ; overlay call to {}", &asm::intel_syntax::format(region.addr(), None, raw, true, Some(binary)).unwrap(), dest);
+      region.advance_by(n);
+    }
+
     if instr_is_return(&instr) {
       println!("");
     }
@@ -297,7 +410,7 @@ fn disassemble_data(binary: &Binary, start: SegOff, end: SegOff, _cfg: Option<&C
     let addr = region.addr();
     let raw = region.slice(addr, n as u16);
     region.advance_by(n);
-    println!("{}", &asm::intel_syntax::format(addr, None, raw, true).unwrap());
+    println!("{}", &asm::intel_syntax::format(addr, None, raw, true, None).unwrap());
   }
   println!("");
 }
@@ -1,12 +1,19 @@
 use crate::segoff::{Seg, SegOff};
 use crate::region::RegionIter;
 use crate::config::{self, Config};
+use crate::asm::decode::{self, Decoder};
+use crate::asm::instr::{Instr, Opcode, Operand, OperandReg, Reg};
+use crate::asm::intel_syntax;
 use crate::binfmt;
 
+// How far into the startup code to look for DS being loaded
+const STARTUP_LOOKAHEAD: usize = 64;
+
 #[derive(Debug)]
 pub enum Fmt {
   Raw(String),
   Exe(String),
+  Com(String),
 }
 
 impl Fmt {
@@ -14,6 +21,7 @@ impl Fmt {
     match self {
       Fmt::Raw(path) => path,
       Fmt::Exe(path) => path,
+      Fmt::Com(path) => path,
     }
   }
 }
@@ -49,9 +57,19 @@ impl Binary {
         Binary::from_raw(&data, config)
       }
       Fmt::Exe(_) => {
-        let exe = binfmt::mz::Exe::decode(&data).unwrap();
+        let mut exe = binfmt::mz::Exe::decode(&data).unwrap();
+        // Compressed exes are only a decompressor stub until unpacked
+        match binfmt::unpack::unpack(&exe) {
+          Ok(Some(unpacked)) => exe = unpacked,
+          Ok(None) => (),
+          Err(err) => eprintln!("WARN: {}, using the packed exe", err),
+        }
         Self::from_exe(&exe, config)
       }
+      Fmt::Com(_) => {
+        let com = binfmt::com::Com::decode(&data)?;
+        Self::from_com(&com, config)
+      }
     };
 
     Ok(binary)
@@ -67,10 +85,18 @@ impl Binary {
     Binary { main, overlays, config: config.cloned(), segmap, exe: Some(exe.clone()) }
   }
 
+  pub fn from_com(com: &binfmt::com::Com, config: Option<&Config>) -> Self {
+    Binary { main: Data(com.image()), overlays: vec![], config: config.cloned(), segmap: None, exe: None }
+  }
+
   pub fn from_raw(data: &[u8], config: Option<&Config>) -> Binary {
     Binary { main: Data(data.to_vec()), overlays: vec![], config: config.cloned(), segmap: None, exe: None }
   }
 
+  pub fn set_config(&mut self, config: Option<&Config>) {
+    self.config = config.cloned();
+  }
+
   pub fn region(&self, start: SegOff, end: SegOff) -> &[u8] {
     assert!(start.seg == end.seg);
     match start.seg {
@@ -79,16 +105,52 @@ impl Binary {
     }
   }
 
+  // Like region(), but None past the end of the data
+  pub fn try_region(&self, start: SegOff, end: SegOff) -> Option<&[u8]> {
+    if start.seg != end.seg { return None; }
+    match start.seg {
+      Seg::Normal(_) => self.main.0.get(start.abs_normal() .. end.abs_normal()),
+      Seg::Overlay(seg) => self.overlays.get(seg as usize)?.0.get(start.off.0 as usize .. end.off.0 as usize),
+    }
+  }
+
   pub fn region_iter(&self, start: SegOff, end: SegOff) -> RegionIter<'_> {
     RegionIter::new(self.region(start, end), start)
   }
 
-  pub fn remap_to_segment(&self, old: u16) -> Seg {
+  // Segments in overlays are seginfo table offsets, None if this one doesn't name an entry
+  pub fn remap_to_segment(&self, old: u16) -> Option<Seg> {
     let Some(segmap) = self.segmap.as_ref() else {
-      panic!("Cannot remap segments when binary has no seginfo table");
+      // Microsoft LINK overlays address the root segments directly
+      return self.ms_overlays().then_some(Seg::Normal(old));
     };
-    assert!(old%8 == 0);
-    Seg::Normal(segmap[(old/8) as usize])
+    if old%8 != 0 { return None; }
+    Some(Seg::Normal(*segmap.get((old/8) as usize)?))
+  }
+
+  fn ms_overlays(&self) -> bool {
+    let ovr = self.exe.as_ref().and_then(|exe| exe.ovr.as_ref());
+    ovr.map(|ovr| ovr.kind == binfmt::mz::OverlayKind::Microsoft).unwrap_or(false)
+  }
+
+  // The destination of the Microsoft LINK overlay call ("int 3Fh" with inline operands) at this address
+  pub fn ms_overlay_call(&self, addr: SegOff) -> Option<SegOff> {
+    if !self.ms_overlays() { return None; }
+    let ovr = self.exe.as_ref()?.ovr.as_ref()?;
+    ovr.stubs.iter().find(|s| s.stub_addr() == addr).map(|s| s.dest_addr())
+  }
+
+  // Decode the code in [start, end), stepping over the inline operands of Microsoft LINK overlay calls
+  pub fn decode_code(&self, start: SegOff, end: SegOff) -> Result<Vec<(Instr, &[u8])>, String> {
+    let mut region = self.region_iter(start, end);
+    let mut out = vec![];
+    while let Some((ins, raw)) = decode::decode_one(&mut region)? {
+      if ins.opcode == Opcode::OP_INT && self.ms_overlay_call(ins.addr).is_some() {
+        region.advance_by(std::cmp::min(3, region.bytes_remaining()));
+      }
+      out.push((ins, raw));
+    }
+    Ok(out)
   }
 
   pub fn lookup_call(&self, from: SegOff, to: SegOff) -> Option<&config::Func> {
@@ -97,18 +159,136 @@ impl Binary {
       Seg::Overlay(_) => {
         // We're calling from an overlay, so we need to remap the dest seg before making the call...
         let Seg::Normal(seg) = to.seg else { return None; /*panic!("Unexpected destination segment as overlay!") */ };
-        let remapped_seg = self.remap_to_segment(seg);
+        let remapped_seg = self.remap_to_segment(seg)?;
         let to_modified = SegOff { seg: remapped_seg, off: to.off };
         cfg_func(self.config.as_ref(), to_modified)
       }
     }
   }
 
+  // The segment DS points at (DGROUP), clipped to the bytes actually in the file. Borland places any
+  // far data segments before it, so with segment info it's the last DATA segment. Without, it's
+  // wherever the startup code points DS.
+  pub fn data_segment(&self) -> Option<(SegOff, SegOff)> {
+    let exe = self.exe.as_ref()?;
+    let (seg, size) = match exe.seginfo.as_ref() {
+      Some(seginfo) => {
+        let s = seginfo.iter().rev().find(|s| s.typ == binfmt::mz::SegInfoType::DATA)?;
+        (s.seg, s.maxoff as usize)
+      }
+      None => (self.startup_ds()?, 0xffff),
+    };
+    let avail = self.main.0.len().checked_sub(seg as usize * 16)?;
+    let end = std::cmp::min(size, avail) as u16;
+    Some((SegOff::new_normal(seg, 0), SegOff::new_normal(seg, end)))
+  }
+
+  // The "mov ax,DGROUP ... mov ds,ax" of the startup code, following its jumps
+  fn startup_ds(&self) -> Option<u16> {
+    let exe = self.exe.as_ref()?;
+    let mut addr = SegOff::new(exe.hdr.cs as u16, exe.hdr.ip);
+    let mut vals: [Option<u16>; 8] = [None; 8];
+    for _ in 0..STARTUP_LOOKAHEAD {
+      let avail = self.main.0.len().checked_sub(addr.abs_normal())?;
+      let bytes = self.try_region(addr, addr.add_offset(avail.min(16) as u16))?;
+      let (ins, _) = Decoder::new(RegionIter::new(bytes, addr)).try_next().ok()??;
+      addr = ins.end_addr();
+      match (ins.opcode, ins.operands.as_slice()) {
+        (Opcode::OP_MOV, [Operand::Reg(OperandReg(Reg::DS)), Operand::Reg(OperandReg(src))]) => {
+          return vals.get(*src as usize).copied().flatten();
+        }
+        (Opcode::OP_MOV, [Operand::Reg(OperandReg(dst)), Operand::Imm(imm)]) if (*dst as usize) < 8 => {
+          vals[*dst as usize] = Some(imm.val);
+        }
+        (Opcode::OP_JMP, [Operand::Rel(rel)]) => addr = ins.rel_addr(rel),
+        (Opcode::OP_JMP | Opcode::OP_JMPF | Opcode::OP_RET | Opcode::OP_RETF | Opcode::OP_IRET, _) => return None,
+        (Opcode::OP_CMP | Opcode::OP_TEST | Opcode::OP_PUSH, _) => (),
+        (_, [Operand::Reg(OperandReg(dst)), ..]) if (*dst as usize) < 8 => vals[*dst as usize] = None,
+        _ => (),
+      }
+    }
+    None
+  }
+
   pub fn exe(&self) -> Option<&binfmt::mz::Exe> {
     self.exe.as_ref()
   }
 }
 
+impl intel_syntax::Symbols for Binary {
+  fn code_symbol(&self, from: SegOff, to: SegOff) -> Option<String> {
+    Some(self.lookup_call(from, to)?.name.clone())
+  }
+
+  fn data_symbol(&self, off: u16) -> Option<String> {
+    let g = self.config.as_ref()?.global_lookup(off)?;
+    if off == g.offset {
+      Some(g.name.clone())
+    } else {
+      Some(format!("{}+0x{:x}", g.name, off - g.offset))
+    }
+  }
+}
+
 fn cfg_func(cfg: Option<&Config>, addr: SegOff) -> Option<&config::Func> {
   cfg?.func_lookup(addr)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn data_segment_from_startup() {
+    let mut dat = vec![0x4d, 0x5a, 0x40, 0x01, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0xff, 0xff];
+    dat.resize(0x20, 0);
+    dat.extend([
+      0xbf, 0x10, 0x00, // 0000: mov di,0x10
+      0xeb, 0x01,       // 0003: jmp 0x6
+      0x90,             // 0005: nop
+      0x31, 0xc0,       // 0006: xor ax,ax
+      0x8e, 0xdf,       // 0008: mov ds,di
+      0xc3,             // 000a: ret
+    ]);
+    dat.resize(0x140, 0);
+    let exe = binfmt::mz::Exe::decode(&dat).unwrap();
+    assert!(exe.seginfo.is_none());
+
+    let binary = Binary::from_exe(&exe, None);
+    assert_eq!(binary.data_segment(), Some((SegOff::new(0x10, 0), SegOff::new(0x10, 0x20))));
+  }
+
+  #[test]
+  fn remap_without_seginfo() {
+    let binary = Binary::from_raw(&[0xcb], None);
+    assert_eq!(binary.remap_to_segment(0x10), None);
+    let from = SegOff { seg: Seg::Overlay(0), off: crate::segoff::Off(0) };
+    assert!(binary.lookup_call(from, SegOff::new(0x13, 0)).is_none());
+  }
+
+  #[test]
+  fn decode_ms_overlay_calls() {
+    // A minimal MZ file: a 32 byte header (no relocs) and the load module
+    let mz = |image: &[u8], ovno: u16| {
+      let len = 32 + image.len();
+      let mut out = vec![];
+      for word in [0x5a4d, (len % 512) as u16, len.div_ceil(512) as u16, 0, 2, 0, 0xffff, 0, 0x100, 0, 0, 0, 0x1c, ovno] {
+        out.extend(u16::to_le_bytes(word));
+      }
+      out.resize(32, 0);
+      out.extend(image);
+      out
+    };
+    let mut dat = mz(&[
+      0xcd, 0x3f, 0x01, 0x00, 0x00, // 0000: int 3Fh -> overlay 1, offset 0
+      0xcb,                         // 0005: retf
+    ], 0);
+    dat.extend(mz(&[0xcb], 1));
+    let exe = binfmt::mz::Exe::decode(&dat).unwrap();
+
+    let binary = Binary::from_exe(&exe, None);
+    let decoded = binary.decode_code(SegOff::new(0, 0), SegOff::new(0, 6)).unwrap();
+    let found: Vec<_> = decoded.iter().map(|(ins, _)| (ins.addr, ins.opcode)).collect();
+    assert_eq!(found, vec![(SegOff::new(0, 0), Opcode::OP_INT), (SegOff::new(0, 5), Opcode::OP_RETF)]);
+  }
+}
@@ -0,0 +1,64 @@
+use crate::segoff::SegOff;
+
+// A COM file is loaded right after the 256-byte PSP, with CS=DS=ES=SS pointing at the PSP
+pub const LOAD_OFF: u16 = 0x100;
+
+// The image has to fit in the segment with room for the initial stack word
+pub const MAX_SIZE: usize = 0x10000 - LOAD_OFF as usize - 2;
+
+#[derive(Debug, Clone)]
+pub struct Com {
+  pub data: Vec<u8>,
+}
+
+impl Com {
+  pub fn decode(data: &[u8]) -> Result<Com, String> {
+    // DOS checks the magic, not the extension, so this would be loaded as an exe
+    if data.starts_with(b"MZ") || data.starts_with(b"ZM") {
+      return Err("File has an MZ header, it's an exe".to_string());
+    }
+    if data.len() > MAX_SIZE {
+      return Err(format!("COM file is too large: {} bytes (max: {})", data.len(), MAX_SIZE));
+    }
+    Ok(Com { data: data.to_vec() })
+  }
+
+  // Addresses are relative to the PSP segment, like MZ segments are relative to the load segment
+  pub fn entry(&self) -> SegOff {
+    SegOff::new(0, LOAD_OFF)
+  }
+
+  pub fn end(&self) -> SegOff {
+    SegOff::new(0, LOAD_OFF + self.data.len() as u16)
+  }
+
+  // The segment as loaded, with the (zeroed) PSP in front
+  pub fn image(&self) -> Vec<u8> {
+    let mut image = vec![0; LOAD_OFF as usize];
+    image.extend_from_slice(&self.data);
+    image
+  }
+
+  pub fn print(&self) {
+    println!("COM File:");
+    println!("  size      0x{:04x} ({})", self.data.len(), self.data.len());
+    println!("  entry     {}", self.entry());
+    println!("  end       {}", self.end());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn decode() {
+    let com = Com::decode(&[0xb4, 0x4c, 0xcd, 0x21]).unwrap();
+    assert_eq!(com.entry(), SegOff::new(0, 0x100));
+    assert_eq!(com.end(), SegOff::new(0, 0x104));
+    assert_eq!(&com.image()[0x100..], &[0xb4, 0x4c, 0xcd, 0x21]);
+
+    assert!(Com::decode(b"MZ\x00\x00").is_err());
+    assert!(Com::decode(&vec![0x90; MAX_SIZE + 1]).is_err());
+  }
+}
@@ -0,0 +1,204 @@
+use crate::segoff::SegOff;
+
+// Linker MAP files, as written by Borland TLINK and Microsoft LINK. Both have the same layout:
+//
+//    Start  Stop   Length Name               Class
+//    00000H 0009FH 000A0H _TEXT              CODE
+//    ...
+//   Origin   Group
+//    00A3:0   DGROUP
+//
+//     Address         Publics by Name
+//    0000:02B3       _main
+//    00A3:0094       _counter
+//    ...
+//
+// Addresses are relative to the start of the load module, like the segments of the exe.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+  pub name: String,
+  pub class: String,
+  pub start: u32, // linear, in the load module
+  pub end: u32,   // exclusive
+}
+
+impl Segment {
+  pub fn is_code(&self) -> bool {
+    self.class.to_uppercase().ends_with("CODE") && self.end > self.start
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+  pub name: String,
+  pub seg: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Public {
+  pub name: String,
+  pub addr: SegOff,
+}
+
+impl Public {
+  pub fn linear(&self) -> u32 {
+    self.addr.abs_normal() as u32
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MapFile {
+  pub segments: Vec<Segment>,
+  pub groups: Vec<Group>,
+  pub publics: Vec<Public>, // distinct, in the order listed
+  pub entry: Option<SegOff>,
+}
+
+impl MapFile {
+  pub fn group(&self, name: &str) -> Option<&Group> {
+    self.groups.iter().find(|g| g.name.eq_ignore_ascii_case(name))
+  }
+
+  pub fn segment_containing(&self, linear: u32) -> Option<&Segment> {
+    self.segments.iter().find(|s| s.start <= linear && linear < s.end)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+  Other,
+  Segments,
+  Groups,
+  Publics,
+}
+
+// "0009FH"
+fn parse_hex_h(s: &str) -> Option<u32> {
+  u32::from_str_radix(s.strip_suffix(['H', 'h'])?, 16).ok()
+}
+
+// "00A3:0094", or "00A3:0" in the group table
+fn parse_addr(s: &str) -> Option<SegOff> {
+  let (seg, off) = s.split_once(':')?;
+  Some(SegOff::new(u16::from_str_radix(seg, 16).ok()?, u16::from_str_radix(off, 16).ok()?))
+}
+
+fn parse_segment(toks: &[&str]) -> Option<Segment> {
+  // Newer Microsoft LINK versions give the start as an address and no stop
+  let (start, len, rest) = match parse_addr(toks.first()?) {
+    Some(addr) => (addr.abs_normal() as u32, parse_hex_h(toks.get(1)?)?, &toks[2..]),
+    None => (parse_hex_h(toks[0])?, parse_hex_h(toks.get(2)?)?, &toks[3..]),
+  };
+  let name = rest.first()?.to_string();
+  let class = rest.get(1).unwrap_or(&"").to_string();
+  Some(Segment { name, class, start, end: start + len })
+}
+
+fn parse_public(toks: &[&str]) -> Option<Option<Public>> {
+  let addr = parse_addr(toks.first()?)?;
+  match toks[1..] {
+    [name] | ["Idle", name] => Some(Some(Public { name: name.to_string(), addr })),
+    ["Abs" | "Imp" | "Res", _] => Some(None), // not an address in the image
+    _ => None,
+  }
+}
+
+pub fn parse(text: &str) -> Result<MapFile, String> {
+  let mut map = MapFile::default();
+  let mut section = Section::Other;
+  for line in text.lines() {
+    let toks: Vec<&str> = line.split_whitespace().collect();
+    if toks.is_empty() { continue; }
+
+    // Section headers
+    if toks.contains(&"Length") && toks.contains(&"Name") { section = Section::Segments; continue; }
+    if toks[..] == ["Origin", "Group"] { section = Section::Groups; continue; }
+    if line.contains("Publics by") { section = Section::Publics; continue; }
+    if let Some(rest) = line.trim().strip_prefix("Program entry point at") {
+      map.entry = parse_addr(rest.trim());
+      section = Section::Other;
+      continue;
+    }
+
+    // Anything else ends the table (Borland's detailed map, line numbers, trailing notes, ...)
+    match section {
+      Section::Segments => match parse_segment(&toks) {
+        Some(seg) => map.segments.push(seg),
+        None => section = Section::Other,
+      }
+      Section::Groups => match (parse_addr(toks[0]), &toks[..]) {
+        (Some(addr), [_, name]) => map.groups.push(Group { name: name.to_string(), seg: addr.seg.unwrap_normal() }),
+        _ => section = Section::Other,
+      }
+      Section::Publics => match parse_public(&toks) {
+        // Listed twice: by name and by value
+        Some(Some(p)) => if !map.publics.contains(&p) { map.publics.push(p) },
+        Some(None) => (),
+        None => section = Section::Other,
+      }
+      Section::Other => (),
+    }
+  }
+
+  if map.segments.is_empty() && map.publics.is_empty() {
+    return Err("Not a MAP file: no segments or publics found".to_string());
+  }
+  Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const BORLAND: &str = "
+ Start  Stop   Length Name               Class
+
+ 00000H 0009FH 000A0H _TEXT              CODE
+ 000A0H 00A2FH 00990H MAIN_TEXT          CODE
+ 00A30H 00E8DH 0045EH _DATA              DATA
+ 00E8EH 00E8EH 00000H _BSS               BSS
+
+Detailed map of segments
+
+ 0000:0000 009F C=CODE   S=_TEXT          G=(none)  M=c0.ASM    ACBP=28
+
+ Address         Publics by Name
+
+ 0000:0000  Abs  __AHINCR
+ 000A:0010       _main
+ 00A3:0094       _counter
+ 000A:0050  Idle _unused
+
+ Address         Publics by Value
+
+ 000A:0010       _main
+
+Program entry point at 0000:0000
+";
+
+  #[test]
+  fn borland() {
+    let map = parse(BORLAND).unwrap();
+    assert_eq!(map.segments.len(), 4);
+    assert_eq!(map.segments[1], Segment { name: "MAIN_TEXT".to_string(), class: "CODE".to_string(), start: 0xa0, end: 0xa30 });
+    assert!(map.segments[1].is_code() && !map.segments[2].is_code());
+    let publics: Vec<(&str, SegOff)> = map.publics.iter().map(|p| (p.name.as_str(), p.addr)).collect();
+    assert_eq!(publics, vec![
+      ("_main", SegOff::new(0xa, 0x10)),
+      ("_counter", SegOff::new(0xa3, 0x94)),
+      ("_unused", SegOff::new(0xa, 0x50)),
+    ]);
+    assert_eq!(map.entry, Some(SegOff::new(0, 0)));
+    assert_eq!(map.segment_containing(map.publics[1].linear()).unwrap().name, "_DATA");
+
+    let ms = "\n Start  Length     Name                   Class\n 0001:0000 00170H     _TEXT                  CODE\n\n \
+              Origin   Group\n 0017:0   DGROUP\n";
+    let map = parse(ms).unwrap();
+    assert_eq!(map.segments[0].start, 0x10);
+    assert_eq!(map.segments[0].end, 0x180);
+    assert_eq!(map.group("DGROUP"), Some(&Group { name: "DGROUP".to_string(), seg: 0x17 }));
+
+    assert!(parse("garbage").is_err());
+  }
+}
@@ -1,2 +1,6 @@
 #[allow(unused_imports)]
 pub mod mz;
+pub mod com;
+pub mod unpack;
+pub mod map;
+pub mod tdinfo;
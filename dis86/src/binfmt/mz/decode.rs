@@ -1,13 +1,12 @@
 use crate::binfmt::mz::*;
+use std::collections::BTreeSet;
 
 fn decode_exe(data: &[u8]) -> Result<Exe, String> {
   // Decode the header
   let hdr = decode_hdr(data)?;
 
   // Compute the EXE Region
-  let exe_start = hdr.cparhdr as u32 * 16;
-  let mut exe_end = hdr.cp as u32 * 512;
-  if hdr.cblp != 0 { exe_end -= 512 - hdr.cblp as u32; }
+  let (exe_start, exe_end) = image_region(hdr);
   if exe_end as usize > data.len() {
     return Err(format!("End of exe region is beyond the end of data"));
   }
@@ -31,11 +30,11 @@ fn decode_exe(data: &[u8]) -> Result<Exe, String> {
     seginfo = Some(slice.to_vec());
   }
 
-  // Optional overlay info
-  let mut ovr = None;
-  if let Some(fbov) = fbov {
-    ovr = Some(overlay::decode_overlay_info(data, exe_start, fbov, seginfo.as_ref().unwrap())?);
-  }
+  // Optional overlay info: Borland's FBOV, or else Microsoft LINK's appended images
+  let ovr = match fbov {
+    Some(fbov) => Some(overlay::decode_overlay_info(data, exe_start, fbov, seginfo.as_ref().unwrap())?),
+    None => overlay::decode_ms_overlay_info(data, exe_start, exe_end, relocs, hdr.cs as u16, hdr.ss as u16),
+  };
 
   Ok(Exe {
     hdr: hdr.clone(),
@@ -49,7 +48,44 @@ fn decode_exe(data: &[u8]) -> Result<Exe, String> {
   })
 }
 
-fn decode_hdr<'a>(data: &'a [u8]) -> Result<&'a Header, String> {
+// Without Borland's seginfo table (Microsoft C, Turbo Pascal, assembly, ...) the code segments are the
+// segment values that far calls and jumps are relocated with, plus the entry point's. Each one extends
+// to the next segment value relocated anywhere (or SS, or the end of the image), within 64K.
+// Returns (segment, size) pairs.
+pub fn code_segments_from_relocations(dat: &[u8], relocs: &[Reloc], entry_cs: u16, ss: u16) -> Vec<(u16, u32)> {
+  let mut code = BTreeSet::from([entry_cs]);
+  let mut all = BTreeSet::from([entry_cs, ss]);
+  for r in relocs {
+    let loc = (r.segment as usize) * 16 + (r.offset as usize);
+    if loc + 2 > dat.len() { continue; }
+    let val = u16::from_le_bytes([dat[loc], dat[loc+1]]);
+    all.insert(val);
+    // "call far seg:off" and "jmp far seg:off": the segment word follows the opcode and offset
+    if loc >= 3 && (dat[loc-3] == 0x9a || dat[loc-3] == 0xea) {
+      code.insert(val);
+    }
+  }
+
+  let mut out = vec![];
+  for seg in code {
+    let start = seg as usize * 16;
+    if start >= dat.len() { continue; }
+    let next = all.range(seg+1..).next().map(|s| *s as usize * 16).unwrap_or(usize::MAX);
+    let end = next.min(dat.len()).min(start + 0xffff);
+    out.push((seg, (end - start) as u32));
+  }
+  out
+}
+
+// The load module, as file offsets: from the end of the header to the end given by the page counts
+pub(super) fn image_region(hdr: &Header) -> (u32, u32) {
+  let start = hdr.cparhdr as u32 * 16;
+  let mut end = hdr.cp as u32 * 512;
+  if hdr.cblp != 0 { end -= 512 - hdr.cblp as u32; }
+  (start, end)
+}
+
+pub(super) fn decode_hdr<'a>(data: &'a [u8]) -> Result<&'a Header, String> {
   // Get the header and perform magic check
   let hdr: &Header = unsafe { util::try_struct_from_bytes(data) }?;
   let magic_expect = ['M' as u8, 'Z' as u8];
@@ -69,8 +69,15 @@ pub struct OverlayStub {
   pub dest_offset: u16,      // Destination offset into the overlay segment (wherever it ends up resident)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayKind {
+  Borland,   // FBOV: overlay data after the exe, reached through the stubs of STUB segments
+  Microsoft, // LINK: an MZ image per overlay (numbered by ovno), reached by inline "int 3Fh" calls
+}
+
 #[derive(Debug, Clone)]
 pub struct OverlayInfo {
+  pub kind: OverlayKind,
   pub file_offset: u32,
   pub segs: Vec<OverlaySeg>,
   pub stubs: Vec<OverlayStub>,
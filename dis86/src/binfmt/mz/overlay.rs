@@ -106,8 +106,109 @@ pub(super) fn decode_overlay_info(data: &[u8], exe_start: u32, fbov: &FBOV, segi
     - data.as_ptr() as usize;
 
   Ok(OverlayInfo {
+    kind: OverlayKind::Borland,
     file_offset: file_offset.try_into().unwrap(),
     segs: out_segs,
     stubs: out_stubs
   })
 }
+
+// Microsoft LINK overlays are complete MZ images appended after the main one (each starting where
+// the previous image ends, or at the next page), numbered from 1 by Header::ovno. Calls into them are
+// "int 3Fh" followed by the overlay number byte and the destination offset word, inline in the code.
+// Anything that doesn't fit that pattern just means there are no (more) overlays.
+pub(super) fn decode_ms_overlay_info(data: &[u8], exe_start: u32, exe_end: u32, relocs: &[Reloc], entry_cs: u16, ss: u16) -> Option<OverlayInfo> {
+  let mut segs = vec![];
+  let mut pos = exe_end as usize;
+  loop {
+    let found = [pos, pos.next_multiple_of(512)].into_iter().find_map(|p| {
+      let hdr = decode::decode_hdr(data.get(p..)?).ok()?;
+      (hdr.ovno as usize == segs.len() + 1).then_some((p, hdr))
+    });
+    let Some((start, hdr)) = found else { break };
+    let (img_start, img_end) = decode::image_region(hdr);
+    let (img_start, img_end) = (start + img_start as usize, start + img_end as usize);
+    if img_end > data.len() || img_end < img_start || img_end - img_start > 0xffff { break; }
+    segs.push(OverlaySeg {
+      stub_segment: 0, // no stub segments: the calls are inline
+      segment_size: (img_end - img_start) as u16,
+      data_offset: img_start as u32,
+      _unknown_1: 0,
+      _unknown_2: 0,
+    });
+    pos = img_end;
+  }
+  if segs.is_empty() { return None; }
+
+  // Inline calls are addressed in the code segment they're in. Only code segments are scanned, so
+  // data that happens to contain the pattern isn't taken for a call.
+  let image = &data[exe_start as usize..exe_end as usize];
+  let mut stubs = vec![];
+  for (base, size) in decode::code_segments_from_relocations(image, relocs, entry_cs, ss) {
+    let start = base as usize * 16;
+    let code = &image[start..start + size as usize];
+    let mut i = 0;
+    while i + 5 <= code.len() {
+      if code[i..i+2] != CODE_OVERLAY_STUB_INTERRUPT_CODE { i += 1; continue; }
+      let num = code[i+2] as usize;
+      let dest_offset = u16::from_le_bytes([code[i+3], code[i+4]]);
+      match segs.get(num.wrapping_sub(1)) {
+        Some(seg) if dest_offset < seg.segment_size => {
+          stubs.push(OverlayStub {
+            overlay_seg_num: (num - 1) as u16,
+            stub_segment: base,
+            stub_offset: i as u16,
+            dest_offset,
+          });
+          i += 5;
+        }
+        _ => i += 1,
+      }
+    }
+  }
+
+  Some(OverlayInfo { kind: OverlayKind::Microsoft, file_offset: 0, segs, stubs })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::segoff::{Off, Seg, SegOff};
+
+  // A minimal MZ file: a 32 byte header (no relocs) and the load module
+  fn mz(image: &[u8], ovno: u16) -> Vec<u8> {
+    let len = 32 + image.len();
+    let mut out = vec![];
+    for word in [0x5a4d, (len % 512) as u16, len.div_ceil(512) as u16, 0, 2, 0, 0xffff, 0, 0x100, 0, 0, 0, 0x1c, ovno] {
+      out.extend(u16::to_le_bytes(word));
+    }
+    out.resize(32, 0);
+    out.extend(image);
+    out
+  }
+
+  #[test]
+  fn ms_overlays() {
+    let mut dat = mz(&[
+      0xcd, 0x3f, 0x01, 0x04, 0x00, // 0000: int 3Fh -> overlay 1, offset 4
+      0xcd, 0x3f, 0x02, 0x00, 0x00, // 0005: no overlay 2
+      0xcd, 0x3f, 0x01, 0x40, 0x00, // 000a: past the end of overlay 1
+      0xcb,                         // 000f: retf
+      0xcd, 0x3f, 0x01, 0x00, 0x00, // 0010: not code
+    ], 0);
+    dat.extend(mz(&[0x90, 0x90, 0x90, 0x90, 0xcb, 0x90, 0x90, 0x90], 1));
+    let ovr_start = dat.len() - 8;
+    dat.extend(mz(&[0xcb], 3)); // out of sequence, so not an overlay
+    dat[0x0e..0x10].copy_from_slice(&1u16.to_le_bytes()); // SS (and so data) from 0x10
+
+    let exe = Exe::decode(&dat).unwrap();
+    let ovr = exe.ovr.as_ref().unwrap();
+    assert_eq!(ovr.kind, OverlayKind::Microsoft);
+    assert_eq!(ovr.segs.len(), 1);
+    assert_eq!(ovr.segs[0].data_offset as usize, ovr_start);
+    assert_eq!(exe.overlay_data(0)[4], 0xcb);
+    assert_eq!(ovr.stubs.len(), 1);
+    assert_eq!(ovr.stubs[0].stub_addr(), SegOff::new(0, 0));
+    assert_eq!(ovr.stubs[0].dest_addr(), SegOff { seg: Seg::Overlay(0), off: Off(4) });
+  }
+}
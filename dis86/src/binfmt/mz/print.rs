@@ -101,6 +101,7 @@ impl Exe {
 
 
   fn print_overlayinfo(ovr: &OverlayInfo) {
+    println!("Overlay Kind: {:?}", ovr.kind);
     println!("Overlay File Offset: 0x{:x}", ovr.file_offset);
     println!("");
 
@@ -0,0 +1,581 @@
+use crate::binfmt::map::{self, MapFile};
+use crate::binfmt::mz::Exe;
+use crate::config;
+use crate::segoff::SegOff;
+use crate::types::{ArraySize, Type, TypeDatabase};
+use std::collections::{HashMap, HashSet};
+
+// Turbo Debugger debug information, as appended to the exe by TLINK /v (after the image and any
+// overlays, running to the end of the file) or split off into a .TDS file by TDSTRIP -s. After the
+// header come fixed size tables in this order: symbols, modules, source files, line numbers, scopes,
+// segments and correlations. Then the type and member tables, and the name pool last. Indexes into
+// the tables (and names) count from 1, with 0 meaning none.
+//
+// A type record is its id, name and size in bytes followed by what the id needs: the target type
+// for pointers, the element type for arrays, the first member for structs, unions and enums, and
+// the return type for functions. Members are fixed size, and a struct's run until the one flagged
+// as its last. Their offsets aren't recorded: struct members follow each other unpadded.
+
+pub const MAGIC: u16 = 0x52fb;
+const HEADER_SIZE: usize = 0x30;
+
+// Type ids, of those that translate to something other than their size
+pub const TID_VOID: u8     = 0x00;
+pub const TID_SCHAR: u8    = 0x04;
+pub const TID_SINT: u8     = 0x05;
+pub const TID_SLONG: u8    = 0x06;
+pub const TID_UCHAR: u8    = 0x08;
+pub const TID_UINT: u8     = 0x09;
+pub const TID_ULONG: u8    = 0x0a;
+pub const TID_PCHAR: u8    = 0x0c;
+pub const TID_NEAR: u8     = 0x15;
+pub const TID_FAR: u8      = 0x16;
+pub const TID_SEG: u8      = 0x17;
+pub const TID_NEAR386: u8  = 0x18;
+pub const TID_FAR386: u8   = 0x19;
+pub const TID_CARRAY: u8   = 0x1a;
+pub const TID_VLARRAY: u8  = 0x1b;
+pub const TID_STRUCT: u8   = 0x1e;
+pub const TID_UNION: u8    = 0x1f;
+pub const TID_VLSTRUCT: u8 = 0x20;
+pub const TID_VLUNION: u8  = 0x21;
+pub const TID_ENUM: u8     = 0x22;
+pub const TID_FUNCTION: u8 = 0x23;
+
+const MEMBER_LAST: u8 = 0x80;
+
+#[derive(Debug, Clone, Default)]
+pub struct Header {
+  pub version: u16,
+  pub names_pool_size: u32,
+  pub names_count: u16,
+  pub types_count: u16,
+  pub members_count: u16,
+  pub symbols_count: u16,
+  pub globals_count: u16,
+  pub modules_count: u16,
+  pub locals_count: u16,
+  pub scopes_count: u16,
+  pub line_numbers_count: u16,
+  pub source_files_count: u16,
+  pub segments_count: u16,
+  pub correlations_count: u16,
+  pub extension_size: u16,
+}
+
+impl Header {
+  // Everything up to the type table
+  fn fixed_size(&self) -> usize {
+    HEADER_SIZE + self.extension_size as usize
+      + 9  * self.symbols_count as usize
+      + 16 * self.modules_count as usize
+      + 6  * self.source_files_count as usize
+      + 4  * self.line_numbers_count as usize
+      + 12 * self.scopes_count as usize
+      + 16 * self.segments_count as usize
+      + 8  * self.correlations_count as usize
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolClass {
+  Static,
+  Absolute,
+  Auto,      // BP relative
+  PascalVar,
+  Register,
+  Constant,
+  Typedef,
+  Tag,       // struct, union or enum
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+  pub index: u16, // first, counting from 1
+  pub count: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+  pub name: u16,
+  pub typ: u16,
+  pub offset: u16,
+  pub segment: u16,
+  pub class: SymbolClass,
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+  pub name: u16,
+  pub language: u8,
+  pub symbols: Span,
+  pub source_files: Span,
+  pub correlations: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+  pub name: u16,
+  pub time: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LineNumber {
+  pub line: u16,
+  pub offset: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scope {
+  pub symbols: Span,
+  pub parent: u16,
+  pub function: u16, // the symbol of the function it's in
+  pub offset: u16,
+  pub length: u16,
+}
+
+// A module's contribution to a code segment
+#[derive(Debug, Clone)]
+pub struct Segment {
+  pub module: u16,
+  pub seg: u16,
+  pub offset: u16,
+  pub size: u16,
+  pub scopes: Span,
+  pub correlations: Span,
+}
+
+// The line numbers of a source file in a segment
+#[derive(Debug, Clone)]
+pub struct Correlation {
+  pub segment: u16,
+  pub file: u16,
+  pub line_numbers: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeRecord {
+  pub id: u8,
+  pub name: u16,
+  pub size: u16,
+  pub base: u16,    // pointed to, element or return type
+  pub members: u16, // the first, of a struct, union or enum
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+  pub flags: u8,
+  pub name: u16,
+  pub typ: u16, // or the value, of an enum member
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TdInfo {
+  pub header: Header,
+  pub symbols: Vec<Symbol>,
+  pub modules: Vec<Module>,
+  pub source_files: Vec<SourceFile>,
+  pub line_numbers: Vec<LineNumber>,
+  pub scopes: Vec<Scope>,
+  pub segments: Vec<Segment>,
+  pub correlations: Vec<Correlation>,
+  pub types: Vec<TypeRecord>,
+  pub members: Vec<Member>,
+  pub names: Vec<String>,
+}
+
+struct Reader<'a> {
+  dat: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn bytes(&mut self, n: usize) -> Result<&'a [u8], String> {
+    let b = self.dat.get(self.pos..self.pos+n).ok_or_else(|| format!("Unexpected end of debug info at 0x{:x}", self.pos))?;
+    self.pos += n;
+    Ok(b)
+  }
+  fn u8(&mut self) -> Result<u8, String> { Ok(self.bytes(1)?[0]) }
+  fn u16(&mut self) -> Result<u16, String> { let b = self.bytes(2)?; Ok(u16::from_le_bytes([b[0], b[1]])) }
+  fn u32(&mut self) -> Result<u32, String> { let b = self.bytes(4)?; Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])) }
+  fn span(&mut self) -> Result<Span, String> { Ok(Span { index: self.u16()?, count: self.u16()? }) }
+
+  fn table<T>(&mut self, count: u16, f: impl Fn(&mut Self) -> Result<T, String>) -> Result<Vec<T>, String> {
+    (0..count).map(|_| f(self)).collect()
+  }
+}
+
+fn decode_header(r: &mut Reader) -> Result<Header, String> {
+  let magic = r.u16()?;
+  if magic != MAGIC {
+    return Err(format!("Debug info magic mismatch: got 0x{:04x}, expected 0x{:04x}", magic, MAGIC));
+  }
+  let mut h = Header { version: r.u16()?, names_pool_size: r.u32()?, ..Header::default() };
+  for field in [&mut h.names_count, &mut h.types_count, &mut h.members_count, &mut h.symbols_count,
+                &mut h.globals_count, &mut h.modules_count, &mut h.locals_count, &mut h.scopes_count,
+                &mut h.line_numbers_count, &mut h.source_files_count, &mut h.segments_count, &mut h.correlations_count] {
+    *field = r.u16()?;
+  }
+  r.bytes(14)?; // image size, debugger hook, program flags, string segment, data count, filler
+  h.extension_size = r.u16()?;
+  Ok(h)
+}
+
+fn decode_symbol(r: &mut Reader) -> Result<Symbol, String> {
+  let (name, typ, offset, segment) = (r.u16()?, r.u16()?, r.u16()?, r.u16()?);
+  let class = match r.u8()? & 7 {
+    0 => SymbolClass::Static,
+    1 => SymbolClass::Absolute,
+    2 => SymbolClass::Auto,
+    3 => SymbolClass::PascalVar,
+    4 => SymbolClass::Register,
+    5 => SymbolClass::Constant,
+    6 => SymbolClass::Typedef,
+    _ => SymbolClass::Tag,
+  };
+  Ok(Symbol { name, typ, offset, segment, class })
+}
+
+fn decode_type(r: &mut Reader) -> Result<TypeRecord, String> {
+  let mut t = TypeRecord { id: r.u8()?, name: r.u16()?, size: r.u16()?, base: 0, members: 0 };
+  match t.id {
+    TID_NEAR..=TID_FAR386 => { r.u8()?; t.base = r.u16()?; }
+    TID_CARRAY | TID_VLARRAY => t.base = r.u16()?,
+    TID_STRUCT..=TID_VLUNION => t.members = r.u16()?,
+    TID_ENUM => { r.bytes(4)?; t.members = r.u16()?; } // after the lower and upper bound
+    TID_FUNCTION => { t.base = r.u16()?; r.bytes(2)?; } // and the language and varargs flag
+    _ => (),
+  }
+  Ok(t)
+}
+
+// Debug info starting with its header: a .TDS file, or what follows the image of an exe
+pub fn decode(dat: &[u8]) -> Result<TdInfo, String> {
+  let mut r = Reader { dat, pos: 0 };
+  let header = decode_header(&mut r)?;
+  r.bytes(header.extension_size as usize)?;
+
+  let symbols = r.table(header.symbols_count, decode_symbol)?;
+  let modules = r.table(header.modules_count, |r| {
+    let (name, language, _model) = (r.u16()?, r.u8()?, r.u8()?);
+    Ok(Module { name, language, symbols: r.span()?, source_files: r.span()?, correlations: r.span()? })
+  })?;
+  let source_files = r.table(header.source_files_count, |r| Ok(SourceFile { name: r.u16()?, time: r.u32()? }))?;
+  let line_numbers = r.table(header.line_numbers_count, |r| Ok(LineNumber { line: r.u16()?, offset: r.u16()? }))?;
+  let scopes = r.table(header.scopes_count, |r| {
+    Ok(Scope { symbols: r.span()?, parent: r.u16()?, function: r.u16()?, offset: r.u16()?, length: r.u16()? })
+  })?;
+  let segments = r.table(header.segments_count, |r| {
+    Ok(Segment { module: r.u16()?, seg: r.u16()?, offset: r.u16()?, size: r.u16()?, scopes: r.span()?, correlations: r.span()? })
+  })?;
+  let correlations = r.table(header.correlations_count, |r| {
+    Ok(Correlation { segment: r.u16()?, file: r.u16()?, line_numbers: r.span()? })
+  })?;
+
+  let types = r.table(header.types_count, decode_type)?;
+  let members = r.table(header.members_count, |r| Ok(Member { flags: r.u8()?, name: r.u16()?, typ: r.u16()? }))?;
+
+  let pool_size = header.names_pool_size as usize;
+  let Some(pool) = dat.get(r.pos..r.pos+pool_size) else {
+    return Err(format!("Name pool of {} bytes at 0x{:x} doesn't fit in the debug info", pool_size, r.pos));
+  };
+  let mut names: Vec<String> = pool.split(|b| *b == 0).map(|s| String::from_utf8_lossy(s).into_owned()).collect();
+  names.pop(); // after the last NUL
+  if names.len() != header.names_count as usize {
+    return Err(format!("Name pool has {} names, expected {}", names.len(), header.names_count));
+  }
+
+  Ok(TdInfo { header, symbols, modules, source_files, line_numbers, scopes, segments, correlations, types, members, names })
+}
+
+// The debug info TLINK appended to the exe, if any
+pub fn find(exe: &Exe) -> Option<TdInfo> {
+  let dat = &exe.rawdata;
+  (exe.exe_end as usize..dat.len().saturating_sub(HEADER_SIZE))
+    .filter(|pos| dat[*pos..].starts_with(&MAGIC.to_le_bytes()))
+    .find_map(|pos| {
+      let dat = &dat[pos..];
+      let header = decode_header(&mut Reader { dat, pos: 0 }).ok()?;
+      if header.fixed_size() + header.names_pool_size as usize > dat.len() { return None; }
+      decode(dat).ok()
+    })
+}
+
+fn span<T>(table: &[T], span: Span) -> &[T] {
+  let start = (span.index as usize).saturating_sub(1).min(table.len());
+  let end = (start + span.count as usize).min(table.len());
+  &table[start..end]
+}
+
+fn spans(span: Span) -> std::ops::Range<usize> {
+  span.index as usize..span.index as usize + span.count as usize
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Local {
+  pub name: String,
+  pub bp_off: i16,
+  pub typ: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+  pub name: String,
+  pub addr: SegOff,
+  pub locals: Vec<Local>, // BP relative, from all its scopes
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+  pub file: String,
+  pub line: u16,
+  pub addr: SegOff,
+}
+
+// The type table in config terms: a struct for each struct or union, in the order they need defining
+pub struct Types {
+  pub db: TypeDatabase,
+  pub structs: Vec<config::Struct>,
+  types: Vec<Type>, // by index
+}
+
+impl Types {
+  pub fn get(&self, idx: u16) -> Option<&Type> {
+    self.types.get((idx as usize).wrapping_sub(1)).filter(|t| !matches!(t, Type::Void | Type::Unknown))
+  }
+}
+
+struct TypeBuilder {
+  db: TypeDatabase,
+  structs: Vec<config::Struct>,
+  done: HashMap<u16, Type>,
+  busy: HashSet<u16>,
+}
+
+fn sized(size: u16) -> Type {
+  match size {
+    0 => Type::Unknown,
+    1 => Type::U8,
+    2 => Type::U16,
+    4 => Type::U32,
+    n => Type::Array(Box::new(Type::U8), ArraySize::Known(n as usize)),
+  }
+}
+
+impl TdInfo {
+  pub fn name(&self, idx: u16) -> &str {
+    match idx {
+      0 => "",
+      _ => self.names.get(idx as usize - 1).map(|s| s.as_str()).unwrap_or(""),
+    }
+  }
+
+  fn in_code(&self, seg: u16, off: u16) -> bool {
+    self.segments.iter().any(|s| s.seg == seg && s.offset <= off && (off as u32) < s.offset as u32 + s.size as u32)
+  }
+
+  fn members_from(&self, first: u16) -> &[Member] {
+    let start = (first as usize).saturating_sub(1).min(self.members.len());
+    let rest = &self.members[start..];
+    let count = rest.iter().position(|m| m.flags & MEMBER_LAST != 0).map(|i| i + 1).unwrap_or(rest.len());
+    &rest[..count]
+  }
+
+  // Its own name, or that of its struct tag or typedef
+  fn struct_name(&self, idx: u16, t: &TypeRecord) -> String {
+    let tag = self.symbols.iter()
+      .find(|s| s.typ == idx && s.name != 0 && matches!(s.class, SymbolClass::Tag | SymbolClass::Typedef));
+    match (t.name, tag) {
+      (0, Some(tag)) => self.name(tag.name).to_string(),
+      (0, None) => format!("struct_{}", idx),
+      (name, _) => self.name(name).to_string(),
+    }
+  }
+
+  fn convert_type(&self, idx: u16, b: &mut TypeBuilder) -> Type {
+    if let Some(typ) = b.done.get(&idx) { return typ.clone(); }
+    let Some(t) = self.types.get((idx as usize).wrapping_sub(1)) else { return Type::Unknown };
+    if !b.busy.insert(idx) { return sized(t.size); } // a struct containing itself
+    let typ = match t.id {
+      TID_VOID => Type::Void,
+      TID_SCHAR => Type::I8,
+      TID_SINT => Type::I16,
+      TID_SLONG => Type::I32,
+      TID_UCHAR | TID_PCHAR => Type::U8,
+      TID_UINT => Type::U16,
+      TID_ULONG => Type::U32,
+      TID_CARRAY => {
+        let elem = self.convert_type(t.base, b);
+        match elem.size_in_bytes() {
+          Some(n) if n > 0 && (t.size as usize).is_multiple_of(n) => Type::Array(Box::new(elem), ArraySize::Known(t.size as usize / n)),
+          _ => sized(t.size),
+        }
+      }
+      TID_STRUCT | TID_UNION => self.convert_struct(idx, t, b),
+      _ => sized(t.size), // pointers too, the config has no pointer types
+    };
+    b.busy.remove(&idx);
+    b.done.insert(idx, typ.clone());
+    typ
+  }
+
+  fn convert_struct(&self, idx: u16, t: &TypeRecord, b: &mut TypeBuilder) -> Type {
+    let name = self.struct_name(idx, t);
+    if let Ok(typ) = b.db.parse_type(&name) { return typ; } // the same struct, seen from another module
+    let mut members = vec![];
+    let mut off = 0;
+    for m in self.members_from(t.members) {
+      let typ = self.convert_type(m.typ, b);
+      let size = typ.size_in_bytes().unwrap_or(0) as u16;
+      if m.name != 0 { members.push(config::StructMember { name: self.name(m.name).to_string(), typ, off }); }
+      if t.id == TID_STRUCT { off += size; }
+    }
+    let s = config::Struct { name: name.clone(), size: t.size, members };
+    b.db.append_struct(&s);
+    b.structs.push(s);
+    b.db.parse_type(&name).unwrap()
+  }
+
+  pub fn types(&self) -> Types {
+    let mut b = TypeBuilder { db: TypeDatabase::new(), structs: vec![], done: HashMap::new(), busy: HashSet::new() };
+    let types = (1..=self.types.len() as u16).map(|idx| self.convert_type(idx, &mut b)).collect();
+    Types { db: b.db, structs: b.structs, types }
+  }
+
+  // Static symbols outside of any scope, which are the globals and the functions
+  fn statics(&self) -> impl Iterator<Item=(u16, &Symbol)> {
+    let scoped: Vec<std::ops::Range<usize>> = self.scopes.iter().map(|s| spans(s.symbols)).collect();
+    self.symbols.iter().enumerate()
+      .map(|(i, s)| ((i + 1) as u16, s))
+      .filter(move |(idx, s)| s.class == SymbolClass::Static && !scoped.iter().any(|r| r.contains(&(*idx as usize))))
+  }
+
+  pub fn functions(&self) -> Vec<Function> {
+    let mut out = vec![];
+    for (idx, sym) in self.statics() {
+      if !self.in_code(sym.segment, sym.offset) { continue; }
+      let mut locals = vec![];
+      for scope in self.scopes.iter().filter(|s| s.function == idx) {
+        for l in span(&self.symbols, scope.symbols).iter().filter(|l| l.class == SymbolClass::Auto) {
+          locals.push(Local { name: self.name(l.name).to_string(), bp_off: l.offset as i16, typ: l.typ });
+        }
+      }
+      out.push(Function { name: self.name(sym.name).to_string(), addr: SegOff::new(sym.segment, sym.offset), locals });
+    }
+    out.sort_by_key(|f| f.addr);
+    out
+  }
+
+  pub fn lines(&self) -> Vec<Line> {
+    let mut out = vec![];
+    for c in &self.correlations {
+      let Some(seg) = self.segments.get((c.segment as usize).wrapping_sub(1)) else { continue };
+      let file = self.source_files.get((c.file as usize).wrapping_sub(1)).map(|f| self.name(f.name)).unwrap_or("");
+      for l in span(&self.line_numbers, c.line_numbers) {
+        out.push(Line { file: file.to_string(), line: l.line, addr: SegOff::new(seg.seg, l.offset) });
+      }
+    }
+    out
+  }
+
+  // As a linker MAP: a code segment per module contribution and a public per static symbol
+  pub fn to_map(&self) -> MapFile {
+    let mut map = MapFile::default();
+    for s in &self.segments {
+      let module = self.modules.get((s.module as usize).wrapping_sub(1)).map(|m| self.name(m.name)).unwrap_or("");
+      let start = s.seg as u32 * 16 + s.offset as u32;
+      map.segments.push(map::Segment { name: module.to_string(), class: "CODE".to_string(), start, end: start + s.size as u32 });
+    }
+    for (_, sym) in self.statics() {
+      map.publics.push(map::Public { name: self.name(sym.name).to_string(), addr: SegOff::new(sym.segment, sym.offset) });
+    }
+    map
+  }
+
+  pub fn print(&self) {
+    let types = self.types();
+    let type_name = |idx| types.get(idx).map(|t| types.db.type_name(t)).unwrap_or_else(|| "?".to_string());
+    println!("Turbo Debugger info version 0x{:04x}", self.header.version);
+    println!("Structs:");
+    for s in &types.structs {
+      println!("  {}  ({} bytes)", s.name, s.size);
+      for m in &s.members {
+        println!("      +0x{:02x} {} {}", m.off, types.db.type_name(&m.typ), m.name);
+      }
+    }
+    println!("Functions:");
+    for f in self.functions() {
+      println!("  {}  {}", f.addr, f.name);
+      for l in &f.locals {
+        println!("      [bp{:+}] {} {}", l.bp_off, type_name(l.typ), l.name);
+      }
+    }
+    println!("Line numbers:");
+    for l in self.lines() {
+      println!("  {}  {}:{}", l.addr, l.file, l.line);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn words(out: &mut Vec<u8>, words: &[u16]) {
+    for w in words { out.extend(w.to_le_bytes()); }
+  }
+
+  #[test]
+  fn decoding() {
+    let names = b"main\0count\0i\0main.c\0MAIN\0point\0tag\0xy\0";
+    let mut dat = vec![];
+    words(&mut dat, &[MAGIC, 0x0400]);
+    dat.extend((names.len() as u32).to_le_bytes());
+    // names, types, members, symbols, globals, modules, locals, scopes, lines, files, segments, correlations
+    words(&mut dat, &[8, 3, 2, 3, 2, 1, 1, 1, 2, 1, 1, 1]);
+    dat.extend([0; 14]);
+    words(&mut dat, &[0]); // no extension
+    assert_eq!(dat.len(), HEADER_SIZE);
+
+    words(&mut dat, &[1, 0, 0x10, 0x0000]); dat.push(0); // main, static
+    words(&mut dat, &[2, 2, 0x94, 0x00a3]); dat.push(0); // count, static point
+    words(&mut dat, &[3, 1, 0xfffe, 0]); dat.push(2);    // i, auto int
+    words(&mut dat, &[5]); dat.extend([0, 0]); words(&mut dat, &[1, 2, 1, 1, 1, 1]); // module MAIN
+    words(&mut dat, &[4, 0, 0]);                         // main.c
+    words(&mut dat, &[10, 0x10, 11, 0x13]);              // line numbers
+    words(&mut dat, &[3, 1, 0, 1, 0, 8]);                // scope of main, holding i
+    words(&mut dat, &[1, 0x0000, 0x10, 0x20, 1, 1, 1, 1]); // segment
+    words(&mut dat, &[1, 1, 1, 2]);                      // correlation
+    dat.push(TID_SINT); words(&mut dat, &[0, 2]);         // int
+    dat.push(TID_STRUCT); words(&mut dat, &[6, 6, 1]);   // struct point
+    dat.push(TID_CARRAY); words(&mut dat, &[0, 4, 1]);   // int[2]
+    dat.push(0); words(&mut dat, &[7, 1]);               // point.tag
+    dat.push(MEMBER_LAST); words(&mut dat, &[8, 3]);     // point.xy
+    dat.extend(names);
+    dat.extend([0xaa; 4]); // something else after the names
+
+    let info = decode(&dat).unwrap();
+    assert_eq!(info.functions(), vec![Function {
+      name: "main".to_string(), addr: SegOff::new(0, 0x10), locals: vec![Local { name: "i".to_string(), bp_off: -2, typ: 1 }],
+    }]);
+
+    let types = info.types();
+    assert_eq!(types.structs.len(), 1);
+    let members: Vec<(&str, String, u16)> = types.structs[0].members.iter().map(|m| (m.name.as_str(), m.typ.to_string(), m.off)).collect();
+    assert_eq!(members, vec![("tag", "i16".to_string(), 0), ("xy", "i16[2]".to_string(), 2)]);
+    assert_eq!(types.db.type_name(types.get(2).unwrap()), "point");
+    assert_eq!(info.lines()[1], Line { file: "main.c".to_string(), line: 11, addr: SegOff::new(0, 0x13) });
+
+    let map = info.to_map();
+    assert_eq!(map.segments[0].name, "MAIN");
+    let publics: Vec<&str> = map.publics.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(publics, vec!["main", "count"]);
+
+    // Appended to an exe
+    let mut exe = vec![0x4d, 0x5a, 0x20, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0xff, 0xff];
+    exe.resize(0x20, 0);
+    exe.extend(dat);
+    let exe = Exe::decode(&exe).unwrap();
+    assert_eq!(find(&exe).unwrap().names.len(), 8);
+  }
+}
@@ -0,0 +1,157 @@
+use super::{stub, u16_at, Packer, Unpacked};
+use crate::binfmt::mz::{Exe, Reloc};
+
+// Microsoft EXEPACK: a header at CS:0000 ending in "RB", followed by the stub (at the entry point),
+// the "Packed file is corrupt" message and the packed relocation table. The data before the header
+// decompresses backwards, in place, from its end.
+
+const SIGNATURE: &[u8] = b"RB";
+const ERROR_MSG: &[u8] = b"Packed file is corrupt";
+
+struct Header {
+  real_ip: u16,
+  real_cs: u16,
+  exepack_size: u16, // header, stub and relocation table
+  real_sp: u16,
+  real_ss: u16,
+  dest_len: u16,     // paragraphs, decompressed
+  skip_len: u16,     // paragraphs of padding between the data and the header, plus one
+}
+
+// The header is 16 bytes, or 18 with skip_len, and the stub starts right after it
+pub fn detect(exe: &Exe) -> Option<Packer> {
+  let ip = exe.hdr.ip as usize;
+  if ip != 16 && ip != 18 { return None; }
+  let stub = stub(exe).ok()?;
+  if stub.get(ip-2..ip)? != SIGNATURE { return None; }
+  Some(Packer::Exepack)
+}
+
+fn decode_header(stub: &[u8], len: usize) -> Result<Header, String> {
+  Ok(Header {
+    real_ip: u16_at(stub, 0)?,
+    real_cs: u16_at(stub, 2)?,
+    exepack_size: u16_at(stub, 6)?,
+    real_sp: u16_at(stub, 8)?,
+    real_ss: u16_at(stub, 10)?,
+    dest_len: u16_at(stub, 12)?,
+    skip_len: if len == 18 { u16_at(stub, 14)? } else { 1 },
+  })
+}
+
+fn decompress(packed: &[u8], end: usize, dest_len: usize) -> Result<Vec<u8>, String> {
+  if dest_len < packed.len() {
+    return Err("Decompressed size is smaller than the packed data".to_string());
+  }
+  let mut out = vec![0; dest_len];
+  out[..packed.len()].copy_from_slice(packed);
+
+  // Padding between the data and the header
+  let mut src = end;
+  while src > 0 && out[src-1] == 0xff { src -= 1; }
+
+  let mut dst = dest_len;
+  loop {
+    if src < 3 { return Err("Truncated command".to_string()); }
+    let cmd = out[src-1];
+    let len = u16::from_le_bytes([out[src-3], out[src-2]]) as usize;
+    src -= 3;
+    if dst < len { return Err("Output underflow".to_string()); }
+    match cmd & 0xfe {
+      0xb0 => {
+        if src < 1 { return Err("Truncated fill".to_string()); }
+        let b = out[src-1];
+        src -= 1;
+        dst -= len;
+        out[dst..dst+len].fill(b);
+      }
+      0xb2 => {
+        if src < len { return Err("Truncated copy".to_string()); }
+        src -= len;
+        dst -= len;
+        out.copy_within(src..src+len, dst);
+      }
+      _ => return Err(format!("Invalid command 0x{:02x}", cmd)),
+    }
+    if cmd & 1 != 0 { break; }
+  }
+  Ok(out)
+}
+
+// For each 0x1000 paragraph segment: a count, then that many offsets
+fn decode_relocs(dat: &[u8]) -> Result<Vec<Reloc>, String> {
+  let mut relocs = vec![];
+  let mut pos = 0;
+  for i in 0..16 {
+    let count = u16_at(dat, pos)?;
+    pos += 2;
+    for _ in 0..count {
+      relocs.push(Reloc { offset: u16_at(dat, pos)?, segment: i * 0x1000 });
+      pos += 2;
+    }
+  }
+  Ok(relocs)
+}
+
+pub(super) fn unpack(exe: &Exe) -> Result<Unpacked, String> {
+  let hdr_len = exe.hdr.ip as usize;
+  let stub = stub(exe)?;
+  let hdr = decode_header(stub, hdr_len)?;
+
+  let block = stub.get(..hdr.exepack_size as usize).ok_or("EXEPACK block is truncated")?;
+  let Some(msg) = block.windows(ERROR_MSG.len()).position(|w| w == ERROR_MSG) else {
+    return Err("Relocation table not found".to_string());
+  };
+  let relocs = decode_relocs(&block[msg + ERROR_MSG.len()..])?;
+
+  let packed = &exe.exe_data()[..exe.hdr.cs as u16 as usize * 16];
+  let end = packed.len().checked_sub((hdr.skip_len as usize).saturating_sub(1) * 16).ok_or("Invalid skip length")?;
+  let image = decompress(packed, end, hdr.dest_len as usize * 16)?;
+
+  Ok(Unpacked { image, relocs, cs: hdr.real_cs, ip: hdr.real_ip, ss: hdr.real_ss, sp: hdr.real_sp })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::binfmt::unpack::{self, build_exe};
+
+  #[test]
+  fn unpack_exepack() {
+    // Packed data: an untouched prefix, a fill of 16 zeros and a 12 byte literal run
+    let mut image = b"HEAD".to_vec();
+    image.extend([0x00, 0x10, 0x00, 0xb1]);
+    image.extend(b"0123456789ab");
+    image.extend([0x0c, 0x00, 0xb2]);
+    image.resize(0x20, 0xff);
+
+    // Header at CS:0000 (CS = 2), the stub and the relocation table
+    let mut block = vec![];
+    for f in [0x1234, 0x0000, 0x0000, 0x0000, 0x0200, 0x0001, 0x0002] {
+      block.extend(u16::to_le_bytes(f));
+    }
+    block.extend(b"RB");
+    block.extend([0xcb]);
+    block.extend(ERROR_MSG);
+    block.extend([0x01, 0x00, 0x06, 0x00]);
+    block.extend([0x00; 30]);
+    let size = block.len() as u16;
+    block[6..8].copy_from_slice(&size.to_le_bytes());
+    image.extend(block);
+
+    let packed = Unpacked { image, relocs: vec![], cs: 2, ip: 0x10, ss: 0, sp: 0 };
+    let packed = build_exe(&packed, 0, 0xffff).unwrap();
+    assert_eq!(unpack::detect(&packed), Some(Packer::Exepack));
+
+    let exe = unpack::unpack(&packed).unwrap().unwrap();
+    let (ip, cs, sp, ss) = (exe.hdr.ip, exe.hdr.cs, exe.hdr.sp, exe.hdr.ss);
+    assert_eq!((ip, cs, sp, ss), (0x1234, 0, 0x200, 1));
+    let mut expect = b"HEAD".to_vec();
+    expect.extend([0x00; 16]);
+    expect.extend(b"0123456789ab");
+    assert_eq!(exe.exe_data(), &expect[..]);
+    let relocs: Vec<(u16, u16)> = exe.relocs.iter().map(|r| (r.segment, r.offset)).collect();
+    assert_eq!(relocs, vec![(0, 6)]);
+    assert_eq!(unpack::detect(&exe), None);
+  }
+}
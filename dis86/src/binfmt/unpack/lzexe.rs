@@ -0,0 +1,202 @@
+use super::{stub, u16_at, Packer, Unpacked};
+use crate::binfmt::mz::{Exe, Reloc};
+
+// LZEXE (Fabrice Bellard): "LZ09"/"LZ91" right after the MZ header, the original registers at
+// CS:0000 and an LZ77 compressed image with the control bits interleaved as 16-bit words
+
+const RELOCS_OFF_90: usize = 0x19d;
+const RELOCS_OFF_91: usize = 0x158;
+
+pub fn detect(exe: &Exe) -> Option<Packer> {
+  match exe.rawdata.get(0x1c..0x20)? {
+    b"LZ09" => Some(Packer::Lzexe90),
+    b"LZ91" => Some(Packer::Lzexe91),
+    _ => None,
+  }
+}
+
+struct Reader<'a> {
+  dat: &'a [u8],
+  pos: usize,
+  bits: u16,
+  count: u8,
+}
+
+impl<'a> Reader<'a> {
+  fn new(dat: &'a [u8]) -> Result<Reader<'a>, String> {
+    let mut r = Reader { dat, pos: 0, bits: 0, count: 16 };
+    r.bits = r.word()?;
+    Ok(r)
+  }
+
+  fn byte(&mut self) -> Result<u8, String> {
+    let b = *self.dat.get(self.pos).ok_or("Unexpected end of compressed data")?;
+    self.pos += 1;
+    Ok(b)
+  }
+
+  fn word(&mut self) -> Result<u16, String> {
+    let w = u16_at(self.dat, self.pos)?;
+    self.pos += 2;
+    Ok(w)
+  }
+
+  // The next control word is read as soon as the last bit of the current one is used
+  fn bit(&mut self) -> Result<bool, String> {
+    let b = self.bits & 1 != 0;
+    self.count -= 1;
+    if self.count == 0 {
+      self.bits = self.word()?;
+      self.count = 16;
+    } else {
+      self.bits >>= 1;
+    }
+    Ok(b)
+  }
+}
+
+fn decompress(dat: &[u8]) -> Result<Vec<u8>, String> {
+  let mut r = Reader::new(dat)?;
+  let mut out: Vec<u8> = vec![];
+  loop {
+    if r.bit()? {
+      out.push(r.byte()?);
+      continue;
+    }
+
+    let (span, len) = if !r.bit()? {
+      let len = ((r.bit()? as usize) << 1 | r.bit()? as usize) + 2;
+      (0xff00 | r.byte()? as u16, len)
+    } else {
+      let lo = r.byte()? as u16;
+      let hi = r.byte()? as u16;
+      let span = lo | ((hi & !0x07) << 5) | 0xe000;
+      let len = match (hi & 0x07) as usize + 2 {
+        2 => match r.byte()? {
+          0 => break,    // end of data
+          1 => continue, // segment change (for the benefit of the real-mode decompressor)
+          n => n as usize + 1,
+        },
+        n => n,
+      };
+      (span, len)
+    };
+
+    // A negative distance back into the output
+    let dist = 0x10000 - span as usize;
+    if dist > out.len() { return Err("Match distance is before the start of the data".to_string()); }
+    for _ in 0..len {
+      out.push(out[out.len() - dist]);
+    }
+  }
+  Ok(out)
+}
+
+// 0.90: for each 0x1000 paragraph segment, a count and then that many offsets
+fn decode_relocs_90(dat: &[u8]) -> Result<Vec<Reloc>, String> {
+  let mut relocs = vec![];
+  let mut pos = 0;
+  for i in 0..16 {
+    let count = u16_at(dat, pos)?;
+    pos += 2;
+    for _ in 0..count {
+      relocs.push(Reloc { offset: u16_at(dat, pos)?, segment: i * 0x1000 });
+      pos += 2;
+    }
+  }
+  Ok(relocs)
+}
+
+// 0.91: deltas from the previous relocation, normalized so the offset stays below 16
+fn decode_relocs_91(dat: &[u8]) -> Result<Vec<Reloc>, String> {
+  let mut relocs = vec![];
+  let mut pos = 0;
+  let (mut seg, mut off) = (0u16, 0u16);
+  loop {
+    let mut span = *dat.get(pos).ok_or("Relocation table is truncated")? as u16;
+    pos += 1;
+    if span == 0 {
+      span = u16_at(dat, pos)?;
+      pos += 2;
+      match span {
+        0 => { seg = seg.wrapping_add(0x0fff); continue; }
+        1 => break,
+        _ => (),
+      }
+    }
+    off = off.wrapping_add(span);
+    seg = seg.wrapping_add((off & !0x0f) >> 4);
+    off &= 0x0f;
+    relocs.push(Reloc { offset: off, segment: seg });
+  }
+  Ok(relocs)
+}
+
+pub(super) fn unpack(exe: &Exe, v91: bool) -> Result<Unpacked, String> {
+  let stub = stub(exe)?;
+  let (ip, cs, sp, ss) = (u16_at(stub, 0)?, u16_at(stub, 2)?, u16_at(stub, 4)?, u16_at(stub, 6)?);
+
+  let relocs = if v91 {
+    decode_relocs_91(stub.get(RELOCS_OFF_91..).ok_or("Stub is truncated")?)?
+  } else {
+    decode_relocs_90(stub.get(RELOCS_OFF_90..).ok_or("Stub is truncated")?)?
+  };
+  let image = decompress(exe.exe_data())?;
+
+  Ok(Unpacked { image, relocs, cs, ip, ss, sp })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Control bits go into 16-bit words reserved in the byte stream, as the decompressor reads them
+  struct Writer { out: Vec<u8>, word_at: usize, count: u8 }
+
+  impl Writer {
+    fn new() -> Writer { Writer { out: vec![0, 0], word_at: 0, count: 0 } }
+
+    fn bit(&mut self, b: bool) {
+      if b { self.out[self.word_at + (self.count / 8) as usize] |= 1 << (self.count % 8); }
+      self.count += 1;
+      if self.count == 16 {
+        self.word_at = self.out.len();
+        self.out.extend([0, 0]);
+        self.count = 0;
+      }
+    }
+  }
+
+  #[test]
+  fn decompression() {
+    let mut w = Writer::new();
+    for c in b"abc" {
+      w.bit(true);
+      w.out.push(*c);
+    }
+    // Short match: 3 bytes from 3 back
+    for b in [false, false, false, true] { w.bit(b); }
+    w.out.push(0xfd);
+    // Long match: 5 bytes from 6 back
+    for b in [false, true] { w.bit(b); }
+    w.out.extend([0xfa, 0xfb]);
+    // End marker
+    for b in [false, true] { w.bit(b); }
+    w.out.extend([0x00, 0x00, 0x00]);
+
+    assert_eq!(decompress(&w.out).unwrap(), b"abcabcabcab");
+  }
+
+  #[test]
+  fn relocs() {
+    let relocs = decode_relocs_91(&[0x02, 0x00, 0x00, 0x00, 0x10, 0x00, 0x01, 0x00]).unwrap();
+    let relocs: Vec<(u16, u16)> = relocs.iter().map(|r| (r.segment, r.offset)).collect();
+    assert_eq!(relocs, vec![(0, 2), (0x1000, 2)]);
+
+    let mut dat = vec![0x01, 0x00, 0x34, 0x12, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00];
+    dat.resize(36, 0);
+    let relocs = decode_relocs_90(&dat).unwrap();
+    let relocs: Vec<(u16, u16)> = relocs.iter().map(|r| (r.segment, r.offset)).collect();
+    assert_eq!(relocs, vec![(0, 0x1234), (0x2000, 2)]);
+  }
+}
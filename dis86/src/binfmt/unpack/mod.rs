@@ -0,0 +1,96 @@
+// Compressed executables: detect the packer from its stub and rebuild the original exe
+mod exepack;
+mod lzexe;
+mod pklite;
+
+use crate::binfmt::mz::{Exe, Reloc};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Packer {
+  Exepack,
+  Lzexe90,
+  Lzexe91,
+  Pklite(u8, u8), // major, minor version
+}
+
+impl fmt::Display for Packer {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Packer::Exepack             => write!(f, "EXEPACK"),
+      Packer::Lzexe90             => write!(f, "LZEXE 0.90"),
+      Packer::Lzexe91             => write!(f, "LZEXE 0.91"),
+      Packer::Pklite(major, minor) => write!(f, "PKLITE {}.{:02}", major, minor),
+    }
+  }
+}
+
+pub fn detect(exe: &Exe) -> Option<Packer> {
+  lzexe::detect(exe).or_else(|| pklite::detect(exe)).or_else(|| exepack::detect(exe))
+}
+
+// The unpacked exe, or None if it isn't packed with anything we recognize
+pub fn unpack(exe: &Exe) -> Result<Option<Exe>, String> {
+  let Some(packer) = detect(exe) else { return Ok(None) };
+  let unpacked = match packer {
+    Packer::Exepack    => exepack::unpack(exe),
+    Packer::Lzexe90    => lzexe::unpack(exe, false),
+    Packer::Lzexe91    => lzexe::unpack(exe, true),
+    Packer::Pklite(..) => pklite::unpack(exe),
+  }.map_err(|err| format!("Failed to unpack {}: {}", packer, err))?;
+
+  // The original needs no more memory than the packed exe asked for to decompress into
+  let packed_paras = exe.exe_data().len().div_ceil(16) + exe.hdr.minalloc as usize;
+  let minalloc = packed_paras.saturating_sub(unpacked.image.len().div_ceil(16)).min(0xffff) as u16;
+  Ok(Some(build_exe(&unpacked, minalloc, exe.hdr.maxalloc)?))
+}
+
+// What each unpacker recovers
+struct Unpacked {
+  image: Vec<u8>,
+  relocs: Vec<Reloc>,
+  cs: u16,
+  ip: u16,
+  ss: u16,
+  sp: u16,
+}
+
+fn u16_at(dat: &[u8], off: usize) -> Result<u16, String> {
+  match dat.get(off..off+2) {
+    Some(b) => Ok(u16::from_le_bytes([b[0], b[1]])),
+    None => Err(format!("Unexpected end of data at 0x{:x}", off)),
+  }
+}
+
+// The decompressor stub (and whatever header the packer put there) at the packed CS:0000
+fn stub(exe: &Exe) -> Result<&[u8], String> {
+  let off = exe.hdr.cs as u16 as usize * 16;
+  exe.exe_data().get(off..).ok_or_else(|| "Entry point is past the end of the image".to_string())
+}
+
+// A fresh MZ file: header, relocation table, image
+fn build_exe(u: &Unpacked, minalloc: u16, maxalloc: u16) -> Result<Exe, String> {
+  const HDR_SIZE: usize = 0x1c;
+  let hdr_len = (HDR_SIZE + 4 * u.relocs.len()).div_ceil(16) * 16;
+  let file_len = hdr_len + u.image.len();
+  let crlc: u16 = u.relocs.len().try_into().map_err(|_| "Too many relocations".to_string())?;
+  let cp: u16 = file_len.div_ceil(512).try_into().map_err(|_| "Image is too large".to_string())?;
+
+  let fields = [
+    (file_len % 512) as u16, cp, crlc, (hdr_len / 16) as u16, minalloc, maxalloc,
+    u.ss, u.sp, 0, u.ip, u.cs, HDR_SIZE as u16, 0,
+  ];
+  let mut out = b"MZ".to_vec();
+  for f in fields {
+    out.extend(f.to_le_bytes());
+  }
+  for r in &u.relocs {
+    let (offset, segment) = (r.offset, r.segment);
+    out.extend(offset.to_le_bytes());
+    out.extend(segment.to_le_bytes());
+  }
+  out.resize(hdr_len, 0);
+  out.extend(&u.image);
+
+  Exe::decode(&out)
+}
@@ -0,0 +1,156 @@
+use super::{Packer, Unpacked};
+use crate::binfmt::mz::{Exe, Reloc};
+use crate::emu86::stub::{self, Handover};
+
+// PKLITE (PKWARE): the version after the MZ header, followed by a "PKLITE Copr." notice.
+//
+// The compressed stream uses fixed Huffman tables that differ between versions and the "extra
+// compression" option, and some versions encrypt the relocation table too. Rather than carry every
+// variant, run the stub itself in the emulator: it moves itself up, decompresses the program to the
+// load segment, relocates it and jumps to its entry point. Loading it at two different segments
+// tells the relocated words apart from the rest.
+
+const PSP_A: u16 = 0x0813;
+const PSP_B: u16 = PSP_A + 0x100;
+const MAX_STEPS: u64 = 200_000_000;
+
+// Stack windows to ignore when looking for the end of the program (below the stub's and the program's SP)
+const STACK_WINDOW: usize = 0x100;
+
+pub fn detect(exe: &Exe) -> Option<Packer> {
+  let hdr = exe.rawdata.get(0x1c..0x1e)?;
+  if !exe.rawdata.get(0x1e..)?.starts_with(b"PKLITE") { return None; }
+  Some(Packer::Pklite(hdr[1] & 0x0f, hdr[0]))
+}
+
+fn u16_at(dat: &[u8], i: usize) -> u16 {
+  u16::from_le_bytes([dat[i], dat[i+1]])
+}
+
+// Where the moved copy of the stub (and its compressed data) starts: back from its first far jump
+// target for as long as memory matches the packed image
+fn moved_stub_start(h: &Handover, packed: &[u8]) -> Result<usize, String> {
+  let sig = h.after.get(h.stub_addr..h.stub_addr+32).ok_or("The stub never moved itself")?;
+  let mut packed_at = packed.windows(sig.len()).position(|w| w == sig).ok_or("Moved stub not found in the packed image")?;
+  let mut start = h.stub_addr;
+  while start > 0 && packed_at > 0 && h.after[start-1] == packed[packed_at-1] {
+    start -= 1;
+    packed_at -= 1;
+  }
+  Ok(start)
+}
+
+pub(super) fn unpack(exe: &Exe) -> Result<Unpacked, String> {
+  let a = stub::run_until_handover(exe, PSP_A, MAX_STEPS)?;
+  let b = stub::run_until_handover(exe, PSP_B, MAX_STEPS)?;
+  if (a.cs, a.ip, a.ss, a.sp) != (b.cs, b.ip, b.ss, b.sp) {
+    return Err("The entry point depends on the load segment".to_string());
+  }
+
+  // The program is everything the stub changed below its moved copy, except on the stacks
+  let stack_top = |ss: u16, sp: u16| (ss as usize * 16 + sp as usize) & 0xfffff;
+  let stacks = [stack_top(exe.hdr.ss as u16, exe.hdr.sp), stack_top(a.ss, a.sp)];
+  let on_stack = |i: usize| stacks.iter().any(|top| *top > i && i + STACK_WINDOW >= *top);
+  let limit = moved_stub_start(&a, exe.exe_data())?;
+  let len = (0..limit).rev()
+    .find(|&i| a.after[i] != a.before[i] && !on_stack(i))
+    .map(|i| i + 1)
+    .ok_or("The stub didn't unpack anything")?;
+
+  // Relocated words moved with the load segment, everything else stayed the same
+  let delta = PSP_B - PSP_A;
+  let mut image = a.after[..len].to_vec();
+  let mut relocs = vec![];
+  let mut i = 0;
+  while i + 1 < len {
+    let (wa, wb) = (u16_at(&a.after, i), u16_at(&b.after, i));
+    if wb == wa.wrapping_add(delta) {
+      image[i..i+2].copy_from_slice(&wa.wrapping_sub(a.load_seg).to_le_bytes());
+      relocs.push(Reloc { segment: (i / 0x10000 * 0x1000) as u16, offset: (i % 0x10000) as u16 });
+      i += 2;
+    } else {
+      if a.after[i] != b.after[i] { return Err(format!("Runs differ at 0x{:x}", i)); }
+      i += 1;
+    }
+  }
+
+  Ok(Unpacked { image, relocs, cs: a.cs, ip: a.ip, ss: a.ss, sp: a.sp })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // A stand-in for the real stub with the same moves: copies itself and the payload up 0x100
+  // paragraphs, jumps there, copies the payload back down to the load segment, relocates it, sets
+  // up the program's stack and jumps to its entry point
+  const STUB: [u8; 0x3b] = [
+    0x8c, 0xc8,             // mov ax,cs
+    0x05, 0x00, 0x01,       // add ax,0x100
+    0x8e, 0xc0,             // mov es,ax
+    0x31, 0xf6,             // xor si,si
+    0x31, 0xff,             // xor di,di
+    0xb9, 0x5b, 0x00,       // mov cx,0x5b
+    0x0e, 0x1f,             // push cs; pop ds
+    0xfc,                   // cld
+    0xf3, 0xa4,             // rep movsb
+    0x06,                   // push es
+    0xb8, 0x19, 0x00,       // mov ax,0x19
+    0x50,                   // push ax
+    0xcb,                   // retf
+    0x06, 0x1f,             // 0019: push es; pop ds
+    0x8c, 0xc8,             // mov ax,cs
+    0x2d, 0x00, 0x01,       // sub ax,0x100
+    0x8e, 0xc0,             // mov es,ax
+    0xbe, 0x3b, 0x00,       // mov si,0x3b
+    0x31, 0xff,             // xor di,di
+    0xb9, 0x20, 0x00,       // mov cx,0x20
+    0xf3, 0xa4,             // rep movsb
+    0x26, 0x01, 0x06, 0x01, 0x00, // add es:[0x1],ax
+    0x8e, 0xd0,             // mov ss,ax
+    0xbc, 0x00, 0x04,       // mov sp,0x400
+    0x50,                   // push ax
+    0x31, 0xdb,             // xor bx,bx
+    0x53,                   // push bx
+    0xcb,                   // retf
+  ];
+
+  fn program() -> Vec<u8> {
+    let mut prog = vec![
+      0xb8, 0x01, 0x00, // mov ax,seg 0001
+      0x8e, 0xd8,       // mov ds,ax
+      0xb4, 0x4c,       // mov ah,0x4c
+      0xcd, 0x21,       // int 0x21
+    ];
+    prog.resize(0x1f, 0x90);
+    prog.push(0xc3);
+    prog
+  }
+
+  #[test]
+  fn unpack_by_emulation() {
+    let mut image = STUB.to_vec();
+    image.extend(program());
+
+    let mut dat = b"MZ".to_vec();
+    let hdr_paras = 4;
+    let file_len = hdr_paras * 16 + image.len();
+    let fields = [
+      (file_len % 512) as u16, file_len.div_ceil(512) as u16, 0, hdr_paras as u16, 0x100, 0xffff,
+      0x40, 0x100, 0, 0, 0, 0x1c, 0,
+    ];
+    for f in fields { dat.extend(f.to_le_bytes()); }
+    dat.extend([0x03, 0x11]); // version 1.03
+    dat.extend(b"PKLITE Copr.");
+    dat.resize(hdr_paras * 16, 0);
+    dat.extend(&image);
+    let exe = Exe::decode(&dat).unwrap();
+    assert_eq!(detect(&exe), Some(Packer::Pklite(1, 3)));
+
+    let u = unpack(&exe).unwrap();
+    assert_eq!(u.image, program());
+    assert_eq!(u.relocs.len(), 1);
+    assert_eq!((u.relocs[0].segment, u.relocs[0].offset), (0, 1));
+    assert_eq!((u.cs, u.ip, u.ss, u.sp), (0, 0, 0, 0x400));
+  }
+}
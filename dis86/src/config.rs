@@ -21,6 +21,15 @@ pub struct Func {
   pub args: Option<u16>,  // None means "unknown", Some(0) means "no args"
   pub regargs: Option<Vec<Reg>>,
   pub dont_pop_args: bool,
+  pub locals: Vec<Local>,
+}
+
+// A named stack variable: a local below BP or a param above it
+#[derive(Debug, Clone)]
+pub struct Local {
+  pub name: String,
+  pub off: i16, // from BP, after "push bp; mov bp,sp"
+  pub typ: Option<Type>,
 }
 
 #[derive(Debug, Clone)]
@@ -124,6 +133,16 @@ impl Config {
     None
   }
 
+  pub fn func_lookup_by_start(&self, addr: SegOff) -> Option<&Func> {
+    // TODO: Consider something better than linear search
+    for f in &self.funcs {
+      if addr == f.start {
+        return Some(f)
+      }
+    }
+    None
+  }
+
   pub fn indirect_lookup(&self, addr: SegOff) -> Option<&Indirect> {
     // TODO: Consider something better than linear search
     for i in &self.indirects {
@@ -155,6 +174,17 @@ impl Config {
     ret
   }
 
+  pub fn global_lookup(&self, off: u16) -> Option<&Global> {
+    // TODO: Consider something better than linear search
+    for g in &self.globals {
+      let size = g.typ.size_in_bytes().unwrap_or(1) as u32;
+      if (g.offset as u32) <= (off as u32) && (off as u32) < g.offset as u32 + size {
+        return Some(g)
+      }
+    }
+    None
+  }
+
   pub fn text_region_lookup_by_start_addr(&self, addr: SegOff) -> Option<&TextSectionRegion> {
     // TODO: Consider something better than linear search
     for r in &self.text_section {
@@ -195,8 +225,8 @@ impl Config {
 }
 
 impl Config {
-  pub fn from_path(path: &str) -> Result<Config, String> {
-    let mut cfg = Config {
+  pub fn empty() -> Config {
+    Config {
       types: Rc::new(TypeDatabase::new()), // dummy
       structs: vec![],
       code_segs: vec![],
@@ -204,7 +234,11 @@ impl Config {
       indirects: vec![],
       globals: vec![],
       text_section: vec![],
-    };
+    }
+  }
+
+  pub fn from_path(path: &str) -> Result<Config, String> {
+    let mut cfg = Config::empty();
 
     let dat = std::fs::read_to_string(path)
       .map_err(|err| format!("Failed to read file with: {}'", err))?;
@@ -298,6 +332,23 @@ impl Config {
           .map_err(|err| format!("Expected type for '{}.ret', got '{}' | {}", key, ret_str, err))?);
       }
 
+      let mut locals = vec![];
+      if let Some(node) = f.get_node("locals") {
+        for (name, val) in node.iter() {
+          let l = val.as_node()
+            .ok_or_else(|| format!("Expected local properties for '{}.locals.{}'", key, name))?;
+          let off_str = l.get_str("off")
+            .ok_or_else(|| format!("No 'off' property for '{}.locals.{}'", key, name))?;
+          let off: i16 = off_str.parse()
+            .map_err(|_| format!("Expected i16 for '{}.locals.{}.off', got '{}'", key, name, off_str))?;
+          let typ = match l.get_str("type") {
+            Some(s) => Some(types.parse_type(s)?),
+            None => None,
+          };
+          locals.push(Local { name: name.to_string(), off, typ });
+        }
+      }
+
       let regargs = match regargs {
         None => None,
         Some(s) => {
@@ -322,6 +373,7 @@ impl Config {
           args: if args >= 0 { Some(args as u16) } else { None },
           regargs,
           dont_pop_args,
+          locals,
         });
       } else {
         if mode != CallMode::Far {
@@ -3,7 +3,7 @@ use crate::decompile::sym;
 use crate::decompile::control_flow::{self, ControlFlow, Detail, ElemId};
 use crate::types::*;
 use crate::config::Config;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 const OPT_DEFINE_TEMPS_AT_USE: bool = false;
 
@@ -35,6 +35,7 @@ pub enum Expr {
   StructAccess(Box<Expr>, Box<Expr>),
   Deref(Box<Expr>),
   Cast(Type, Box<Expr>),
+  Commented(Box<Expr>, String), // followed by a /* comment */
   UnimplPhi,
   UnimplPin,
 }
@@ -196,6 +197,7 @@ pub struct Block(pub Vec<Stmt>);
 
 struct Builder<'a> {
   cfg: &'a Config,
+  strings: &'a BTreeMap<u16, String>, // string globals by DS offset, as C literals
   ir: &'a ir::IR,
   cf: &'a ControlFlow,
   n_uses: HashMap<ir::Ref, usize>,
@@ -224,10 +226,11 @@ impl Block {
 }
 
 impl<'a> Builder<'a> {
-  fn new(cfg: &'a Config, ir: &'a ir::IR, cf: &'a ControlFlow) -> Self {
+  fn new(cfg: &'a Config, strings: &'a BTreeMap<u16, String>, ir: &'a ir::IR, cf: &'a ControlFlow) -> Self {
     let n_uses = ir.compute_uses();
     Self {
       cfg,
+      strings,
       ir,
       cf,
       n_uses,
@@ -289,6 +292,7 @@ impl<'a> Builder<'a> {
       ir::Opcode::UMul => (BinaryOperator::Mul,  false),
       ir::Opcode::IDiv => (BinaryOperator::Div,  true),
       ir::Opcode::UDiv => (BinaryOperator::Div,  false),
+      ir::Opcode::IRem => (BinaryOperator::Mod,  true),
       ir::Opcode::And  => (BinaryOperator::And,  false),
       ir::Opcode::Or   => (BinaryOperator::Or,   false),
       ir::Opcode::Xor  => (BinaryOperator::Xor,  false),
@@ -432,8 +436,17 @@ impl<'a> Builder<'a> {
         let funcidx = instr.operands[0].unwrap_func();
         let funcname = self.ir.funcs[funcidx].clone();
         let mut args = vec![];
-        for a in &instr.operands[1..] {
-          args.push(self.ref_to_expr(*a, depth+1));
+        let call_args = &instr.operands[1..];
+        for (i, a) in call_args.iter().enumerate() {
+          let mut arg = self.ref_to_expr(*a, depth+1);
+          // Pointers to strings: show the text. The config doesn't type args, so only trust a constant
+          // that's the offset half of a far pointer, i.e. pushed right after DS.
+          let far_ds = call_args.get(i+1) == Some(&ir::Ref::Init(crate::asm::instr::Reg::DS));
+          let literal = self.ir.const_lookup(*a).filter(|_| far_ds).and_then(|k| self.strings.get(&(k as u16)));
+          if let Some(literal) = literal {
+            arg = Expr::Commented(Box::new(arg), literal.clone());
+          }
+          args.push(arg);
         }
         Expr::Call(Box::new(Expr::Name(funcname)), args)
       }
@@ -1033,7 +1046,7 @@ impl<'a> Builder<'a> {
 }
 
 impl Function {
-  pub fn from_ir(cfg: &Config, name: &str, ret: Option<Type>, ir: &ir::IR, ctrlflow: &ControlFlow) -> Self {
-    Builder::new(cfg, ir, ctrlflow).build(name, ret)
+  pub fn from_ir(cfg: &Config, strings: &BTreeMap<u16, String>, name: &str, ret: Option<Type>, ir: &ir::IR, ctrlflow: &ControlFlow) -> Self {
+    Builder::new(cfg, strings, ir, ctrlflow).build(name, ret)
   }
 }
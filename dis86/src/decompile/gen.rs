@@ -224,6 +224,10 @@ impl<'a> Gen<'a> {
         self.text(&format!("({})", typ))?;
         self.expr(expr, level+1, imp)?;
       }
+      Expr::Commented(expr, comment) => {
+        self.expr(expr, level, imp)?;
+        self.text(&format!(" /* {} */", comment))?;
+      }
       Expr::Deref(expr) => {
         self.text("*")?;
         self.expr(expr, level+1, imp)?;
@@ -33,6 +33,14 @@ fn reg_name(r: instr::Reg) -> &'static str {
     instr::Reg::DS => "ds",
     instr::Reg::IP => "ip",
     instr::Reg::FLAGS => "flags",
+    instr::Reg::ST0 => "st0",
+    instr::Reg::ST1 => "st1",
+    instr::Reg::ST2 => "st2",
+    instr::Reg::ST3 => "st3",
+    instr::Reg::ST4 => "st4",
+    instr::Reg::ST5 => "st5",
+    instr::Reg::ST6 => "st6",
+    instr::Reg::ST7 => "st7",
   }
 }
 
@@ -18,6 +18,7 @@ pub enum Opcode {
   UMul,  // unsigned
   IDiv,  // signed
   UDiv,  // unsigned
+  IRem,  // signed
 
   Neg,
   Not,  // bitwise
@@ -111,6 +112,7 @@ impl Opcode {
       Opcode::UMul        => "umul",
       Opcode::IDiv        => "idiv",
       Opcode::UDiv        => "udiv",
+      Opcode::IRem        => "irem",
       Opcode::Neg         => "neg",
       Opcode::Not         => "not",
       Opcode::SignExtTo16 => "signext16",
@@ -1,6 +1,7 @@
 use crate::decompile::sym;
 use crate::decompile::ir::*;
 use crate::asm::instr;
+use crate::analyze::jump_table;
 use crate::binary;
 use crate::segoff::{Seg, Off, SegOff};
 use crate::config::{self, Config};
@@ -123,9 +124,13 @@ impl<'a> IRBuilder<'a> {
       off: Off(off),
     };
 
-    // Try to find a matching text segment region in config
-    let region = self.cfg.text_region_lookup(addr, ins.addr).unwrap_or_else(
-      || panic!("Failed to find text section region ({}) for: '{}' at '{}'", addr, instr_str(ins), ins.addr));
+    // Try to find a matching text segment region in config, otherwise recover the table from the range guard
+    let Some(region) = self.cfg.text_region_lookup(addr, ins.addr) else {
+      let idx = self.instrs.iter().position(|i| i.addr == ins.addr).unwrap();
+      let table = jump_table::recover(&self.instrs[..=idx], self.binary).unwrap_or_else(
+        |err| panic!("Failed to find text section region ({}) for: '{}' at '{}': {}", addr, instr_str(ins), ins.addr, err));
+      return Some(table.targets);
+    };
 
     // Unpack the array type
     let Type::Array(basetype, ArraySize::Known(len)) = &region.typ else {
@@ -327,6 +332,7 @@ impl IRBuilder<'_> {
       instr::Size::Size8 => (Type::U8, Opcode::Load8),
       instr::Size::Size16 => (Type::U16, Opcode::Load16),
       instr::Size::Size32 => (Type::U32, Opcode::Load32),
+      _ => panic!("Unsupported load size: {:?}", mem.sz),
     };
     self.append_instr_with_attrs(typ, Attribute::MAY_ESCAPE, opcode, vec![seg, addr])
   }
@@ -646,7 +652,10 @@ impl IRBuilder<'_> {
     };
     let seg = if self.overlay {
       // Far calls from overlays need to be remapped
-      self.binary.remap_to_segment(far.seg)
+      self.binary.remap_to_segment(far.seg).unwrap_or_else(|| {
+        eprintln!("WARN: No segment for 0x{:04x} in '{}', using it as is", far.seg, instr_str(ins));
+        Seg::Normal(far.seg)
+      })
     } else {
       // Otherwise: Normal
       Seg::Normal(far.seg)
@@ -719,6 +728,7 @@ impl IRBuilder<'_> {
   fn append_asm_instr(&mut self, ins: &instr::Instr) {
     //println!("## {}", intel_syntax::format(ins, &[], false).unwrap());
     assert!(ins.rep.is_none());
+    // LOCK is dropped: atomicity has no meaning in the single-threaded code we generate
 
     let special = self.special.take();
 
@@ -987,6 +997,11 @@ impl IRBuilder<'_> {
         let cs_pushed = matches!(special, Some(SpecialState::PushCS));
         self.process_calln(ins, cs_pushed);
       }
+      // Microsoft LINK overlay calls: "int 3Fh" with the destination inline
+      instr::Opcode::OP_INT if self.binary.ms_overlay_call(ins.addr).is_some() => {
+        let dest = self.binary.ms_overlay_call(ins.addr).unwrap();
+        self.process_call_segoff(dest, config::CallMode::Far, ins);
+      }
       instr::Opcode::OP_INT => {
         let num = self.append_asm_src_operand(&ins.operands[0]);
         self.append_instr(Type::Void, Opcode::Int, vec![num]);
@@ -1040,6 +1055,17 @@ impl IRBuilder<'_> {
         self.append_asm_dst_operand(&ins.operands[0], upper_out);
         self.append_asm_dst_operand(&ins.operands[1], lower_out);
       }
+      instr::Opcode::OP_IDIV => {
+        let upper_in = self.append_asm_src_operand(&ins.operands[0]);
+        let lower_in = self.append_asm_src_operand(&ins.operands[1]);
+        let divisor = self.append_asm_src_operand(&ins.operands[2]);
+        let dividend = self.append_instr(Type::U32, Opcode::Make32, vec![upper_in, lower_in]);
+        let quotient = self.append_instr(Type::U32, Opcode::IDiv, vec![dividend, divisor]);
+        let remainder = self.append_instr(Type::U16, Opcode::IRem, vec![dividend, divisor]);
+        let quotient_out = self.append_instr(Type::U16, Opcode::Lower16, vec![quotient]);
+        self.append_asm_dst_operand(&ins.operands[0], remainder);
+        self.append_asm_dst_operand(&ins.operands[1], quotient_out);
+      }
       instr::Opcode::OP_STOS => {
         let src = self.append_asm_src_operand(&ins.operands[1]);
         self.append_asm_dst_operand(&ins.operands[0], src);
@@ -420,6 +420,7 @@ fn allow_cse(opcode: Opcode) -> bool {
     Opcode::UMul => true,
     Opcode::IDiv => true,
     Opcode::UDiv => true,
+    Opcode::IRem => true,
     Opcode::Neg => true,
     Opcode::SignExtTo32 => true,
     Opcode::Lower16 => true,
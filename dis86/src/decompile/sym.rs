@@ -1,5 +1,5 @@
 use crate::asm::instr;
-use crate::config::Config;
+use crate::config::{self, Config};
 use crate::decompile::ir::*;
 use crate::access;
 use crate::types::{Type, TypeDatabase};
@@ -402,6 +402,19 @@ pub fn symbolize_globals(ir: &mut IR, cfg: &Config) {
   }
 }
 
+// Names (and types, where the size agrees) from the config for the stack variables found
+pub fn name_locals(ir: &mut IR, locals: &[config::Local]) {
+  let frame_offset = 2; // the config's are BP relative, after "push bp"
+  for l in locals {
+    let table = if l.off > 0 { &mut ir.symbols.params } else { &mut ir.symbols.locals };
+    let Some(sym) = table.symbols.iter_mut().find(|s| s.off + frame_offset == l.off) else { continue };
+    sym.name = l.name.clone();
+    if let Some(typ) = l.typ.as_ref().filter(|t| t.size_in_bytes() == Some(sym.size as usize)) {
+      sym.typ = typ.clone();
+    }
+  }
+}
+
 pub fn symbolize(ir: &mut IR, cfg: &Config) {
   symbolize_stack(ir);
   symbolize_globals(ir, cfg);
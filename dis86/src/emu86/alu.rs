@@ -115,18 +115,44 @@ fn update_flags_shr(f: &mut Flags, a: u16, n: u8, r: u16, sign_mask: u16, value_
 }
 
 // Returns (quotient, remainder, flags)
-pub fn divmod(a: Value, b: Value, mut f: Flags) -> (Value, Value, Flags) {
+pub fn divmod(a: Value, b: Value, f: Flags) -> (Value, Value, Flags) {
   let Value::U32(a) = a else { panic!("expected u32 for lhs") };
   let Value::U16(b) = b else { panic!("expected u16 for rhs") };
   let b = b as u32;
 
-  let quotient = a / b;
-  let remainder = a % b;
+  let (Some(quotient), Some(remainder)) = (a.checked_div(b), a.checked_rem(b)) else {
+    panic!("Divide Error");
+  };
 
   if quotient > 0xffff {
     panic!("Divide Error"); // What should be done about this??
   }
 
+  let f = divmod_flags(quotient as u16, remainder as u16, f);
+  (Value::U16(quotient as u16), Value::U16(remainder as u16), f)
+}
+
+// Returns (quotient, remainder, flags)
+pub fn idivmod(a: Value, b: Value, f: Flags) -> (Value, Value, Flags) {
+  let Value::U32(a) = a else { panic!("expected u32 for lhs") };
+  let Value::U16(b) = b else { panic!("expected u16 for rhs") };
+  let a = a as i32;
+  let b = b as i16 as i32;
+
+  // Dividing by zero and 0x80000000 / -1 are divide errors too
+  let (Some(quotient), Some(remainder)) = (a.checked_div(b), a.checked_rem(b)) else {
+    panic!("Divide Error");
+  };
+
+  if quotient > i16::MAX as i32 || quotient < i16::MIN as i32 {
+    panic!("Divide Error"); // What should be done about this??
+  }
+
+  let f = divmod_flags(quotient as u16, remainder as u16, f);
+  (Value::U16(quotient as u16), Value::U16(remainder as u16), f)
+}
+
+fn divmod_flags(quotient: u16, remainder: u16, mut f: Flags) -> Flags {
   // Mirroring the behaviour of dosbox-x
   f.set(FLAG_CF, (remainder&3) >= 1 && (remainder&3) <= 2);  // Set iff low 2 bits of remainder are 01 or 10 )
   f.set(FLAG_ZF, remainder == 0 && (quotient&1) != 0);       // Set iff remainder is zero AND quotient is odd
@@ -139,7 +165,7 @@ pub fn divmod(a: Value, b: Value, mut f: Flags) -> (Value, Value, Flags) {
   let quo_parity = quotient.count_ones() % 2 != 0;
   f.set(FLAG_PF, rem_parity == quo_parity);
 
-  (Value::U16(quotient as u16), Value::U16(remainder as u16), f)
+  f
 }
 
 pub fn multiply(op: MultiplyOp, a: Value, b: Value, mut f: Flags) -> (Value, Flags) {
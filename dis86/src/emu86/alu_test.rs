@@ -761,3 +761,36 @@ fn sbb16_zero_minus_one_no_borrow_in() {
   // 0x0000 - 0x0001 - 0 = 0xFFFF: CF=1, SF=1, low byte 0xFF = even parity, AF=1
   check_sbb16!(0x0000, 0x0001, cf_in=0, cf=1, zf=0, sf=1, of=0, pf=1, af=1);
 }
+
+// --- idivmod ---
+
+#[test]
+fn idivmod_signs() {
+  // -7 / 2 = -3 rem -1 (truncates towards zero)
+  let (quo, rem, _f) = alu::idivmod(Value::U32((-7i32) as u32), Value::U16(2), Flags(0));
+  assert_eq!(quo, Value::U16((-3i16) as u16));
+  assert_eq!(rem, Value::U16((-1i16) as u16));
+
+  // 1000 / -3 = -333 rem 1
+  let (quo, rem, _f) = alu::idivmod(Value::U32(1000), Value::U16((-3i16) as u16), Flags(0));
+  assert_eq!(quo, Value::U16((-333i16) as u16));
+  assert_eq!(rem, Value::U16(1));
+}
+
+#[test]
+#[should_panic(expected = "Divide Error")]
+fn idivmod_by_zero() {
+  alu::idivmod(Value::U32(1), Value::U16(0), Flags(0));
+}
+
+#[test]
+#[should_panic(expected = "Divide Error")]
+fn idivmod_overflow() {
+  alu::idivmod(Value::U32(0x80000000), Value::U16(0xffff), Flags(0));
+}
+
+#[test]
+#[should_panic(expected = "Divide Error")]
+fn divmod_by_zero() {
+  alu::divmod(Value::U32(1), Value::U16(0), Flags(0));
+}
@@ -69,6 +69,11 @@ impl Dos {
 }
 
 impl Machine {
+  // Legacy terminate: where COM programs end up when they return from their entry point
+  pub fn dos_interrupt_0x20(&mut self) {
+    panic!("Exited with 0");
+  }
+
   pub fn dos_interrupt_0x21(&mut self) {
     let func = self.reg_read_u8(AH);
     match func {
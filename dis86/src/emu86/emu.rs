@@ -1,4 +1,5 @@
 use crate::binfmt::mz;
+use crate::binfmt::com::Com;
 use super::machine::Machine;
 use super::cpu::{Cpu, Register};
 use super::cpu_flags::Flag;
@@ -11,7 +12,7 @@ pub struct Emulator {
   #[allow(dead_code)]
   exe_path: String,
   #[allow(dead_code)]
-  exe: mz::Exe,
+  exe: Option<mz::Exe>, // None for COM files
   pub machine: Machine,
   app: sdl::App,
   step_count: u64,
@@ -23,14 +24,19 @@ impl Emulator {
     let Ok(data) = std::fs::read(exe_path) else {
       panic!("Failed to read file: {}", exe_path);
     };
-    let exe = mz::Exe::decode(&data).unwrap();
+    // Like DOS, go by the magic rather than the extension
+    let is_exe = data.starts_with(b"MZ") || data.starts_with(b"ZM");
+    let exe = if is_exe { Some(mz::Exe::decode(&data).unwrap()) } else { None };
 
     // As the filesystem rootdir, use the root dir of the exe
     let root_dir = Path::new(exe_path).parent().unwrap().to_str().unwrap();
 
     // Init the machine and load up the program
     let mut machine = Machine::new(Some(root_dir));
-    machine.load_exe(&exe)?;
+    match &exe {
+      Some(exe) => machine.load_exe(exe)?,
+      None => machine.load_com(&Com::decode(&data)?)?,
+    }
 
     let app = sdl::App::new();
 
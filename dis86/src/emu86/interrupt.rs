@@ -47,6 +47,7 @@ impl Machine {
     match num {
       0x1a => self.bios_time_of_day(),
       0x10 => self.video_interrupt_0x10(),
+      0x20 => self.dos_interrupt_0x20(),
       0x21 => self.dos_interrupt_0x21(),
       0x33 => self.mouse_interrupt_0x33(),
       _ => panic!("unimplemnted interrupt"),
@@ -1,6 +1,7 @@
 use super::machine::*;
 use super::dos;
 use crate::binfmt::mz;
+use crate::binfmt::com;
 
 impl Machine {
   pub fn code_load_seg(&self) -> Seg {
@@ -10,26 +11,25 @@ impl Machine {
   }
 
   pub fn load_exe(&mut self, exe: &mz::Exe) -> Result<(), String> {
-    let code_seg = self.code_load_seg();
+    self.load_exe_at(exe, PSP_SEGMENT)
+  }
+
+  // Like DOS would with the PSP at this segment and the image right after it
+  pub fn load_exe_at(&mut self, exe: &mz::Exe, psp_seg: Seg) -> Result<(), String> {
+    let code_seg = Seg::Normal(psp_seg.unwrap_normal() + 0x10);
     let code_seg_u16 = code_seg.unwrap_normal();
 
     // Configure the PSP
-    let psp = self.mem.program_segment_prefix_mut();
+    let psp = self.mem.program_segment_prefix_at_mut(psp_seg);
     // NOTE: JUST TO MATCH DOSBOX
     psp.mem_top = dos::MEM_TOP;
     psp.env_seg = dos::ENV_SEG;
     psp.cmd_tail[0] = 0x0d;
     // ... missing fields ...
 
-    // Determine image region to copy
-    let image_start  = exe.hdr.cparhdr as usize * 16;
-    let image_end    = exe.hdr.cp as usize * 512;
-    let image_length = image_end - image_start;
-    let image        = &exe.rawdata[image_start..image_end];
-
-    // println!("image_start:   0x{:x}", image_start);
-    // println!("image_end:     0x{:x}", image_end);
-    // println!("image_length:  0x{:x}", image_length);
+    // Determine image region to copy (the last page may be partial)
+    let image        = exe.exe_data();
+    let image_length = image.len();
 
     // Copy into memory
     let mem_start = code_seg.abs_normal();
@@ -52,8 +52,40 @@ impl Machine {
     self.reg_set(SP, exe.hdr.sp as u16);
 
     // Set up DS and ES to point at the PSP
-    self.reg_set(DS, PSP_SEGMENT.unwrap_normal());
-    self.reg_set(ES, PSP_SEGMENT.unwrap_normal());
+    self.reg_set(DS, psp_seg.unwrap_normal());
+    self.reg_set(ES, psp_seg.unwrap_normal());
+
+    // IF flag should be set
+    self.reg_set(FLAGS, 1<<9); // IF
+
+    Ok(())
+  }
+
+  pub fn load_com(&mut self, com: &com::Com) -> Result<(), String> {
+    let psp_seg = PSP_SEGMENT.unwrap_normal();
+
+    // Configure the PSP
+    let psp = self.mem.program_segment_prefix_mut();
+    // NOTE: JUST TO MATCH DOSBOX
+    psp.int20 = [0xcd, 0x20];
+    psp.mem_top = dos::MEM_TOP;
+    psp.env_seg = dos::ENV_SEG;
+    psp.cmd_tail[0] = 0x0d;
+
+    // Copy into memory right after the PSP
+    let mem_start = SegOff::new(psp_seg, com::LOAD_OFF).abs_normal();
+    let mem_end   = mem_start + com.data.len();
+    self.mem.0[mem_start..mem_end].copy_from_slice(&com.data);
+
+    // Everything points at the PSP
+    for sreg in [CS, DS, ES, SS] {
+      self.reg_set(sreg, psp_seg);
+    }
+    self.reg_set(IP, com::LOAD_OFF);
+
+    // A near return from the top level lands on the "int 20h" at PSP:0000
+    self.reg_set(SP, 0xfffe);
+    self.mem.write_u16(SegOff::new(psp_seg, 0xfffe), 0);
 
     // IF flag should be set
     self.reg_set(FLAGS, 1<<9); // IF
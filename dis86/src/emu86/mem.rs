@@ -63,7 +63,11 @@ impl Memory {
   }
 
   pub fn program_segment_prefix_mut(&mut self) -> &mut ProgramSegmentPrefix {
-    let off = PSP_SEGMENT.abs_normal();
+    self.program_segment_prefix_at_mut(PSP_SEGMENT)
+  }
+
+  pub fn program_segment_prefix_at_mut(&mut self, seg: Seg) -> &mut ProgramSegmentPrefix {
+    let off = seg.abs_normal();
     let slice = &mut self.0[off..off+std::mem::size_of::<ProgramSegmentPrefix>()];
     unsafe { &mut *(slice.as_mut_ptr() as *mut ProgramSegmentPrefix) }
   }
@@ -34,6 +34,7 @@ mod sdl;
 pub mod emu;
 
 pub use emu::run;
+pub mod stub;
 pub mod validator;
 
 // Tests
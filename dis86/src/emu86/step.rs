@@ -56,6 +56,7 @@ impl Machine {
       instr::Size::Size8  => Value::U8(self.mem.read_u8(addr)),
       instr::Size::Size16 => Value::U16(self.mem.read_u16(addr)),
       instr::Size::Size32 => Value::U32(self.mem.read_u32(addr)),
+      _ => panic!("unsupported size"),
     }
   }
 
@@ -234,6 +235,15 @@ impl Machine {
     let instr_addr = SegOff::new_normal(cs, ip);
     let instr = decode_instr(&self.mem, instr_addr)?;
 
+    // 8087 emulator escapes are still just software interrupts to the cpu
+    if let Some(num) = instr.fpu_emu {
+      let int_len = instr.prefixes.len() as u16 + 2;
+      self.reg_set(IP, instr.addr.off.0.wrapping_add(int_len));
+      self.interrupt(num);
+      self.exec_count += 1;
+      return Ok(());
+    }
+
     // Update IP
     self.reg_set(IP, instr.end_addr().off.0);
 
@@ -247,6 +257,9 @@ impl Machine {
 
     if instr.rep.is_some() { panic!("REP prefix is not yet implemented"); }
 
+    // LOCK only asserts the bus lock signal and there is no other bus master to exclude, so it
+    // is accepted on any instruction and otherwise ignored
+
     let f = self.flag_read_all();
     match instr.opcode {
       Opcode::OP_MOV   => self.operand_write(&instr, 0, self.operand_read(&instr, 1)),
@@ -484,6 +497,19 @@ impl Machine {
         self.operand_write(&instr, 0, remainder);
       }
 
+      Opcode::OP_IDIV => {
+        let high = self.operand_read(&instr, 0);
+        let low = self.operand_read(&instr, 1);
+        let lhs = Value::join(high, low);
+        let rhs = self.operand_read(&instr, 2);
+
+        let (quotient, remainder, flags) = alu::idivmod(lhs, rhs, self.flag_read_all());
+        self.flag_write_all(flags);
+
+        self.operand_write(&instr, 1, quotient);
+        self.operand_write(&instr, 0, remainder);
+      }
+
       Opcode::OP_XCHG => {
         let lhs = self.operand_read(&instr, 0);
         let rhs = self.operand_read(&instr, 1);
@@ -553,5 +579,6 @@ fn convert_reg(r: instr::Reg) -> Register {
     instr::Reg::CL    => CL,
     instr::Reg::DH    => DH,
     instr::Reg::DL    => DL,
+    _ => panic!("unsupported register: {:?}", r),
   }
 }
@@ -0,0 +1,73 @@
+use super::machine::*;
+use super::dos;
+use crate::binfmt::mz;
+use std::panic::{self, AssertUnwindSafe};
+
+// Runs a self-extracting exe (a packer's decompressor stub) until it jumps to the program it unpacked
+
+// Compared against the packed image to tell the stub's own (moved) code from the program's. Long
+// enough not to match by chance, and longer than any literal run in a bit-packed stream.
+const SIGNATURE_LEN: usize = 32;
+
+pub struct Handover {
+  pub load_seg: u16,  // where the image was loaded (the PSP is just below)
+  pub before: Vec<u8>, // memory from the load segment up to the top of memory, as loaded
+  pub after: Vec<u8>,  // ... and when the stub jumped to the program
+  pub stub_addr: usize, // lowest far jump target in the stub's own code, relative to the load segment
+  pub cs: u16,
+  pub ip: u16,
+  pub ss: u16,
+  pub sp: u16,
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+  haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+pub fn run_until_handover(exe: &mz::Exe, psp_seg: u16, max_steps: u64) -> Result<Handover, String> {
+  let mut m = Machine::new(None);
+  m.load_exe_at(exe, Seg::Normal(psp_seg))?;
+
+  let load_seg = psp_seg + 0x10;
+  let mem_range = SegOff::new(load_seg, 0).abs_normal()..SegOff::new(dos::MEM_TOP, 0).abs_normal();
+  let before = m.mem.0[mem_range.clone()].to_vec();
+  let packed = exe.exe_data();
+
+  let mut stub_addr = usize::MAX;
+  let result = panic::catch_unwind(AssertUnwindSafe(|| {
+    let mut cs = m.reg_read_u16(CS);
+    for _ in 0..max_steps {
+      m.step()?;
+      if m.halted() { return Err("Exited before reaching the program".to_string()); }
+      if m.reg_read_u16(CS) == cs { continue; }
+      cs = m.reg_read_u16(CS);
+
+      // A far jump: either into a copy of the stub or to the program's entry point
+      let addr = m.instr_addr().abs_normal();
+      let target = &m.mem.0[addr..addr+SIGNATURE_LEN];
+      if !contains(packed, target) { return Ok(()); }
+      stub_addr = stub_addr.min(addr.wrapping_sub(mem_range.start));
+    }
+    Err(format!("Still unpacking after {} instructions", max_steps))
+  }));
+  match result {
+    Ok(res) => res?,
+    Err(err) => {
+      let msg = err.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| err.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown".to_string());
+      return Err(format!("Emulation failed: {}", msg));
+    }
+  }
+
+  Ok(Handover {
+    load_seg,
+    before,
+    after: m.mem.0[mem_range].to_vec(),
+    stub_addr,
+    cs: m.reg_read_u16(CS).wrapping_sub(load_seg),
+    ip: m.reg_read_u16(IP),
+    ss: m.reg_read_u16(SS).wrapping_sub(load_seg),
+    sp: m.reg_read_u16(SP),
+  })
+}
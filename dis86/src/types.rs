@@ -128,6 +128,16 @@ impl TypeDatabase {
     self.structs.get(r.idx)
   }
 
+  // The way parse_type reads it back: structs by name
+  pub fn type_name(&self, typ: &Type) -> String {
+    match typ {
+      Type::Struct(r) => self.lookup_struct(*r).map(|s| s.name.clone()).unwrap_or_else(|| typ.to_string()),
+      Type::Array(base, ArraySize::Known(n)) => format!("{}[{}]", self.type_name(base), n),
+      Type::Array(base, ArraySize::Unknown) => format!("{}[]", self.type_name(base)),
+      _ => typ.to_string(),
+    }
+  }
+
   fn parse_array_type(&self, s: &str) -> Result<Type, String> {
     let array_start = s.find('[')
       .ok_or_else(|| format!("No opening an array bracket"))?;
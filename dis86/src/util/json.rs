@@ -0,0 +1,26 @@
+// Quoted and escaped JSON string
+pub fn json_str(s: &str) -> String {
+  let mut out = String::from("\"");
+  for c in s.chars() {
+    match c {
+      '"'  => out += "\\\"",
+      '\\' => out += "\\\\",
+      '\n' => out += "\\n",
+      c if (c as u32) < 0x20 => out += &format!("\\u{:04x}", c as u32),
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn escape() {
+    assert_eq!(json_str("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+    assert_eq!(json_str("\t"), "\"\\u0009\"");
+  }
+}
@@ -2,3 +2,4 @@ pub mod arrayvec;
 pub mod range_set;
 pub mod hexdump;
 pub mod parse;
+pub mod json;
@@ -39,6 +39,10 @@ impl RangeSet {
     ranges.splice(lo..hi, [merged]);
   }
 
+  pub fn iter(&self) -> std::slice::Iter<'_, Range> {
+    self.0.iter()
+  }
+
   pub fn span(&self) -> Option<Range> {
     let ranges = &self.0;
     Some(Range {